@@ -0,0 +1,26 @@
+// Follow the HeartIO WebSocket feed and show the latest heart rate
+const overlay = document.getElementById("overlay");
+const bpmLabel = document.getElementById("bpm");
+const RECONNECT_DELAY_MS = 3000;
+
+function connect() {
+  const socket = new WebSocket(`ws://${location.host}/ws`);
+
+  socket.onmessage = (event) => {
+    const { bpm } = JSON.parse(event.data);
+    if (!Number.isFinite(bpm) || bpm <= 0) {
+      return;
+    }
+    bpmLabel.textContent = bpm;
+    // One pulse per heartbeat
+    overlay.style.setProperty("--beat", `${60 / bpm}s`);
+    overlay.classList.remove("offline");
+  };
+
+  socket.onclose = () => {
+    overlay.classList.add("offline");
+    setTimeout(connect, RECONNECT_DELAY_MS);
+  };
+}
+
+connect();
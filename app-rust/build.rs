@@ -0,0 +1,39 @@
+// Build script embedding build info for the About dialog
+use vergen::EmitBuilder;
+
+/// Dependencies whose resolved versions are shown in the About dialog
+const REPORTED_DEPENDENCIES: [&str; 5] = ["btleplug", "egui", "sqlx", "rosc", "axum"];
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    EmitBuilder::builder()
+        .build_timestamp()
+        .cargo_target_triple()
+        .rustc_semver()
+        .git_sha(true)
+        .emit()?;
+
+    emit_dependency_versions();
+    Ok(())
+}
+
+/// Expose resolved dependency versions from Cargo.lock as `HEARTIO_DEP_<NAME>`
+fn emit_dependency_versions() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    let lock = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+
+    for dependency in REPORTED_DEPENDENCIES {
+        let name_line = format!("name = \"{}\"", dependency);
+        let version = lock
+            .lines()
+            .skip_while(|line| *line != name_line)
+            .nth(1)
+            .and_then(|line| line.strip_prefix("version = \""))
+            .and_then(|line| line.strip_suffix('"'))
+            .unwrap_or("unknown");
+        println!(
+            "cargo:rustc-env=HEARTIO_DEP_{}={}",
+            dependency.to_uppercase(),
+            version
+        );
+    }
+}
@@ -0,0 +1,31 @@
+// End-to-end OSC test: encode a chatbox message and decode it from a loopback socket
+#[allow(dead_code)]
+#[path = "../src/osc.rs"]
+mod osc;
+
+use osc::OscClient;
+use rosc::{OscPacket, OscType};
+use std::net::UdpSocket;
+use std::time::Duration;
+
+#[tokio::test]
+async fn chatbox_message_survives_udp_round_trip() {
+    let server = UdpSocket::bind("0.0.0.0:19000").expect("bind OSC test port");
+    server
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+
+    let osc_client = OscClient::new("127.0.0.1".to_string(), 19000, false, None).unwrap();
+    osc_client.send_message("test 💓 75").await.unwrap();
+
+    let mut buf = [0u8; 1536];
+    let (size, _) = server.recv_from(&mut buf).expect("receive OSC datagram");
+    let (rest, packet) = rosc::decoder::decode_udp(&buf[..size]).expect("decode OSC packet");
+    assert!(rest.is_empty(), "trailing bytes after OSC packet");
+
+    let OscPacket::Message(msg) = packet else {
+        panic!("expected an OSC message, got {:?}", packet);
+    };
+    assert_eq!(msg.addr, "/chatbox/input");
+    assert_eq!(msg.args.first(), Some(&OscType::String("test 💓 75".to_string())));
+}
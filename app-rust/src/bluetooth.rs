@@ -1,20 +1,47 @@
 // Bluetooth Low Energy heart rate monitoring for HeartIO
 use anyhow::{Context, Result};
-use btleplug::api::{Central, Manager as _, Peripheral as _, ScanFilter};
+use async_trait::async_trait;
+use btleplug::api::bleuuid::uuid_from_u16;
+use btleplug::api::{
+    Central, CentralState, Characteristic, Manager as _, Peripheral as _, ScanFilter,
+    ValueNotification, WriteType,
+};
 use btleplug::platform::{Adapter, Manager, Peripheral};
-use futures::stream::StreamExt;
-use std::time::Duration;
-use tokio::time::sleep;
+use futures::stream::{Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::time::{sleep, timeout};
 use uuid::Uuid;
 
+use crate::config::{BpmRange, MonitorMode};
+use crate::source::{HeartRateSample, HeartRateSource};
+
 // Heart Rate Service UUID definitions
 // Short form (16-bit): 0x180D
 const HEART_RATE_SERVICE_UUID_SHORT: u16 = 0x180D;
+// Full 128-bit form on the Bluetooth base UUID, used for scan filters
+const HEART_RATE_SERVICE_UUID_FULL: Uuid = uuid_from_u16(HEART_RATE_SERVICE_UUID_SHORT);
 
 // Heart Rate Measurement Characteristic UUID definitions
 // Short form (16-bit): 0x2A37
 const HEART_RATE_MEASUREMENT_CHAR_UUID_SHORT: u16 = 0x2A37;
 
+// Body Sensor Location Characteristic UUID definitions
+// Short form (16-bit): 0x2A38
+const BODY_SENSOR_LOCATION_CHAR_UUID_SHORT: u16 = 0x2A38;
+
+// Heart Rate Control Point Characteristic, writing 0x01 resets the energy expended
+const HEART_RATE_CONTROL_POINT_CHAR_UUID_SHORT: u16 = 0x2A39;
+const HEART_RATE_CONTROL_POINT_RESET_ENERGY: u8 = 0x01;
+
+// Client Characteristic Configuration Descriptor, 0x0001 enables notifications
+const CCCD_UUID_SHORT: u16 = 0x2902;
+const CCCD_ENABLE_NOTIFICATIONS: [u8; 2] = [0x01, 0x00];
+
+/// How long to wait for the first notification before writing the CCCD directly
+const NOTIFICATION_START_TIMEOUT: Duration = Duration::from_secs(5);
+
 // Helper function to check if a UUID represents the heart rate service
 fn is_heart_rate_service_uuid(uuid: &Uuid) -> bool {
     let uuid_bytes = uuid.as_u128();
@@ -32,49 +59,449 @@ fn is_heart_rate_measurement_char_uuid(uuid: &Uuid) -> bool {
     char_id == HEART_RATE_MEASUREMENT_CHAR_UUID_SHORT
 }
 
+// Helper function to check if a UUID represents the body sensor location characteristic
+fn is_body_sensor_location_char_uuid(uuid: &Uuid) -> bool {
+    ((uuid.as_u128() >> 96) & 0xFFFF) as u16 == BODY_SENSOR_LOCATION_CHAR_UUID_SHORT
+}
+
+// Helper function to check if a UUID represents the heart rate control point characteristic
+fn is_heart_rate_control_point_uuid(uuid: &Uuid) -> bool {
+    ((uuid.as_u128() >> 96) & 0xFFFF) as u16 == HEART_RATE_CONTROL_POINT_CHAR_UUID_SHORT
+}
+
+/// Name of a Body Sensor Location value as defined by the Heart Rate Service
+fn body_sensor_location_name(value: u8) -> &'static str {
+    match value {
+        1 => "Chest",
+        2 => "Wrist",
+        3 => "Finger",
+        4 => "Hand",
+        5 => "Ear Lobe",
+        6 => "Foot",
+        _ => "Other",
+    }
+}
+
+// Helper function to check if a UUID represents the client characteristic configuration
+fn is_cccd_uuid(uuid: &Uuid) -> bool {
+    ((uuid.as_u128() >> 96) & 0xFFFF) as u16 == CCCD_UUID_SHORT
+}
+
+/// Wait for the first heart rate notification, `None` if none arrives in time
+async fn first_notification(stream: &mut NotificationStream) -> Option<ValueNotification> {
+    let first = async {
+        while let Some(data) = stream.next().await {
+            if is_heart_rate_measurement_char_uuid(&data.uuid) {
+                return Some(data);
+            }
+        }
+        None
+    };
+    timeout(NOTIFICATION_START_TIMEOUT, first).await.ok().flatten()
+}
+
+/// Bluetooth permission macOS has granted to the app
+#[cfg(target_os = "macos")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BluetoothAuthStatus {
+    Authorized,
+    Denied,
+    NotDetermined,
+    Restricted,
+}
+
+/// Ask CoreBluetooth whether the app may use Bluetooth, macOS 10.15+
+#[cfg(target_os = "macos")]
+pub fn check_macos_bluetooth_authorization() -> BluetoothAuthStatus {
+    use objc2_core_bluetooth::{CBManager, CBManagerAuthorization};
+
+    // SAFETY: a class property without arguments, available on every supported macOS
+    let authorization = unsafe { CBManager::authorization_class() };
+    match authorization {
+        CBManagerAuthorization::AllowedAlways => BluetoothAuthStatus::Authorized,
+        CBManagerAuthorization::Denied => BluetoothAuthStatus::Denied,
+        CBManagerAuthorization::Restricted => BluetoothAuthStatus::Restricted,
+        _ => BluetoothAuthStatus::NotDetermined,
+    }
+}
+
+/// Fail with instructions instead of an opaque btleplug error when Bluetooth access is refused
+pub fn ensure_bluetooth_authorized() -> Result<()> {
+    #[cfg(target_os = "macos")]
+    match check_macos_bluetooth_authorization() {
+        BluetoothAuthStatus::Authorized => {}
+        // macOS shows the permission prompt on first use, so let the scan go ahead
+        BluetoothAuthStatus::NotDetermined => {
+            tracing::info!("macOS will ask for Bluetooth permission, please click Allow");
+        }
+        BluetoothAuthStatus::Denied => anyhow::bail!(
+            "Bluetooth permission denied. Open System Settings > Privacy & Security > Bluetooth, \
+             allow HeartIO (or the terminal running it), then restart HeartIO."
+        ),
+        BluetoothAuthStatus::Restricted => anyhow::bail!(
+            "Bluetooth access is restricted on this Mac, e.g. by a device management profile. \
+             Ask your administrator to allow Bluetooth for HeartIO."
+        ),
+    }
+
+    Ok(())
+}
+
+/// Bluetooth problems the user has to fix, as opposed to a device that is simply out of range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ScanError {
+    #[error("Bluetooth permission denied. {}", permission_hint())]
+    PermissionDenied,
+    #[error("Bluetooth is turned off. Turn on Bluetooth, then try again.")]
+    PoweredOff,
+}
+
+impl ScanError {
+    /// Short instruction for the status bar
+    pub fn guidance(&self) -> &'static str {
+        match self {
+            ScanError::PermissionDenied => "Grant Bluetooth permission",
+            ScanError::PoweredOff => "Turn on Bluetooth",
+        }
+    }
+}
+
+/// Where Bluetooth access is granted on this platform
+fn permission_hint() -> &'static str {
+    if cfg!(target_os = "linux") {
+        "Add your user to the bluetooth group or allow HeartIO in the BlueZ D-Bus policy, \
+         then log in again."
+    } else if cfg!(target_os = "macos") {
+        "Open System Settings > Privacy & Security > Bluetooth, allow HeartIO (or the terminal \
+         running it), then restart HeartIO."
+    } else {
+        "Allow HeartIO to use Bluetooth in the system privacy settings, then restart HeartIO."
+    }
+}
+
+/// Recognise permission and power problems, BlueZ only reports them in the D-Bus error text
+pub fn classify_scan_error(error: &btleplug::Error) -> Option<ScanError> {
+    if matches!(error, btleplug::Error::PermissionDenied) {
+        return Some(ScanError::PermissionDenied);
+    }
+    let message = error.to_string().to_lowercase();
+    let mentions = |patterns: &[&str]| patterns.iter().any(|pattern| message.contains(pattern));
+    if mentions(&["accessdenied", "access denied", "notauthorized", "not authorized"]) {
+        Some(ScanError::PermissionDenied)
+    } else if mentions(&["notready", "not ready", "powered off", "poweredoff"]) {
+        Some(ScanError::PoweredOff)
+    } else {
+        None
+    }
+}
+
+/// Turn a failed scan call into a `ScanError` when the cause is known
+fn scan_failure(error: btleplug::Error, action: &'static str) -> anyhow::Error {
+    match classify_scan_error(&error) {
+        Some(scan_error) => {
+            tracing::debug!("{}: {}", action, error);
+            scan_error.into()
+        }
+        None => anyhow::Error::new(error).context(action),
+    }
+}
+
+/// Progress of an ongoing device scan
+#[derive(Debug, Clone, Copy)]
+pub struct ScanProgress {
+    pub elapsed: Duration,
+    pub devices_seen: usize,
+}
+
+/// Pick the Bluetooth adapter matching `selector`, an index or part of the adapter name.
+/// Without a selector the first adapter is used. All adapters are logged either way.
+pub async fn select_adapter(selector: Option<&str>) -> Result<Adapter> {
+    let manager = Manager::new()
+        .await
+        .context("Failed to create Bluetooth manager")?;
+
+    let adapters = manager
+        .adapters()
+        .await
+        .context("Failed to get Bluetooth adapters")?;
+
+    let mut names = Vec::with_capacity(adapters.len());
+    for (index, adapter) in adapters.iter().enumerate() {
+        let name = adapter
+            .adapter_info()
+            .await
+            .unwrap_or_else(|_| "unknown adapter".to_string());
+        tracing::info!("Bluetooth adapter {}: {}", index, name);
+        names.push(name);
+    }
+
+    let selected = match selector.map(str::trim).filter(|s| !s.is_empty()) {
+        None => 0,
+        Some(selector) => match selector.parse::<usize>() {
+            Ok(index) if index < adapters.len() => index,
+            Ok(index) => anyhow::bail!(
+                "BLE_ADAPTER index {} is out of range, {} adapters found",
+                index,
+                adapters.len()
+            ),
+            Err(_) => {
+                let wanted = selector.to_lowercase();
+                names
+                    .iter()
+                    .position(|name| name.to_lowercase().contains(&wanted))
+                    .with_context(|| format!("No Bluetooth adapter matches \"{}\"", selector))?
+            }
+        },
+    };
+
+    let adapter = adapters
+        .into_iter()
+        .nth(selected)
+        .context("No Bluetooth adapter found")?;
+    tracing::info!("Using Bluetooth adapter {}: {}", selected, names[selected]);
+    Ok(adapter)
+}
+
+/// Lifecycle of the connection to the heart rate device
+#[derive(Debug, Clone)]
+pub enum ConnectionState {
+    Disconnected,
+    Scanning { since: Instant },
+    /// Linking to the device, until its heart rate notifications are subscribed
+    Connecting { peripheral: Peripheral },
+    Connected {
+        peripheral: Peripheral,
+        subscribed_char: Characteristic,
+    },
+    Disconnecting,
+}
+
+impl ConnectionState {
+    /// State name for logs
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConnectionState::Disconnected => "Disconnected",
+            ConnectionState::Scanning { .. } => "Scanning",
+            ConnectionState::Connecting { .. } => "Connecting",
+            ConnectionState::Connected { .. } => "Connected",
+            ConnectionState::Disconnecting => "Disconnecting",
+        }
+    }
+
+    /// The device being connected to or connected
+    fn peripheral(&self) -> Option<&Peripheral> {
+        match self {
+            ConnectionState::Connecting { peripheral }
+            | ConnectionState::Connected { peripheral, .. } => Some(peripheral),
+            _ => None,
+        }
+    }
+}
+
+type ScanProgressCallback = Box<dyn Fn(ScanProgress) + Send + Sync>;
+type StateChangeCallback = Box<dyn Fn(&ConnectionState, &ConnectionState) + Send + Sync>;
+type NotificationStream = Pin<Box<dyn Stream<Item = ValueNotification> + Send>>;
+
 pub struct BluetoothHeartRateMonitor {
     adapter: Adapter,
-    device: Option<Peripheral>,
+    state: ConnectionState,
+    bpm_range: BpmRange,
+    scan_progress_callback: Option<ScanProgressCallback>,
+    state_change_callback: Option<StateChangeCallback>,
+    strict_scan: bool,
+    post_connect_delay: Duration,
+    discovery_attempts: u32,
+    discovery_retry_interval: Duration,
+    sensor_location: Option<&'static str>,
+    /// Only wrapped to make the monitor Sync, always accessed through `get_mut`
+    notifications: Mutex<Option<NotificationStream>>,
 }
 
 impl BluetoothHeartRateMonitor {
     /// Create a new Bluetooth heart rate monitor
-    pub async fn new() -> Result<Self> {
-        let manager = Manager::new()
-            .await
-            .context("Failed to create Bluetooth manager")?;
+    pub async fn new(bpm_range: BpmRange, adapter_selector: Option<&str>) -> Result<Self> {
+        ensure_bluetooth_authorized()?;
+        #[cfg(target_os = "windows")]
+        crate::system::windows::init_com();
 
-        let adapters = manager
-            .adapters()
-            .await
-            .context("Failed to get Bluetooth adapters")?;
-
-        let adapter = adapters
-            .into_iter()
-            .next()
-            .context("No Bluetooth adapter found")?;
+        let adapter = select_adapter(adapter_selector).await?;
 
         tracing::info!("Bluetooth adapter initialized");
 
         Ok(Self {
             adapter,
-            device: None,
+            state: ConnectionState::Disconnected,
+            bpm_range,
+            scan_progress_callback: None,
+            state_change_callback: None,
+            strict_scan: true,
+            post_connect_delay: Duration::from_millis(1000),
+            discovery_attempts: 3,
+            discovery_retry_interval: Duration::from_millis(2000),
+            sensor_location: None,
+            notifications: Mutex::new(None),
         })
     }
 
+    /// Register a callback that receives progress while scanning for a device
+    pub fn on_scan_progress<F>(&mut self, callback: F)
+    where
+        F: Fn(ScanProgress) + Send + Sync + 'static,
+    {
+        self.scan_progress_callback = Some(Box::new(callback));
+    }
+
+    /// Register a callback that receives the old and new state on every transition
+    pub fn on_state_change<F>(&mut self, callback: F)
+    where
+        F: Fn(&ConnectionState, &ConnectionState) + Send + Sync + 'static,
+    {
+        self.state_change_callback = Some(Box::new(callback));
+    }
+
+    /// Only scan for devices advertising the heart rate service, on by default
+    pub fn set_strict_scan(&mut self, strict: bool) {
+        self.strict_scan = strict;
+    }
+
+    /// Wait `post_connect_delay` after connecting, then try service discovery `attempts` times
+    /// with `retry_interval` in between
+    pub fn set_discovery_timing(
+        &mut self,
+        post_connect_delay: Duration,
+        attempts: u32,
+        retry_interval: Duration,
+    ) {
+        self.post_connect_delay = post_connect_delay;
+        self.discovery_attempts = attempts.max(1);
+        self.discovery_retry_interval = retry_interval;
+    }
+
+    /// Current connection state
+    pub fn state(&self) -> &ConnectionState {
+        &self.state
+    }
+
+    /// Where the sensor is worn, if the device reports its Body Sensor Location
+    pub fn sensor_location(&self) -> Option<&'static str> {
+        self.sensor_location
+    }
+
+    /// Reset the accumulated Energy Expended through the Heart Rate Control Point
+    pub async fn reset_energy_expended(&self) -> Result<()> {
+        let ConnectionState::Connected { peripheral, .. } = &self.state else {
+            anyhow::bail!("No device connected");
+        };
+        let control_point = peripheral
+            .characteristics()
+            .into_iter()
+            .find(|c| is_heart_rate_control_point_uuid(&c.uuid))
+            .context("Device has no heart rate control point")?;
+        peripheral
+            .write(
+                &control_point,
+                &[HEART_RATE_CONTROL_POINT_RESET_ENERGY],
+                WriteType::WithResponse,
+            )
+            .await
+            .context("Failed to write the heart rate control point")?;
+        tracing::info!("Energy expended reset");
+        Ok(())
+    }
+
+    /// Move to a new connection state, logging the transition
+    fn set_state(&mut self, state: ConnectionState) {
+        if state.name() != self.state.name() {
+            tracing::debug!("Bluetooth state: {} -> {}", self.state.name(), state.name());
+            if let Some(callback) = &self.state_change_callback {
+                callback(&self.state, &state);
+            }
+        }
+        self.state = state;
+    }
+
+    /// Report scan progress to the registered callback
+    fn report_scan_progress(&self, start_time: std::time::Instant, devices_seen: usize) {
+        if let Some(callback) = &self.scan_progress_callback {
+            callback(ScanProgress {
+                elapsed: start_time.elapsed(),
+                devices_seen,
+            });
+        }
+    }
+
+    /// Fail early when the adapter is powered off, a scan would silently find nothing
+    async fn ensure_powered_on(&self) -> Result<()> {
+        match self.adapter.adapter_state().await {
+            Ok(CentralState::PoweredOff) => Err(ScanError::PoweredOff.into()),
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::debug!("Failed to read the Bluetooth adapter state: {}", e);
+                Ok(())
+            }
+        }
+    }
+
+    /// Error for a scan that timed out, unless Bluetooth was turned off meanwhile
+    async fn not_found(&self, message: String) -> anyhow::Error {
+        match self.ensure_powered_on().await {
+            Err(e) => e,
+            Ok(()) => anyhow::anyhow!(message),
+        }
+    }
+
+    /// Stop an ongoing scan, e.g. after a connection attempt was cancelled
+    pub async fn stop_scan(&mut self) -> Result<()> {
+        if matches!(self.state, ConnectionState::Scanning { .. }) {
+            self.set_state(ConnectionState::Disconnected);
+        }
+        self.adapter
+            .stop_scan()
+            .await
+            .context("Failed to stop Bluetooth scan")
+    }
+
     /// Start scanning and connect to heart rate device
     pub async fn connect(
         &mut self,
         device_name: Option<&str>,
         device_address: Option<&str>,
+    ) -> Result<()> {
+        if !matches!(self.state, ConnectionState::Disconnected) {
+            anyhow::bail!("Cannot connect while {}", self.state.name());
+        }
+
+        self.set_state(ConnectionState::Scanning {
+            since: Instant::now(),
+        });
+        let result = self.scan_and_connect(device_name, device_address).await;
+        if result.is_err() {
+            self.set_state(ConnectionState::Disconnected);
+        }
+        result
+    }
+
+    /// Find the device and open the link to it
+    async fn scan_and_connect(
+        &mut self,
+        device_name: Option<&str>,
+        device_address: Option<&str>,
     ) -> Result<()> {
         tracing::info!("Starting device discovery...");
+        self.ensure_powered_on().await?;
 
-        // Start scanning
+        // Start scanning, some devices leave the service UUID out of their advertisements
+        let filter = if self.strict_scan {
+            ScanFilter {
+                services: vec![HEART_RATE_SERVICE_UUID_FULL],
+            }
+        } else {
+            ScanFilter::default()
+        };
         self.adapter
-            .start_scan(ScanFilter::default())
+            .start_scan(filter)
             .await
-            .context("Failed to start Bluetooth scan")?;
+            .map_err(|e| scan_failure(e, "Failed to start Bluetooth scan"))?;
 
         let device = if let Some(name) = device_name {
             self.find_device_by_name(name).await?
@@ -92,23 +519,32 @@ impl BluetoothHeartRateMonitor {
             .context("Failed to stop Bluetooth scan")?;
 
         // Connect to device
+        self.set_state(ConnectionState::Connecting {
+            peripheral: device.clone(),
+        });
         device
             .connect()
             .await
             .context("Failed to connect to heart rate device")?;
 
-        let device_name = device
+        if let Some((name, address)) = self.connected_device().await {
+            tracing::info!("Connected to device: {} ({})", name, address);
+        }
+
+        Ok(())
+    }
+
+    /// Get the name and address of the connected device
+    pub async fn connected_device(&self) -> Option<(String, String)> {
+        let device = self.state.peripheral()?;
+        let name = device
             .properties()
             .await
             .ok()
             .flatten()
             .and_then(|p| p.local_name)
             .unwrap_or_else(|| "Unknown".to_string());
-
-        tracing::info!("Connected to device: {}", device_name);
-        self.device = Some(device);
-
-        Ok(())
+        Some((name, device.address().to_string()))
     }
 
     /// Find device by name
@@ -123,7 +559,8 @@ impl BluetoothHeartRateMonitor {
                 .adapter
                 .peripherals()
                 .await
-                .context("Failed to get peripherals")?;
+                .map_err(|e| scan_failure(e, "Failed to get peripherals"))?;
+            self.report_scan_progress(start_time, peripherals.len());
 
             for peripheral in peripherals {
                 if let Ok(Some(properties)) = peripheral.properties().await {
@@ -139,10 +576,12 @@ impl BluetoothHeartRateMonitor {
             sleep(Duration::from_millis(500)).await;
         }
 
-        anyhow::bail!(
-            "Device '{}' not found. Please ensure the device is powered on and heart rate broadcasting is enabled. Also check that the device is not connected to other applications.",
-            target_name
-        );
+        Err(self
+            .not_found(format!(
+                "Device '{}' not found. Please ensure the device is powered on and heart rate broadcasting is enabled. Also check that the device is not connected to other applications.",
+                target_name
+            ))
+            .await)
     }
 
     /// Find device by address
@@ -157,7 +596,8 @@ impl BluetoothHeartRateMonitor {
                 .adapter
                 .peripherals()
                 .await
-                .context("Failed to get peripherals")?;
+                .map_err(|e| scan_failure(e, "Failed to get peripherals"))?;
+            self.report_scan_progress(start_time, peripherals.len());
 
             for peripheral in peripherals {
                 if let Ok(Some(properties)) = peripheral.properties().await {
@@ -172,10 +612,12 @@ impl BluetoothHeartRateMonitor {
             sleep(Duration::from_millis(500)).await;
         }
 
-        anyhow::bail!(
-            "Device with address '{}' not found. Please ensure the device is powered on and heart rate broadcasting is enabled. Also check that the device is not connected to other applications.",
-            target_address
-        );
+        Err(self
+            .not_found(format!(
+                "Device with address '{}' not found. Please ensure the device is powered on and heart rate broadcasting is enabled. Also check that the device is not connected to other applications.",
+                target_address
+            ))
+            .await)
     }
 
     /// Find any heart rate device
@@ -190,7 +632,8 @@ impl BluetoothHeartRateMonitor {
                 .adapter
                 .peripherals()
                 .await
-                .context("Failed to get peripherals")?;
+                .map_err(|e| scan_failure(e, "Failed to get peripherals"))?;
+            self.report_scan_progress(start_time, peripherals.len());
 
             tracing::debug!("Scanning {} peripherals...", peripherals.len());
 
@@ -222,24 +665,26 @@ impl BluetoothHeartRateMonitor {
             sleep(Duration::from_millis(1000)).await;
         }
 
-        anyhow::bail!("No heart rate device found within 30 seconds. Please ensure your heart rate device is broadcasting heart rate data. Also check that the device is not connected to other applications.");
+        Err(self
+            .not_found("No heart rate device found within 30 seconds. Please ensure your heart rate device is broadcasting heart rate data. Also check that the device is not connected to other applications.".to_string())
+            .await)
     }
 
-    /// Start monitoring heart rate data
-    pub async fn start_monitoring<F>(&self, mut callback: F) -> Result<()>
-    where
-        F: FnMut(u32) + Send + 'static,
-    {
-        let device = self.device.as_ref().context("No device connected")?;
+    /// Discover the heart rate characteristic and subscribe to its notifications
+    pub async fn subscribe(&mut self) -> Result<()> {
+        let ConnectionState::Connecting { peripheral } = &self.state else {
+            anyhow::bail!("Cannot subscribe while {}", self.state.name());
+        };
+        let device = peripheral.clone();
 
         tracing::info!("Starting heart rate monitoring...");
 
         // Wait a bit for the device to stabilize after connection
-        sleep(Duration::from_millis(1000)).await;
+        sleep(self.post_connect_delay).await;
 
         // Discover services and characteristics with retry
         let mut retry_count = 0;
-        let max_retries = 3;
+        let max_retries = self.discovery_attempts;
 
         while retry_count < max_retries {
             tracing::info!(
@@ -263,11 +708,12 @@ impl BluetoothHeartRateMonitor {
                         ));
                     }
                     tracing::warn!(
-                        "Service discovery failed (attempt {}), retrying in 2 seconds: {}",
+                        "Service discovery failed (attempt {}), retrying in {:.1}s: {}",
                         retry_count,
+                        self.discovery_retry_interval.as_secs_f64(),
                         e
                     );
-                    sleep(Duration::from_millis(2000)).await;
+                    sleep(self.discovery_retry_interval).await;
                 }
             }
         }
@@ -306,6 +752,26 @@ impl BluetoothHeartRateMonitor {
             heart_rate_char.uuid
         );
 
+        // Optional characteristic, also a sign the device really implements the heart rate profile
+        let location_char = heart_rate_service
+            .characteristics
+            .iter()
+            .find(|c| is_body_sensor_location_char_uuid(&c.uuid));
+        self.sensor_location = match location_char {
+            Some(location_char) => match device.read(location_char).await {
+                Ok(value) => value.first().map(|&value| body_sensor_location_name(value)),
+                Err(e) => {
+                    tracing::warn!("Failed to read body sensor location: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+        match self.sensor_location {
+            Some(location) => tracing::info!("Body sensor location: {}", location),
+            None => tracing::debug!("Device does not report a body sensor location"),
+        }
+
         // Subscribe to notifications
         device
             .subscribe(heart_rate_char)
@@ -325,67 +791,370 @@ impl BluetoothHeartRateMonitor {
 
         tracing::info!("Listening for heart rate notifications...");
 
-        while let Some(data) = notification_stream.next().await {
-            if is_heart_rate_measurement_char_uuid(&data.uuid) {
-                if let Some(heart_rate) = Self::parse_heart_rate_data(&data.value) {
-                    tracing::debug!("Heart rate: {}", heart_rate);
-                    callback(heart_rate);
+        // Some devices only start notifying once the CCCD is written explicitly
+        let mut first = first_notification(&mut notification_stream).await;
+        if first.is_some() {
+            tracing::info!("Heart rate notifications started via subscribe");
+        } else {
+            tracing::warn!(
+                "No notifications within {}s of subscribing, writing the CCCD directly",
+                NOTIFICATION_START_TIMEOUT.as_secs()
+            );
+            let cccd = heart_rate_char.descriptors.iter().find(|d| is_cccd_uuid(&d.uuid));
+            let written = match cccd {
+                Some(cccd) => device.write_descriptor(cccd, &CCCD_ENABLE_NOTIFICATIONS).await,
+                None => Err(btleplug::Error::NotSupported("no CCCD descriptor".to_string())),
+            };
+            match written {
+                Ok(()) => {
+                    first = first_notification(&mut notification_stream).await;
+                    if first.is_some() {
+                        tracing::info!("Heart rate notifications started via CCCD write");
+                    } else {
+                        tracing::warn!(
+                            "Still no notifications after the CCCD write, is the strap worn?"
+                        );
+                    }
                 }
+                Err(e) => tracing::warn!("Failed to write the CCCD: {}", e),
             }
         }
 
+        // Keep the notification used to detect the start as the first sample
+        let notification_stream: NotificationStream =
+            Box::pin(futures::stream::iter(first).chain(notification_stream));
+        self.notifications = Mutex::new(Some(notification_stream));
+        self.set_state(ConnectionState::Connected {
+            peripheral: device,
+            subscribed_char: heart_rate_char.clone(),
+        });
+
         Ok(())
     }
 
-    /// Parse heart rate data from BLE notification
-    fn parse_heart_rate_data(data: &[u8]) -> Option<u32> {
+    /// Read the raw heart rate value from a BLE notification, without range checks
+    fn read_heart_rate_value(data: &[u8]) -> Option<u32> {
         if data.is_empty() {
             return None;
         }
 
         let flags = data[0];
-        let heart_rate = if flags & 0x01 != 0 {
+        if flags & 0x01 != 0 {
             // 16-bit heart rate value
             if data.len() >= 3 {
-                u16::from_le_bytes([data[1], data[2]]) as u32
+                Some(u16::from_le_bytes([data[1], data[2]]) as u32)
             } else {
-                return None;
+                None
             }
         } else {
             // 8-bit heart rate value
             if data.len() >= 2 {
-                data[1] as u32
+                Some(data[1] as u32)
             } else {
-                return None;
+                None
             }
-        };
+        }
+    }
 
-        if heart_rate > 0 && heart_rate < 300 {
+    /// Parse heart rate data from BLE notification
+    fn parse_heart_rate_data(data: &[u8], bpm_range: BpmRange) -> Option<u32> {
+        let heart_rate = Self::read_heart_rate_value(data)?;
+        if bpm_range.contains(heart_rate) {
             Some(heart_rate)
         } else {
+            tracing::debug!(
+                "Dropping out-of-range heart rate {} (allowed {}-{})",
+                heart_rate,
+                bpm_range.min,
+                bpm_range.max
+            );
             None
         }
     }
 
+    /// Parse the sensor contact status from a BLE notification, `None` if the strap can't tell
+    fn parse_sensor_contact(data: &[u8]) -> Option<bool> {
+        let flags = *data.first()?;
+        // Bit 2: contact detection supported, bit 1: contact detected
+        (flags & 0x04 != 0).then_some(flags & 0x02 != 0)
+    }
+
+    /// Parse the cumulative energy expended (kJ) from a BLE notification, if present
+    fn parse_energy_expended(data: &[u8]) -> Option<u16> {
+        let flags = *data.first()?;
+        if flags & 0x08 == 0 {
+            return None;
+        }
+
+        // The field follows the 8-bit or 16-bit heart rate value
+        let offset = if flags & 0x01 != 0 { 3 } else { 2 };
+        let bytes = data.get(offset..offset + 2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
     /// Disconnect from device
     pub async fn disconnect(&mut self) -> Result<()> {
-        if let Some(device) = &self.device {
-            device
-                .disconnect()
-                .await
-                .context("Failed to disconnect from device")?;
-            tracing::info!("Disconnected from heart rate device");
-        }
-        self.device = None;
+        self.notifications = Mutex::new(None);
+        let Some(device) = self.state.peripheral().cloned() else {
+            self.set_state(ConnectionState::Disconnected);
+            return Ok(());
+        };
+
+        self.set_state(ConnectionState::Disconnecting);
+        let result = device
+            .disconnect()
+            .await
+            .context("Failed to disconnect from device");
+        self.set_state(ConnectionState::Disconnected);
+        result?;
+
+        tracing::info!("Disconnected from heart rate device");
         Ok(())
     }
 
     /// Check if device is connected
     pub async fn is_connected(&self) -> bool {
-        if let Some(device) = &self.device {
+        if let Some(device) = self.state.peripheral() {
             device.is_connected().await.unwrap_or(false)
         } else {
             false
         }
     }
 }
+
+#[async_trait]
+impl HeartRateSource for BluetoothHeartRateMonitor {
+    async fn next_sample(&mut self) -> Result<HeartRateSample> {
+        let bpm_range = self.bpm_range;
+        let notifications = self
+            .notifications
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_mut()
+            .context("Not subscribed to heart rate notifications")?;
+
+        while let Some(data) = notifications.next().await {
+            if !is_heart_rate_measurement_char_uuid(&data.uuid) {
+                continue;
+            }
+            // Straps send 0 while they have no skin contact, the monitor decides what to show
+            if Self::read_heart_rate_value(&data.value) == Some(0) {
+                tracing::debug!("Heart rate: 0, no sensor contact");
+                return Ok(HeartRateSample::new(MonitorMode::Bluetooth, 0));
+            }
+            if let Some(heart_rate) = Self::parse_heart_rate_data(&data.value, bpm_range) {
+                tracing::debug!("Heart rate: {}", heart_rate);
+                return Ok(HeartRateSample {
+                    energy_expended_kj: Self::parse_energy_expended(&data.value),
+                    sensor_contact: Self::parse_sensor_contact(&data.value),
+                    ..HeartRateSample::new(MonitorMode::Bluetooth, heart_rate)
+                });
+            }
+        }
+
+        Err(anyhow::anyhow!("Heart rate notifications stopped"))
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        self.disconnect().await
+    }
+
+    async fn reset_energy_expended(&mut self) -> Result<()> {
+        BluetoothHeartRateMonitor::reset_energy_expended(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RANGE: BpmRange = BpmRange { min: 30, max: 250 };
+
+    fn parse(data: &[u8]) -> Option<u32> {
+        BluetoothHeartRateMonitor::parse_heart_rate_data(data, RANGE)
+    }
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(parse(&[]), None);
+    }
+
+    #[test]
+    fn eight_bit_value() {
+        assert_eq!(parse(&[0x00, 72]), Some(72));
+        // Trailing fields (energy expended, RR intervals) are ignored
+        assert_eq!(parse(&[0x10, 72, 0x00, 0x04]), Some(72));
+    }
+
+    #[test]
+    fn eight_bit_truncated() {
+        assert_eq!(parse(&[0x00]), None);
+    }
+
+    #[test]
+    fn sixteen_bit_value() {
+        assert_eq!(parse(&[0x01, 0x48, 0x00]), Some(72));
+        assert_eq!(parse(&[0x01, 0xC8, 0x00]), Some(200));
+    }
+
+    #[test]
+    fn sixteen_bit_truncated() {
+        assert_eq!(parse(&[0x01]), None);
+        assert_eq!(parse(&[0x01, 72]), None);
+    }
+
+    #[test]
+    fn out_of_range_values() {
+        assert_eq!(parse(&[0x00, 0]), None);
+        assert_eq!(parse(&[0x00, 29]), None);
+        assert_eq!(parse(&[0x00, 255]), None);
+        assert_eq!(parse(&[0x01, 0x00, 0x01]), None);
+        assert_eq!(parse(&[0x01, 0xFF, 0xFF]), None);
+    }
+
+    #[test]
+    fn zero_value_is_read_before_range_check() {
+        let read = BluetoothHeartRateMonitor::read_heart_rate_value;
+        assert_eq!(read(&[0x00, 0]), Some(0));
+        assert_eq!(read(&[0x01, 0x00, 0x00]), Some(0));
+        assert_eq!(read(&[0x01, 0x00]), None);
+    }
+
+    #[test]
+    fn range_bounds_are_inclusive() {
+        assert_eq!(parse(&[0x00, 30]), Some(30));
+        assert_eq!(parse(&[0x01, 250, 0x00]), Some(250));
+    }
+
+    #[test]
+    fn full_service_uuid() {
+        assert_eq!(
+            HEART_RATE_SERVICE_UUID_FULL.to_string(),
+            "0000180d-0000-1000-8000-00805f9b34fb"
+        );
+        assert!(is_heart_rate_service_uuid(&HEART_RATE_SERVICE_UUID_FULL));
+    }
+
+    #[test]
+    fn control_point_uuid() {
+        assert!(is_heart_rate_control_point_uuid(&uuid_from_u16(0x2A39)));
+        assert!(!is_heart_rate_control_point_uuid(&uuid_from_u16(0x2A37)));
+    }
+
+    #[test]
+    fn body_sensor_locations() {
+        assert_eq!(body_sensor_location_name(1), "Chest");
+        assert_eq!(body_sensor_location_name(2), "Wrist");
+        assert_eq!(body_sensor_location_name(0), "Other");
+        assert_eq!(body_sensor_location_name(7), "Other");
+    }
+
+    /// Range wide enough to reach the 8-bit format limits
+    const WIDE_RANGE: BpmRange = BpmRange { min: 1, max: 299 };
+
+    fn parse_wide(data: &[u8]) -> Option<u32> {
+        BluetoothHeartRateMonitor::parse_heart_rate_data(data, WIDE_RANGE)
+    }
+
+    #[test]
+    fn wide_range_empty_and_single_byte() {
+        assert_eq!(parse_wide(&[]), None);
+        assert_eq!(parse_wide(&[0x00]), None);
+    }
+
+    #[test]
+    fn wide_range_eight_bit_values() {
+        assert_eq!(parse_wide(&[0x00, 1]), Some(1));
+        assert_eq!(parse_wide(&[0x00, 60]), Some(60));
+        assert_eq!(parse_wide(&[0x00, 255]), Some(255));
+        assert_eq!(parse_wide(&[0x00, 0]), None);
+    }
+
+    #[test]
+    fn wide_range_upper_bound() {
+        // 299 and 300 only fit the 16-bit format
+        assert_eq!(parse_wide(&[0x01, 0x2B, 0x01]), Some(299));
+        assert_eq!(parse_wide(&[0x01, 0x2C, 0x01]), None);
+    }
+
+    #[test]
+    fn wide_range_sixteen_bit_values() {
+        assert_eq!(parse_wide(&[0x01, 0x78, 0x00]), Some(120));
+        assert_eq!(parse_wide(&[0x01, 0x78]), None);
+    }
+
+    #[test]
+    fn polar_h10_notification() {
+        // Polar H10 at rest: flags 0x16 (8-bit BPM, sensor contact supported and detected,
+        // RR intervals present), 62 BPM, then one RR interval of 0x040A (1034/1024 s)
+        let data = [0x16, 0x3E, 0x0A, 0x04];
+        assert_eq!(parse_wide(&data), Some(62));
+        assert_eq!(BluetoothHeartRateMonitor::parse_energy_expended(&data), None);
+    }
+
+    #[test]
+    fn energy_expended() {
+        let energy = BluetoothHeartRateMonitor::parse_energy_expended;
+        assert_eq!(energy(&[]), None);
+        assert_eq!(energy(&[0x00, 72, 0x10, 0x00]), None);
+        assert_eq!(energy(&[0x08, 72, 0x10, 0x01]), Some(0x0110));
+        assert_eq!(energy(&[0x09, 72, 0x00, 0x10, 0x01]), Some(0x0110));
+        assert_eq!(energy(&[0x08, 72, 0x10]), None);
+    }
+
+    #[test]
+    fn sensor_contact() {
+        let contact = BluetoothHeartRateMonitor::parse_sensor_contact;
+        assert_eq!(contact(&[]), None);
+        assert_eq!(contact(&[0x00, 72]), None);
+        assert_eq!(contact(&[0x04, 72]), Some(false));
+        assert_eq!(contact(&[0x16, 72]), Some(true));
+    }
+
+    #[test]
+    fn scan_errors_are_classified() {
+        assert_eq!(
+            classify_scan_error(&btleplug::Error::PermissionDenied),
+            Some(ScanError::PermissionDenied)
+        );
+        let bluez = |message: &str| btleplug::Error::Other(message.to_string().into());
+        assert_eq!(
+            classify_scan_error(&bluez("D-Bus error org.bluez.Error.NotReady: Resource Not Ready")),
+            Some(ScanError::PoweredOff)
+        );
+        assert_eq!(
+            classify_scan_error(&bluez("org.freedesktop.DBus.Error.AccessDenied: Rejected send")),
+            Some(ScanError::PermissionDenied)
+        );
+        assert_eq!(classify_scan_error(&btleplug::Error::TimedOut(Duration::ZERO)), None);
+        assert_eq!(classify_scan_error(&btleplug::Error::DeviceNotFound), None);
+    }
+
+    #[test]
+    fn arbitrary_bytes_never_panic() {
+        // Device data is untrusted, so every short payload must parse without panicking
+        for flags in 0..=u8::MAX {
+            assert_eq!(parse(&[flags]), None);
+            for value in 0..=u8::MAX {
+                for data in [&[flags, value][..], &[flags, value, value ^ 0x5A][..]] {
+                    if let Some(bpm) = parse(data) {
+                        assert!(RANGE.contains(bpm));
+                    }
+                }
+            }
+        }
+
+        // Longer random payloads from a fixed-seed xorshift
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        for _ in 0..10_000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let len = (state % 32) as usize;
+            let data: Vec<u8> = (0..len).map(|i| (state >> (i % 8 * 8)) as u8).collect();
+            if let Some(bpm) = parse(&data) {
+                assert!(RANGE.contains(bpm));
+            }
+        }
+    }
+}
@@ -2,14 +2,30 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::stats::CalorieProfile;
+use crate::xiaomi_band::HeartRateField;
+use crate::zones::{HeartRateZone, ZoneConfig, DEFAULT_MAX_HR};
+
+pub mod migrations;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Files without it predate versioning and are migrated from version 1
+    #[serde(rename = "SCHEMA_VERSION", default = "default_schema_version")]
+    pub schema_version: u32,
     #[serde(rename = "OSC_HOST")]
     pub osc_host: String,
     #[serde(rename = "OSC_PORT")]
     pub osc_port: u16,
+    #[serde(rename = "OSC_TARGETS", default)]
+    pub osc_targets: Vec<OscTarget>,
+    /// Local IP the OSC socket sends from, picks the interface on machines with several
+    #[serde(rename = "OSC_BIND_ADDR", default)]
+    pub osc_bind_addr: Option<String>,
     #[serde(rename = "HEART_RATE_DEVICE_NAME")]
     pub heart_rate_device_name: Option<String>,
     #[serde(rename = "HEART_RATE_DEVICE_ADDRESS")]
@@ -18,8 +34,412 @@ pub struct Config {
     pub apple_watch: bool,
     #[serde(rename = "XIAOMI_BAND")]
     pub xiaomi_band: Option<bool>,
+    /// Index of the heart rate in the Xiaomi Band manufacturer data
+    #[serde(rename = "XIAOMI_HR_OFFSET", default = "default_xiaomi_hr_offset")]
+    pub xiaomi_hr_offset: usize,
+    /// Size of the Xiaomi Band heart rate field, 1 or 2 bytes
+    #[serde(rename = "XIAOMI_HR_WIDTH", default = "default_xiaomi_hr_width")]
+    pub xiaomi_hr_width: usize,
+    #[serde(rename = "XIAOMI_HR_BIG_ENDIAN", default)]
+    pub xiaomi_hr_big_endian: bool,
+    /// Seconds without any advertisement before the Xiaomi Band scan is restarted, 0 to disable
+    #[serde(rename = "XIAOMI_SCAN_WATCHDOG_SECS", default = "default_xiaomi_scan_watchdog_secs")]
+    pub xiaomi_scan_watchdog_secs: u64,
     #[serde(rename = "HEART_RATE_LABEL")]
     pub heart_rate_label: HashMap<String, Vec<String>>,
+    #[serde(rename = "DEFAULT_LABEL", default)]
+    pub default_labels: Vec<String>,
+    #[serde(rename = "OFFLINE_MESSAGE", default)]
+    pub offline_message: Option<String>,
+    #[serde(rename = "OFFLINE_TIMEOUT_SECS", default = "default_offline_timeout_secs")]
+    pub offline_timeout_secs: u64,
+    /// Sent once when the sensor reports 0 BPM after losing contact, unset to ignore such readings
+    #[serde(rename = "ZERO_READING_MESSAGE", default)]
+    pub zero_reading_message: Option<String>,
+    /// Seconds without data before the last reading is shown as stale
+    #[serde(rename = "STALE_AFTER_SECS", default = "default_stale_after_secs")]
+    pub stale_after_secs: u64,
+    /// Appended to the last label and sent once when the reading goes stale, e.g. " (stale)"
+    #[serde(rename = "STALE_MARKER", default)]
+    pub stale_marker: Option<String>,
+    #[serde(rename = "MIN_BPM", default = "default_min_bpm")]
+    pub min_bpm: u32,
+    #[serde(rename = "MAX_BPM", default = "default_max_bpm")]
+    pub max_bpm: u32,
+    #[serde(rename = "OSC_SEND_INTERVAL_MS", default = "default_osc_send_interval_ms")]
+    pub osc_send_interval_ms: u64,
+    /// Skip chatbox sends repeating the last label for the same BPM
+    #[serde(rename = "SKIP_DUPLICATE_MESSAGES", default)]
+    pub skip_duplicate_messages: bool,
+    /// Seconds after which an unchanged label is sent again anyway
+    #[serde(rename = "DUPLICATE_REFRESH_SECS", default = "default_duplicate_refresh_secs")]
+    pub duplicate_refresh_secs: u64,
+    #[serde(rename = "HEART_RATE_SOURCES", default)]
+    pub heart_rate_sources: Vec<MonitorMode>,
+    #[serde(rename = "SOURCE_FALLBACK_SECS", default = "default_source_fallback_secs")]
+    pub source_fallback_secs: u64,
+    #[serde(rename = "DB_PATH", default)]
+    pub db_path: Option<String>,
+    /// Database backups kept, taken before schema upgrades and imports
+    #[serde(rename = "DB_BACKUP_COUNT", default = "default_db_backup_count")]
+    pub db_backup_count: u8,
+    #[serde(rename = "OSC_TYPING_INDICATOR", default = "default_osc_typing_indicator")]
+    pub osc_typing_indicator: bool,
+    #[serde(rename = "PERSIST", default = "default_persist")]
+    pub persist: bool,
+    #[serde(rename = "OSC_HEARTBEAT_PARAM", default)]
+    pub osc_heartbeat_param: Option<String>,
+    #[serde(rename = "OSC_CONNECTED_PARAM", default)]
+    pub osc_connected_param: Option<String>,
+    #[serde(rename = "OSC_PAUSED_PARAM", default)]
+    pub osc_paused_param: Option<String>,
+    #[serde(rename = "OSC_ZONE_PARAM", default)]
+    pub osc_zone_param: Option<String>,
+    /// Int avatar parameter set to the new zone index on debounced zone crossings
+    #[serde(rename = "OSC_ZONE_EVENT_PARAM", default)]
+    pub osc_zone_event_param: Option<String>,
+    /// Chatbox message sent when entering a zone, `{{zone}}` is the new zone
+    #[serde(rename = "ZONE_CHANGE_MESSAGE", default)]
+    pub zone_change_message: Option<String>,
+    /// Seconds a new zone must hold before a zone crossing is reported
+    #[serde(rename = "ZONE_CHANGE_DEBOUNCE_SECS", default = "default_zone_change_debounce_secs")]
+    pub zone_change_debounce_secs: u64,
+    /// Warn once when the heart rate rises above this BPM
+    #[serde(rename = "MAX_HR_ALARM", default)]
+    pub max_hr_alarm: Option<u32>,
+    /// Chatbox message sent when the alarm goes off, supports the label placeholders
+    #[serde(rename = "MAX_HR_ALARM_MESSAGE", default = "default_max_hr_alarm_message")]
+    pub max_hr_alarm_message: Option<String>,
+    /// Bool avatar parameter that is true while the alarm is active
+    #[serde(rename = "OSC_ALARM_PARAM", default)]
+    pub osc_alarm_param: Option<String>,
+    #[serde(rename = "MAX_HR_ALARM_SOUND", default = "default_max_hr_alarm_sound")]
+    pub max_hr_alarm_sound: bool,
+    #[serde(rename = "USER_MAX_HR", default)]
+    pub user_max_hr: Option<u32>,
+    #[serde(rename = "USER_AGE", default)]
+    pub user_age: Option<u32>,
+    /// Weight, USER_AGE and USER_SEX estimate the calories burned from heart rate
+    #[serde(rename = "USER_WEIGHT_KG", default)]
+    pub user_weight_kg: Option<f32>,
+    #[serde(rename = "USER_SEX", default)]
+    pub user_sex: Option<UserSex>,
+    /// Switches zones to the Karvonen formula together with USER_MAX_HR or USER_AGE
+    #[serde(rename = "RESTING_HR", default)]
+    pub resting_hr: Option<u32>,
+    #[serde(rename = "OSC_NORMALIZED_PARAM", default)]
+    pub osc_normalized_param: Option<String>,
+    #[serde(rename = "OSC_NORMALIZED_MIN", default = "default_osc_normalized_min")]
+    pub osc_normalized_min: u32,
+    #[serde(rename = "OSC_NORMALIZED_MAX", default = "default_osc_normalized_max")]
+    pub osc_normalized_max: u32,
+    /// Also send the int BPM, normalized float and beat parameters used by common HR prefabs
+    #[serde(rename = "OSC_PREFAB_COMPAT", default)]
+    pub osc_prefab_compat: bool,
+    #[serde(rename = "OSC_PREFAB_BPM_PARAM", default = "default_osc_prefab_bpm_param")]
+    pub osc_prefab_bpm_param: String,
+    #[serde(rename = "OSC_PREFAB_FLOAT_PARAM", default = "default_osc_prefab_float_param")]
+    pub osc_prefab_float_param: String,
+    #[serde(rename = "OSC_PREFAB_BEAT_PARAM", default = "default_osc_prefab_beat_param")]
+    pub osc_prefab_beat_param: String,
+    #[serde(rename = "WARMUP_SECS", default)]
+    pub warmup_secs: u64,
+    /// Readings scoring a lower confidence (0-100) are kept off OSC, 0 sends all of them
+    #[serde(rename = "OSC_MIN_CONFIDENCE", default)]
+    pub osc_min_confidence: u8,
+    #[serde(rename = "GUI_MAX_LOG_ENTRIES", default = "default_gui_max_log_entries")]
+    pub gui_max_log_entries: usize,
+    #[serde(rename = "APPLE_WATCH_BIND_ADDRESS", default)]
+    pub apple_watch_bind_address: Option<String>,
+    /// Requests per second accepted from each client, 0 for no limit
+    #[serde(rename = "APPLE_WATCH_MAX_RPS", default = "default_apple_watch_max_rps")]
+    pub apple_watch_max_rps: u32,
+    /// Consecutive failed chatbox sends before the OSC target is reported unreachable
+    #[serde(rename = "OSC_FAILURE_THRESHOLD", default = "default_osc_failure_threshold")]
+    pub osc_failure_threshold: u32,
+    /// Hide the window to the tray icon when minimized, Windows and macOS only
+    #[serde(rename = "MINIMIZE_TO_TRAY", default)]
+    pub minimize_to_tray: bool,
+    /// Chatbox message sent once when a Bluetooth device connects
+    #[serde(rename = "OSC_CONNECT_MESSAGE", default)]
+    pub osc_connect_message: Option<String>,
+    /// Chatbox message sent once on shutdown while a Bluetooth device is connected
+    #[serde(rename = "OSC_DISCONNECT_MESSAGE", default)]
+    pub osc_disconnect_message: Option<String>,
+    /// Bluetooth adapter to use, an index or part of its name, defaults to the first adapter
+    #[serde(rename = "BLE_ADAPTER", default)]
+    pub ble_adapter: Option<String>,
+    /// Only scan for devices advertising the heart rate service
+    #[serde(rename = "BLUETOOTH_STRICT_SCAN", default = "default_bluetooth_strict_scan")]
+    pub bluetooth_strict_scan: bool,
+    /// Wait after connecting before discovering services, for devices that need to settle
+    #[serde(rename = "BLE_POST_CONNECT_DELAY_MS", default = "default_ble_post_connect_delay_ms")]
+    pub ble_post_connect_delay_ms: u64,
+    /// Service discovery attempts before the connection is given up
+    #[serde(rename = "BLE_DISCOVERY_ATTEMPTS", default = "default_ble_discovery_attempts")]
+    pub ble_discovery_attempts: u32,
+    /// Wait between service discovery attempts
+    #[serde(rename = "BLE_DISCOVERY_RETRY_MS", default = "default_ble_discovery_retry_ms")]
+    pub ble_discovery_retry_ms: u64,
+    #[serde(rename = "BLUETOOTH_FAVORITES", default)]
+    pub bluetooth_favorites: Vec<BluetoothFavorite>,
+    /// Broadcast each reading to a UDP multicast group on the LAN
+    #[serde(rename = "MULTICAST_ENABLED", default)]
+    pub multicast_enabled: bool,
+    #[serde(rename = "MULTICAST_GROUP", default = "default_multicast_group")]
+    pub multicast_group: String,
+    #[serde(rename = "MULTICAST_PORT", default = "default_multicast_port")]
+    pub multicast_port: u16,
+    /// Share heart rate with another HeartIO instance over TCP
+    #[serde(rename = "SHARE_MODE", default)]
+    pub share_mode: HeartRateShareMode,
+    /// Int avatar parameter receiving the heart rate of the remote instance in client mode
+    #[serde(rename = "OSC_AVATAR_PARAM_REMOTE", default)]
+    pub osc_avatar_param_remote: Option<String>,
+    /// Serve a heart rate overlay for OBS Studio browser sources
+    #[serde(rename = "OBS_SERVER_ENABLED", default)]
+    pub obs_server_enabled: bool,
+    #[serde(rename = "OBS_SERVER_PORT", default = "default_obs_server_port")]
+    pub obs_server_port: u16,
+    /// Look for a newer release on startup
+    #[serde(rename = "AUTO_UPDATE_CHECK", default = "default_auto_update_check")]
+    pub auto_update_check: bool,
+    #[serde(rename = "UPDATE_CHECK_URL", default = "default_update_check_url")]
+    pub update_check_url: String,
+}
+
+/// Environment variable overriding GUI_MAX_LOG_ENTRIES, e.g. for a one-off debugging session
+const LOG_ENTRIES_ENV: &str = "HEARTIO_GUI_MAX_LOG_ENTRIES";
+
+/// Allowed values for GUI_MAX_LOG_ENTRIES
+pub const GUI_LOG_ENTRIES_RANGE: RangeInclusive<usize> = 100..=10000;
+
+/// Additional OSC endpoint, e.g. a second VRChat instance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub struct OscTarget {
+    pub host: String,
+    pub port: u16,
+    #[serde(default = "default_osc_target_enabled")]
+    pub enabled: bool,
+}
+
+fn default_osc_target_enabled() -> bool {
+    true
+}
+
+/// Saved Bluetooth device that can be switched to from the GUI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub struct BluetoothFavorite {
+    pub name: String,
+    pub address: String,
+    /// Nickname shown in the favorites list, e.g. "Running strap"
+    pub label: String,
+}
+
+fn default_offline_timeout_secs() -> u64 {
+    10
+}
+
+fn default_stale_after_secs() -> u64 {
+    3
+}
+
+fn default_zone_change_debounce_secs() -> u64 {
+    5
+}
+
+fn default_min_bpm() -> u32 {
+    30
+}
+
+fn default_max_bpm() -> u32 {
+    250
+}
+
+fn default_osc_send_interval_ms() -> u64 {
+    1500
+}
+
+fn default_duplicate_refresh_secs() -> u64 {
+    30
+}
+
+fn default_source_fallback_secs() -> u64 {
+    5
+}
+
+fn default_osc_typing_indicator() -> bool {
+    true
+}
+
+fn default_persist() -> bool {
+    true
+}
+
+fn default_max_hr_alarm_message() -> Option<String> {
+    Some("⚠️ {{bpm}} BPM, time for a break".to_string())
+}
+
+fn default_max_hr_alarm_sound() -> bool {
+    true
+}
+
+fn default_xiaomi_hr_offset() -> usize {
+    HeartRateField::default().offset
+}
+
+fn default_xiaomi_hr_width() -> usize {
+    HeartRateField::default().width
+}
+
+fn default_xiaomi_scan_watchdog_secs() -> u64 {
+    30
+}
+
+fn default_bluetooth_strict_scan() -> bool {
+    true
+}
+
+fn default_ble_post_connect_delay_ms() -> u64 {
+    1000
+}
+
+fn default_ble_discovery_attempts() -> u32 {
+    3
+}
+
+fn default_ble_discovery_retry_ms() -> u64 {
+    2000
+}
+
+fn default_multicast_group() -> String {
+    "239.255.50.50".to_string()
+}
+
+fn default_multicast_port() -> u16 {
+    55000
+}
+
+fn default_obs_server_port() -> u16 {
+    8080
+}
+
+fn default_auto_update_check() -> bool {
+    true
+}
+
+fn default_update_check_url() -> String {
+    "https://api.github.com/repos/xn-sakina/vrchat-heartio/releases/latest".to_string()
+}
+
+fn default_osc_normalized_min() -> u32 {
+    40
+}
+
+fn default_osc_prefab_bpm_param() -> String {
+    "HR".to_string()
+}
+
+fn default_osc_prefab_float_param() -> String {
+    "floatHR".to_string()
+}
+
+fn default_osc_prefab_beat_param() -> String {
+    "isHRBeat".to_string()
+}
+
+fn default_osc_normalized_max() -> u32 {
+    200
+}
+
+fn default_osc_failure_threshold() -> u32 {
+    5
+}
+
+fn default_schema_version() -> u32 {
+    1
+}
+
+fn default_db_backup_count() -> u8 {
+    3
+}
+
+fn default_gui_max_log_entries() -> usize {
+    1000
+}
+
+fn default_apple_watch_max_rps() -> u32 {
+    10
+}
+
+/// Heart rate source selected by the configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MonitorMode {
+    Bluetooth,
+    AppleWatch,
+    XiaomiBand,
+    /// Synthetic readings for testing without a device, only via HEART_RATE_SOURCES
+    Simulated,
+}
+
+impl MonitorMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MonitorMode::Bluetooth => "Bluetooth",
+            MonitorMode::AppleWatch => "Apple Watch",
+            MonitorMode::XiaomiBand => "Xiaomi Band",
+            MonitorMode::Simulated => "Simulated",
+        }
+    }
+}
+
+/// Inclusive range of heart rate values accepted from any source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BpmRange {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl BpmRange {
+    /// Check whether a BPM value is inside the range
+    pub fn contains(&self, bpm: u32) -> bool {
+        bpm >= self.min && bpm <= self.max
+    }
+}
+
+/// Sex for the calorie estimate, `Other` averages the male and female formulas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UserSex {
+    Male,
+    Female,
+    Other,
+}
+
+/// How serious a problem found by `Config::validate` is, only errors stop HeartIO
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigSeverity {
+    Info,
+    Suggestion,
+    Warning,
+    Error,
+}
+
+/// A problem found in the configuration file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigWarning {
+    pub severity: ConfigSeverity,
+    pub message: String,
+}
+
+impl ConfigWarning {
+    fn new(severity: ConfigSeverity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+        }
+    }
 }
 
 impl Default for Config {
@@ -35,20 +455,91 @@ impl Default for Config {
             "❤️❤️❤️ {{bpm}} ❤️❤️❤️".to_string(),
             "💕💕💕 {{bpm}} 💕💕💕".to_string(),
         ]);
-        heart_rate_label.insert("999".to_string(), vec![
+        let default_labels = vec![
             "❤️❤️❤️❤️ {{bpm}} ❤️❤️❤️❤️".to_string(),
             "💕💕💕💕 {{bpm}} 💕💕💕💕".to_string(),
             "LOVE ❤️ {{bpm}} ❤️ LOVE".to_string(),
-        ]);
+        ];
 
         Self {
+            schema_version: migrations::CURRENT_SCHEMA_VERSION,
             osc_host: "127.0.0.1".to_string(),
             osc_port: 9000,
+            osc_targets: Vec::new(),
+            osc_bind_addr: None,
             heart_rate_device_name: None,
             heart_rate_device_address: None,
             apple_watch: false,
             xiaomi_band: Some(false),
+            xiaomi_hr_offset: default_xiaomi_hr_offset(),
+            xiaomi_hr_width: default_xiaomi_hr_width(),
+            xiaomi_hr_big_endian: false,
+            xiaomi_scan_watchdog_secs: default_xiaomi_scan_watchdog_secs(),
             heart_rate_label,
+            default_labels,
+            offline_message: None,
+            offline_timeout_secs: default_offline_timeout_secs(),
+            zero_reading_message: None,
+            stale_after_secs: default_stale_after_secs(),
+            stale_marker: None,
+            min_bpm: default_min_bpm(),
+            max_bpm: default_max_bpm(),
+            osc_send_interval_ms: default_osc_send_interval_ms(),
+            skip_duplicate_messages: false,
+            duplicate_refresh_secs: default_duplicate_refresh_secs(),
+            heart_rate_sources: Vec::new(),
+            source_fallback_secs: default_source_fallback_secs(),
+            db_path: None,
+            db_backup_count: default_db_backup_count(),
+            osc_typing_indicator: default_osc_typing_indicator(),
+            persist: default_persist(),
+            osc_heartbeat_param: None,
+            osc_connected_param: None,
+            osc_paused_param: None,
+            osc_zone_param: None,
+            osc_zone_event_param: None,
+            zone_change_message: None,
+            zone_change_debounce_secs: default_zone_change_debounce_secs(),
+            max_hr_alarm: None,
+            max_hr_alarm_message: default_max_hr_alarm_message(),
+            osc_alarm_param: None,
+            max_hr_alarm_sound: default_max_hr_alarm_sound(),
+            user_max_hr: None,
+            user_age: None,
+            user_weight_kg: None,
+            user_sex: None,
+            resting_hr: None,
+            osc_normalized_param: None,
+            osc_normalized_min: default_osc_normalized_min(),
+            osc_normalized_max: default_osc_normalized_max(),
+            osc_prefab_compat: false,
+            osc_prefab_bpm_param: default_osc_prefab_bpm_param(),
+            osc_prefab_float_param: default_osc_prefab_float_param(),
+            osc_prefab_beat_param: default_osc_prefab_beat_param(),
+            warmup_secs: 0,
+            osc_min_confidence: 0,
+            gui_max_log_entries: default_gui_max_log_entries(),
+            apple_watch_bind_address: None,
+            apple_watch_max_rps: default_apple_watch_max_rps(),
+            osc_failure_threshold: default_osc_failure_threshold(),
+            minimize_to_tray: false,
+            osc_connect_message: None,
+            osc_disconnect_message: None,
+            ble_adapter: None,
+            bluetooth_strict_scan: default_bluetooth_strict_scan(),
+            ble_post_connect_delay_ms: default_ble_post_connect_delay_ms(),
+            ble_discovery_attempts: default_ble_discovery_attempts(),
+            ble_discovery_retry_ms: default_ble_discovery_retry_ms(),
+            bluetooth_favorites: Vec::new(),
+            multicast_enabled: false,
+            multicast_group: default_multicast_group(),
+            multicast_port: default_multicast_port(),
+            share_mode: HeartRateShareMode::None,
+            osc_avatar_param_remote: None,
+            obs_server_enabled: false,
+            obs_server_port: default_obs_server_port(),
+            auto_update_check: default_auto_update_check(),
+            update_check_url: default_update_check_url(),
         }
     }
 }
@@ -61,6 +552,33 @@ impl Config {
         Ok(exe_dir.join("heartio.config.json"))
     }
 
+    /// Apply the HEARTIO_GUI_MAX_LOG_ENTRIES override and keep the log size in its allowed range
+    fn apply_log_entries_limit(&mut self) {
+        if let Ok(value) = std::env::var(LOG_ENTRIES_ENV) {
+            match value.trim().parse() {
+                Ok(entries) => {
+                    tracing::info!("Using {}={}", LOG_ENTRIES_ENV, entries);
+                    self.gui_max_log_entries = entries;
+                }
+                Err(_) => tracing::warn!("Ignoring invalid {}={:?}", LOG_ENTRIES_ENV, value),
+            }
+        }
+
+        if !GUI_LOG_ENTRIES_RANGE.contains(&self.gui_max_log_entries) {
+            let clamped = self
+                .gui_max_log_entries
+                .clamp(*GUI_LOG_ENTRIES_RANGE.start(), *GUI_LOG_ENTRIES_RANGE.end());
+            tracing::warn!(
+                "GUI_MAX_LOG_ENTRIES {} is outside {}-{}, using {}",
+                self.gui_max_log_entries,
+                GUI_LOG_ENTRIES_RANGE.start(),
+                GUI_LOG_ENTRIES_RANGE.end(),
+                clamped
+            );
+            self.gui_max_log_entries = clamped;
+        }
+    }
+
     /// Load configuration from heartio.config.json or create default if not exists
     pub async fn load() -> Result<Self> {
         let config_path = Self::config_path()?;
@@ -69,14 +587,30 @@ impl Config {
             let content = tokio::fs::read_to_string(&config_path)
                 .await
                 .context("Failed to read config file")?;
-            let config: Config = serde_json::from_str(&content)
-                .context("Failed to parse config file")?;
+            let invalid =
+                || format!("{} is not a valid HeartIO config file", config_path.display());
+            let mut value: serde_json::Value =
+                serde_json::from_str(&content).with_context(invalid)?;
+            let migrated = migrations::migrate(&mut value);
+            let mut config: Config = if migrated {
+                serde_json::from_value(value).with_context(invalid)?
+            } else {
+                // Parsing the text again keeps line numbers in errors
+                serde_json::from_str(&content).with_context(invalid)?
+            };
+            if migrated {
+                if let Err(e) = config.save().await {
+                    tracing::warn!("Failed to save migrated config: {:#}", e);
+                }
+            }
+            config.apply_log_entries_limit();
             tracing::info!("Loaded configuration from {}", config_path.display());
             Ok(config)
         } else {
-            let config = Self::default();
+            let mut config = Self::default();
             config.save().await?;
             tracing::info!("Created default configuration at {}", config_path.display());
+            config.apply_log_entries_limit();
             Ok(config)
         }
     }
@@ -93,21 +627,207 @@ impl Config {
         Ok(())
     }
 
+    /// Get the monitoring mode, Xiaomi Band takes priority over Apple Watch
+    pub fn mode(&self) -> MonitorMode {
+        if self.xiaomi_band.is_some_and(|enabled| enabled) {
+            MonitorMode::XiaomiBand
+        } else if self.apple_watch {
+            MonitorMode::AppleWatch
+        } else {
+            MonitorMode::Bluetooth
+        }
+    }
+
+    /// Set the monitoring mode
+    pub fn set_mode(&mut self, mode: MonitorMode) {
+        self.apple_watch = mode == MonitorMode::AppleWatch;
+        self.xiaomi_band = Some(mode == MonitorMode::XiaomiBand);
+    }
+
+    /// Get all enabled OSC endpoints, starting with OSC_HOST/OSC_PORT
+    pub fn osc_endpoints(&self) -> Vec<(String, u16)> {
+        let mut endpoints = vec![(self.osc_host.clone(), self.osc_port)];
+        for target in self.osc_targets.iter().filter(|target| target.enabled) {
+            let endpoint = (target.host.clone(), target.port);
+            if !endpoints.contains(&endpoint) {
+                endpoints.push(endpoint);
+            }
+        }
+        endpoints
+    }
+
+    /// Get the heart rate sources in priority order, defaults to the single configured mode
+    pub fn sources(&self) -> Vec<MonitorMode> {
+        let mut sources = Vec::new();
+        for source in &self.heart_rate_sources {
+            if !sources.contains(source) {
+                sources.push(*source);
+            }
+        }
+        if sources.is_empty() {
+            sources.push(self.mode());
+        }
+        sources
+    }
+
+    /// Check the configuration for mistakes serde can't catch, most serious first
+    pub fn validate(&self) -> Vec<ConfigWarning> {
+        use ConfigSeverity::*;
+        let mut warnings = Vec::new();
+
+        if self.osc_port == 0 {
+            warnings.push(ConfigWarning::new(Error, "OSC_PORT must not be 0"));
+        }
+        if !is_valid_host(&self.osc_host) {
+            warnings.push(ConfigWarning::new(
+                Error,
+                format!("OSC_HOST {:?} is neither an IP address nor a host name", self.osc_host),
+            ));
+        }
+        if let Some(addr) = &self.osc_bind_addr {
+            if addr.trim().parse::<std::net::IpAddr>().is_err() {
+                warnings.push(ConfigWarning::new(
+                    Error,
+                    format!("OSC_BIND_ADDR {:?} is not an IP address", addr),
+                ));
+            }
+        }
+
+        if self.osc_min_confidence > 100 {
+            warnings.push(ConfigWarning::new(
+                Warning,
+                format!(
+                    "OSC_MIN_CONFIDENCE {} is above 100, no reading is sent over OSC",
+                    self.osc_min_confidence
+                ),
+            ));
+        }
+        if self.ble_discovery_attempts == 0 {
+            warnings.push(ConfigWarning::new(
+                Warning,
+                "BLE_DISCOVERY_ATTEMPTS is 0, service discovery is still tried once",
+            ));
+        }
+
+        let mut thresholds: Vec<&String> = self.heart_rate_label.keys().collect();
+        thresholds.sort();
+        for threshold in thresholds {
+            if threshold.parse::<u32>().is_err() {
+                warnings.push(ConfigWarning::new(
+                    Warning,
+                    format!(
+                        "HEART_RATE_LABEL key {:?} is not a BPM value and is ignored",
+                        threshold
+                    ),
+                ));
+            }
+        }
+
+        let labels = self.heart_rate_label.values().flatten().chain(&self.default_labels);
+        let mut without_bpm: Vec<&String> =
+            labels.filter(|label| !label.contains("{{bpm")).collect();
+        without_bpm.sort();
+        without_bpm.dedup();
+        for label in without_bpm {
+            warnings.push(ConfigWarning::new(
+                Suggestion,
+                format!("Label {:?} doesn't show the heart rate, add {{{{bpm}}}}", label),
+            ));
+        }
+
+        if self.apple_watch && self.heart_rate_device_name.is_some() {
+            let sources = self.sources();
+            let message = if sources.contains(&MonitorMode::AppleWatch)
+                && sources.contains(&MonitorMode::Bluetooth)
+            {
+                "APPLE_WATCH and HEART_RATE_DEVICE_NAME are both set, both sources are started \
+                 in HEART_RATE_SOURCES priority order"
+            } else {
+                "APPLE_WATCH and HEART_RATE_DEVICE_NAME are both set, only the Apple Watch is \
+                 used unless both are listed in HEART_RATE_SOURCES"
+            };
+            warnings.push(ConfigWarning::new(Info, message));
+        }
+
+        warnings.sort_by(|a, b| b.severity.cmp(&a.severity));
+        warnings
+    }
+
+    /// Get the accepted BPM range
+    pub fn bpm_range(&self) -> BpmRange {
+        BpmRange {
+            min: self.min_bpm,
+            max: self.max_bpm,
+        }
+    }
+
+    /// Get the Xiaomi Band heart rate field, widths other than 2 bytes read a single byte
+    pub fn xiaomi_hr_field(&self) -> HeartRateField {
+        HeartRateField {
+            offset: self.xiaomi_hr_offset,
+            width: if self.xiaomi_hr_width == 2 { 2 } else { 1 },
+            big_endian: self.xiaomi_hr_big_endian,
+        }
+    }
+
+    /// Get the max heart rate, estimated as 220 - age when only the age is known
+    pub fn max_hr(&self) -> u32 {
+        self.user_max_hr
+            .or_else(|| self.user_age.map(|age| 220u32.saturating_sub(age)))
+            .unwrap_or(DEFAULT_MAX_HR)
+    }
+
+    /// Get the body data for the calorie estimate, `None` unless weight and age are known
+    pub fn calorie_profile(&self) -> Option<CalorieProfile> {
+        Some(CalorieProfile {
+            weight_kg: self.user_weight_kg?,
+            age: self.user_age?,
+            sex: self.user_sex.unwrap_or(UserSex::Other),
+        })
+    }
+
+    /// Get the heart rate zone boundaries, personalized by resting heart rate when it and the
+    /// user's max heart rate or age are known
+    pub fn zone_config(&self) -> ZoneConfig {
+        let max_hr_known = self.user_max_hr.is_some() || self.user_age.is_some();
+        match self.resting_hr {
+            Some(resting_hr) if max_hr_known && resting_hr < self.max_hr() => {
+                ZoneConfig::from_karvonen(resting_hr, self.max_hr())
+            }
+            _ => ZoneConfig::from_max_hr(self.max_hr()),
+        }
+    }
+
+    /// Bool parameters toggled once per beat, the heartbeat and the prefab beat parameter
+    pub fn heartbeat_params(&self) -> Vec<String> {
+        let prefab_beat = self
+            .osc_prefab_compat
+            .then(|| self.osc_prefab_beat_param.clone());
+        self.osc_heartbeat_param.iter().cloned().chain(prefab_beat).collect()
+    }
+
+    /// Map a heart rate onto 0.0-1.0 using the normalized parameter range
+    pub fn normalized_heart_rate(&self, bpm: u32) -> f32 {
+        let range = self.osc_normalized_max.saturating_sub(self.osc_normalized_min).max(1);
+        bpm.saturating_sub(self.osc_normalized_min).clamp(0, range) as f32 / range as f32
+    }
+
     /// Get heart rate text based on BPM and configured thresholds
-    pub fn get_heart_rate_text(&self, bpm: u32) -> Option<String> {
+    pub fn get_heart_rate_text(&self, ctx: &TemplateContext) -> Option<String> {
+        let bpm = ctx.bpm;
         // Find the appropriate threshold
-        let thresholds: Vec<u32> = self.heart_rate_label.keys()
-            .filter_map(|k| k.parse().ok())
+        let mut buckets: Vec<(u32, &Vec<String>)> = self.heart_rate_label.iter()
+            .filter_map(|(threshold, labels)| Some((threshold.parse().ok()?, labels)))
             .collect();
-        
-        let mut sorted_thresholds = thresholds.clone();
-        sorted_thresholds.sort();
-        
-        let threshold = sorted_thresholds.iter()
-            .find(|&&t| bpm < t)
-            .or_else(|| sorted_thresholds.last())?;
-        
-        let labels = self.heart_rate_label.get(&threshold.to_string())?;
+        buckets.sort_by_key(|(threshold, _)| *threshold);
+
+        // Readings at or above every threshold use DEFAULT_LABEL, older configs
+        // without it keep using the highest bucket (the "999" catch-all)
+        let labels = match buckets.iter().find(|(threshold, _)| bpm < *threshold) {
+            Some((_, labels)) => *labels,
+            None if !self.default_labels.is_empty() => &self.default_labels,
+            None => buckets.last()?.1,
+        };
         
         if labels.is_empty() {
             return None;
@@ -123,6 +843,464 @@ impl Config {
             &labels[index]
         };
         
-        Some(label.replace("{{bpm}}", &bpm.to_string()))
+        Some(render_template(label, ctx))
+    }
+}
+
+/// Values available to `{{placeholder}}`s in heart rate labels
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    pub bpm: u32,
+    pub avg: Option<f64>,
+    pub zone: Option<HeartRateZone>,
+    pub min: Option<u32>,
+    pub max: Option<u32>,
+    pub duration: Option<Duration>,
+    pub delta: Option<i64>,
+}
+
+/// Widest padding and most decimals a placeholder format spec may ask for
+const MAX_FORMAT_WIDTH: usize = 8;
+const MAX_FORMAT_DECIMALS: usize = 3;
+
+/// Emoji repeated by `{{hearts}}` when no other is given
+const DEFAULT_HEART: &str = "❤️";
+
+impl TemplateContext {
+    /// Get the replacement for a `name` or `name:spec` placeholder, `None` if unknown
+    fn value(&self, placeholder: &str) -> Option<String> {
+        let (name, spec) = match placeholder.split_once(':') {
+            Some((name, spec)) => (name.trim(), Some(spec.trim())),
+            None => (placeholder, None),
+        };
+        match name {
+            "bpm" => Some(pad_number(self.bpm, spec)),
+            "avg" => self.avg.map(|avg| {
+                let decimals = spec
+                    .and_then(|spec| spec.strip_prefix('.')?.parse::<usize>().ok())
+                    .map_or(1, |decimals| decimals.min(MAX_FORMAT_DECIMALS));
+                format!("{:.*}", decimals, avg)
+            }),
+            "zone" => self.zone.map(|zone| zone.label().to_string()),
+            "hearts" => self.zone.map(|zone| {
+                let heart = spec.filter(|spec| !spec.is_empty()).unwrap_or(DEFAULT_HEART);
+                heart.repeat(zone.index() + 1)
+            }),
+            "min" => self.min.map(|min| pad_number(min, spec)),
+            "max" => self.max.map(|max| pad_number(max, spec)),
+            "duration" => self.duration.map(|duration| {
+                let secs = duration.as_secs();
+                format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+            }),
+            "delta" => self.delta.map(|delta| format!("{:+}", delta)),
+            _ => None,
+        }
+    }
+}
+
+/// Format a number with a `0N` (zero padded) or `N` (space padded) width spec, ignoring others
+fn pad_number(value: u32, spec: Option<&str>) -> String {
+    let Some(width) = spec.and_then(|spec| spec.parse::<usize>().ok()) else {
+        return value.to_string();
+    };
+    let width = width.min(MAX_FORMAT_WIDTH);
+    if spec.is_some_and(|spec| spec.starts_with('0')) {
+        format!("{:0width$}", value)
+    } else {
+        format!("{:width$}", value)
+    }
+}
+
+/// Check whether a host is an IP address or a syntactically valid host name
+fn is_valid_host(host: &str) -> bool {
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        return true;
+    }
+    let host = host.strip_suffix('.').unwrap_or(host);
+    !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Replace `{{placeholder}}`s in a template, unknown placeholders are left unchanged
+pub fn render_template(template: &str, ctx: &TemplateContext) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let placeholder = &rest[start + 2..start + 2 + end];
+        output.push_str(&rest[..start]);
+        match ctx.value(placeholder.trim()) {
+            Some(value) => output.push_str(&value),
+            None => output.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &rest[start + 2 + end + 2..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Window position and size remembered between runs
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowPrefs {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl WindowPrefs {
+    /// Get the path to the window prefs file (next to the config file)
+    pub fn path() -> Result<PathBuf> {
+        Ok(Config::config_path()?.with_file_name("heartio.window.json"))
+    }
+
+    /// Load window prefs, returns None if missing or unreadable
+    pub fn load() -> Option<Self> {
+        let path = Self::path().ok()?;
+        let content = std::fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(prefs) => Some(prefs),
+            Err(e) => {
+                tracing::warn!("Ignoring invalid window prefs at {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Save window prefs (synchronous, called while the GUI exits)
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        let content = serde_json::to_string_pretty(self)
+            .context("Failed to serialize window prefs")?;
+        std::fs::write(&path, content).context("Failed to write window prefs")?;
+        tracing::debug!("Saved window prefs to {}", path.display());
+        Ok(())
+    }
+
+    /// Keep the window fully inside a screen of the given size
+    pub fn clamp_to_screen(&self, screen_width: f32, screen_height: f32) -> Self {
+        let width = self.width.min(screen_width);
+        let height = self.height.min(screen_height);
+        Self {
+            x: self.x.clamp(0.0, screen_width - width),
+            y: self.y.clamp(0.0, screen_height - height),
+            width,
+            height,
+        }
+    }
+}
+
+/// Heart rate sharing between two HeartIO instances, e.g. `{"MODE": "Server", "PORT": 2334}`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "MODE", rename_all_fields = "SCREAMING_SNAKE_CASE")]
+pub enum HeartRateShareMode {
+    #[default]
+    None,
+    /// Send the local heart rate to every connected instance
+    Server { port: u16 },
+    /// Receive the heart rate of a remote instance
+    Client { host: String, port: u16 },
+}
+
+/// GUI color theme
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AppTheme {
+    #[default]
+    System,
+    Dark,
+    Light,
+}
+
+impl AppTheme {
+    pub const ALL: [AppTheme; 3] = [AppTheme::System, AppTheme::Dark, AppTheme::Light];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AppTheme::System => "System",
+            AppTheme::Dark => "Dark",
+            AppTheme::Light => "Light",
+        }
+    }
+
+    /// Check whether the OS prefers dark mode, defaulting to dark when unknown
+    pub fn system_prefers_dark() -> bool {
+        !matches!(dark_light::detect(), dark_light::Mode::Light)
+    }
+}
+
+/// GUI preferences remembered between runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UiPrefs {
+    #[serde(default)]
+    pub theme: AppTheme,
+}
+
+impl UiPrefs {
+    /// Get the path to the UI prefs file (next to the config file)
+    pub fn path() -> Result<PathBuf> {
+        Ok(Config::config_path()?.with_file_name("heartio.prefs.json"))
+    }
+
+    /// Load UI prefs, falling back to defaults if missing or unreadable
+    pub fn load() -> Self {
+        let Ok(path) = Self::path() else {
+            return Self::default();
+        };
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        match serde_json::from_str(&content) {
+            Ok(prefs) => prefs,
+            Err(e) => {
+                tracing::warn!("Ignoring invalid UI prefs at {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Save UI prefs
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        let content =
+            serde_json::to_string_pretty(self).context("Failed to serialize UI prefs")?;
+        std::fs::write(&path, content).context("Failed to write UI prefs")?;
+        tracing::debug!("Saved UI prefs to {}", path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Config with single-label buckets "<threshold>" and no DEFAULT_LABEL
+    fn config_with(labels: &[(&str, &[&str])]) -> Config {
+        let heart_rate_label = labels
+            .iter()
+            .map(|(threshold, labels)| {
+                (threshold.to_string(), labels.iter().map(|l| l.to_string()).collect())
+            })
+            .collect();
+        Config {
+            heart_rate_label,
+            default_labels: Vec::new(),
+            ..Config::default()
+        }
+    }
+
+    fn text(config: &Config, bpm: u32) -> Option<String> {
+        config.get_heart_rate_text(&TemplateContext {
+            bpm,
+            ..Default::default()
+        })
+    }
+
+    fn three_buckets() -> Config {
+        config_with(&[("70", &["low"]), ("100", &["mid"]), ("150", &["high"])])
+    }
+
+    #[test]
+    fn below_lowest_threshold() {
+        assert_eq!(text(&three_buckets(), 50).as_deref(), Some("low"));
+    }
+
+    #[test]
+    fn zero_bpm_uses_lowest_bucket() {
+        assert_eq!(text(&three_buckets(), 0).as_deref(), Some("low"));
+    }
+
+    #[test]
+    fn just_below_threshold_stays_in_bucket() {
+        assert_eq!(text(&three_buckets(), 69).as_deref(), Some("low"));
+        assert_eq!(text(&three_buckets(), 99).as_deref(), Some("mid"));
+    }
+
+    #[test]
+    fn equal_to_lowest_threshold_moves_up() {
+        // Thresholds are "less than", so the boundary belongs to the next bucket
+        assert_eq!(text(&three_buckets(), 70).as_deref(), Some("mid"));
+    }
+
+    #[test]
+    fn equal_to_middle_threshold_moves_up() {
+        assert_eq!(text(&three_buckets(), 100).as_deref(), Some("high"));
+    }
+
+    #[test]
+    fn equal_to_highest_threshold_uses_highest_bucket() {
+        assert_eq!(text(&three_buckets(), 150).as_deref(), Some("high"));
+    }
+
+    #[test]
+    fn above_highest_threshold_uses_highest_bucket() {
+        assert_eq!(text(&three_buckets(), 220).as_deref(), Some("high"));
+    }
+
+    #[test]
+    fn above_highest_threshold_prefers_default_label() {
+        let mut config = three_buckets();
+        config.default_labels = vec!["default {{bpm}}".to_string()];
+        assert_eq!(text(&config, 150).as_deref(), Some("default 150"));
+        assert_eq!(text(&config, 149).as_deref(), Some("high"));
+    }
+
+    #[test]
+    fn empty_label_map() {
+        assert_eq!(text(&config_with(&[]), 80), None);
+    }
+
+    #[test]
+    fn empty_label_map_with_default_label() {
+        let mut config = config_with(&[]);
+        config.default_labels = vec!["{{bpm}}".to_string()];
+        assert_eq!(text(&config, 80).as_deref(), Some("80"));
+    }
+
+    #[test]
+    fn single_entry_map() {
+        let config = config_with(&[("100", &["only {{bpm}}"])]);
+        assert_eq!(text(&config, 60).as_deref(), Some("only 60"));
+        assert_eq!(text(&config, 120).as_deref(), Some("only 120"));
+    }
+
+    #[test]
+    fn bucket_without_labels() {
+        let config = config_with(&[("70", &[]), ("100", &["mid"])]);
+        assert_eq!(text(&config, 60), None);
+        assert_eq!(text(&config, 80).as_deref(), Some("mid"));
+    }
+
+    #[test]
+    fn single_label_is_always_chosen() {
+        let config = config_with(&[("100", &["one"])]);
+        for _ in 0..20 {
+            assert_eq!(text(&config, 80).as_deref(), Some("one"));
+        }
+    }
+
+    #[test]
+    fn multiple_labels_are_picked_randomly() {
+        let config = config_with(&[("100", &["a", "b", "c"])]);
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..200 {
+            seen.insert(text(&config, 80).unwrap());
+        }
+        // 200 picks missing one of three labels is practically impossible
+        assert_eq!(seen.len(), 3);
+    }
+
+    #[test]
+    fn non_numeric_threshold_is_ignored() {
+        let config = config_with(&[("abc", &["bad"]), ("100", &["good"])]);
+        assert_eq!(text(&config, 80).as_deref(), Some("good"));
+        assert_eq!(text(&config, 120).as_deref(), Some("good"));
+    }
+
+    #[test]
+    fn only_non_numeric_thresholds() {
+        let config = config_with(&[("abc", &["bad"]), ("-5", &["negative"])]);
+        assert_eq!(text(&config, 80), None);
+    }
+
+    #[test]
+    fn thresholds_sort_numerically_not_lexically() {
+        // "100" < "70" as strings, the lookup must still treat 70 as the lower bound
+        let config = config_with(&[("100", &["mid"]), ("70", &["low"])]);
+        assert_eq!(text(&config, 65).as_deref(), Some("low"));
+        assert_eq!(text(&config, 85).as_deref(), Some("mid"));
+    }
+
+    fn render(template: &str, zone: Option<HeartRateZone>) -> String {
+        let ctx = TemplateContext {
+            bpm: 85,
+            avg: Some(84.25),
+            zone,
+            ..Default::default()
+        };
+        render_template(template, &ctx)
+    }
+
+    #[test]
+    fn bpm_format_specs() {
+        assert_eq!(render("{{bpm}}", None), "85");
+        assert_eq!(render("{{bpm:03}}", None), "085");
+        assert_eq!(render("{{bpm:4}}", None), "  85");
+        assert_eq!(render("{{avg:.2}}", None), "84.25");
+    }
+
+    #[test]
+    fn unknown_format_spec_is_ignored() {
+        assert_eq!(render("{{bpm:x}}", None), "85");
+        assert_eq!(render("{{avg:zz}}", None), "84.2");
+    }
+
+    #[test]
+    fn hearts_scale_with_zone() {
+        assert_eq!(render("{{hearts}}", Some(HeartRateZone::Rest)), "❤️");
+        assert_eq!(render("{{hearts:💕}}", Some(HeartRateZone::Cardio)), "💕💕💕");
+        // Without a zone the placeholder stays as written
+        assert_eq!(render("{{hearts}}", None), "{{hearts}}");
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        let warnings = Config::default().validate();
+        assert!(warnings.iter().all(|w| w.severity < ConfigSeverity::Warning), "{:?}", warnings);
+    }
+
+    #[test]
+    fn validate_reports_mistakes_most_serious_first() {
+        let mut config = config_with(&[("70", &["{{bpm}}"]), ("fast", &["Running!"])]);
+        config.osc_port = 0;
+        config.osc_host = "bad host".to_string();
+        config.ble_discovery_attempts = 0;
+
+        let severities: Vec<ConfigSeverity> =
+            config.validate().iter().map(|w| w.severity).collect();
+        assert_eq!(
+            severities,
+            [
+                ConfigSeverity::Error,
+                ConfigSeverity::Error,
+                ConfigSeverity::Warning,
+                ConfigSeverity::Warning,
+                ConfigSeverity::Suggestion
+            ]
+        );
+    }
+
+    #[test]
+    fn valid_hosts() {
+        assert!(is_valid_host("127.0.0.1"));
+        assert!(is_valid_host("::1"));
+        assert!(is_valid_host("quest-3.local"));
+        assert!(!is_valid_host(""));
+        assert!(!is_valid_host("bad host"));
+        assert!(!is_valid_host("-quest.local"));
+    }
+
+    #[test]
+    fn prefab_compat_adds_beat_param() {
+        let config = Config {
+            osc_heartbeat_param: Some("HeartBeat".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(config.heartbeat_params(), ["HeartBeat"]);
+
+        let config = Config {
+            osc_prefab_compat: true,
+            ..config
+        };
+        assert_eq!(config.heartbeat_params(), ["HeartBeat", "isHRBeat"]);
     }
 }
@@ -0,0 +1,101 @@
+// Upgrades of heartio.config.json files written by older HeartIO versions
+use serde_json::Value;
+
+/// Key holding the schema version in the config file
+pub const SCHEMA_VERSION_KEY: &str = "SCHEMA_VERSION";
+
+/// Migration `i` upgrades a config from schema version `i + 1` to `i + 2`
+pub const MIGRATIONS: &[fn(&mut Value)] = &[move_catch_all_to_default_label];
+
+/// Schema version of configs written by this build
+pub const CURRENT_SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32 + 1;
+
+/// Upgrade a config to the current schema version, returns whether anything changed
+pub fn migrate(config: &mut Value) -> bool {
+    let Some(object) = config.as_object_mut() else {
+        return false;
+    };
+    let version = object
+        .get(SCHEMA_VERSION_KEY)
+        .and_then(Value::as_u64)
+        .map_or(1, |version| version.max(1) as u32);
+    if version > CURRENT_SCHEMA_VERSION {
+        tracing::warn!(
+            "Config schema version {} is newer than supported ({}), unknown settings are ignored",
+            version,
+            CURRENT_SCHEMA_VERSION
+        );
+    }
+    if version >= CURRENT_SCHEMA_VERSION {
+        return false;
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize - 1) {
+        migration(config);
+        tracing::info!("Migrated config from schema version {} to {}", index + 1, index + 2);
+    }
+    config[SCHEMA_VERSION_KEY] = CURRENT_SCHEMA_VERSION.into();
+    true
+}
+
+/// Version 2: the highest HEART_RATE_LABEL bucket (usually "999") becomes DEFAULT_LABEL,
+/// which it already acted as for readings above every threshold
+fn move_catch_all_to_default_label(config: &mut Value) {
+    let has_default = config
+        .get("DEFAULT_LABEL")
+        .and_then(Value::as_array)
+        .is_some_and(|labels| !labels.is_empty());
+    if has_default {
+        return;
+    }
+    let Some(buckets) = config.get_mut("HEART_RATE_LABEL").and_then(Value::as_object_mut) else {
+        return;
+    };
+    let highest = buckets
+        .keys()
+        .filter_map(|threshold| Some((threshold.parse::<u32>().ok()?, threshold.clone())))
+        .max();
+    if let Some((_, threshold)) = highest {
+        let labels = buckets.remove(&threshold).unwrap_or_default();
+        config["DEFAULT_LABEL"] = labels;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn unversioned_config_is_upgraded() {
+        let mut config = json!({
+            "HEART_RATE_LABEL": { "70": ["low"], "999": ["high"] }
+        });
+        assert!(migrate(&mut config));
+        assert_eq!(
+            config,
+            json!({
+                "HEART_RATE_LABEL": { "70": ["low"] },
+                "DEFAULT_LABEL": ["high"],
+                "SCHEMA_VERSION": CURRENT_SCHEMA_VERSION
+            })
+        );
+    }
+
+    #[test]
+    fn existing_default_label_is_kept() {
+        let mut config = json!({
+            "HEART_RATE_LABEL": { "70": ["low"], "999": ["high"] },
+            "DEFAULT_LABEL": ["default"]
+        });
+        migrate(&mut config);
+        assert_eq!(config["HEART_RATE_LABEL"]["999"], json!(["high"]));
+        assert_eq!(config["DEFAULT_LABEL"], json!(["default"]));
+    }
+
+    #[test]
+    fn current_config_is_unchanged() {
+        let mut config = json!({ "SCHEMA_VERSION": CURRENT_SCHEMA_VERSION });
+        assert!(!migrate(&mut config));
+    }
+}
@@ -1,8 +1,54 @@
 // Database management for HeartIO
 use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
-use sqlx::{sqlite::SqlitePool, Row};
-use std::path::PathBuf;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use directories::ProjectDirs;
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow, SqliteSynchronous},
+    Row,
+};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Attempts at opening the database and at the first write, the file may be locked briefly,
+/// e.g. by an antivirus scan or another instance
+const RETRY_ATTEMPTS: u32 = 4;
+/// Delay before the first retry, doubled after each attempt
+const RETRY_BACKOFF: Duration = Duration::from_millis(250);
+/// How long a statement waits on a locked database before failing. Short, since the retries
+/// run on the monitor loop and would stack on sqlx's 5s default: about 4s at worst this way.
+const BUSY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Run `operation` until it succeeds or the attempts are used up, backing off in between
+pub async fn with_retries<T, F, Fut>(what: &str, mut operation: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut delay = RETRY_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < RETRY_ATTEMPTS => {
+                tracing::warn!(
+                    "{} failed (attempt {}/{}), retrying in {}ms: {:#}",
+                    what,
+                    attempt,
+                    RETRY_ATTEMPTS,
+                    delay.as_millis(),
+                    e
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct HeartRateRecord {
@@ -11,54 +57,136 @@ pub struct HeartRateRecord {
     pub created_at: DateTime<Utc>,
 }
 
+impl HeartRateRecord {
+    fn from_row(row: &SqliteRow) -> Self {
+        Self {
+            id: row.get("id"),
+            bpm: row.get("bpm"),
+            created_at: row.get("created_at"),
+        }
+    }
+}
+
+/// Cheap to clone, clones share the connection pool
+#[derive(Clone)]
 pub struct Database {
     pool: SqlitePool,
+    /// `None` for the in-memory database, which is never backed up
+    path: Option<PathBuf>,
+    /// Backups kept next to the database file, 0 disables backups
+    backup_count: usize,
 }
 
 impl Database {
-    /// Create a new database connection
-    pub async fn new() -> Result<Self> {
-        let db_path = Self::get_db_path()?;
+    /// Create a new database connection, using the configured path if set
+    pub async fn new(configured_path: Option<&str>, backup_count: u8) -> Result<Self> {
+        let db_path = Self::get_db_path(configured_path)?;
         
-        // Create cache directory if it doesn't exist
-        if let Some(parent) = db_path.parent() {
+        // Create database directory if it doesn't exist
+        if let Some(parent) = db_path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
             tokio::fs::create_dir_all(parent).await
-                .with_context(|| format!("Failed to create cache directory: {}", parent.display()))?;
+                .with_context(|| format!("Failed to create database directory: {}", parent.display()))?;
         }
 
         tracing::info!("Attempting to connect to database at: {}", db_path.display());
         
         let database_url = format!("sqlite:{}?mode=rwc", db_path.display());
-        let pool = SqlitePool::connect(&database_url).await
-            .with_context(|| format!("Failed to connect to SQLite database at {}", db_path.display()))?;
+        let options = SqliteConnectOptions::from_str(&database_url)
+            .context("Invalid database path")?
+            // Per connection settings, so they are applied to every connection of the pool.
+            // NORMAL only syncs at WAL checkpoints: a power loss may drop the last
+            // transactions, but never corrupts the database, and inserts skip an fsync each
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(BUSY_TIMEOUT)
+            // Keep sorts and temporary indices of the history queries off the disk
+            .pragma("temp_store", "MEMORY")
+            // Negative sizes are in KiB, 64 MB of page cache keeps the history panel fast
+            .pragma("cache_size", "-64000");
+        let db = with_retries("Opening the database", || async {
+            let pool = SqlitePool::connect_with(options.clone()).await
+                .with_context(|| {
+                    format!("Failed to connect to SQLite database at {}", db_path.display())
+                })?;
+
+            let db = Self {
+                pool,
+                path: Some(db_path.clone()),
+                backup_count: backup_count.into(),
+            };
+            db.init_tables().await
+                .context("Failed to initialize database tables")?;
+            Ok(db)
+        })
+        .await?;
+
+        tracing::info!("Database initialized successfully at {}", db_path.display());
+        Ok(db)
+    }
+
+    /// Create a database that only lives in memory, nothing is written to disk
+    pub async fn in_memory() -> Result<Self> {
+        // Every connection gets its own memory database, so keep exactly one alive
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .context("Failed to create in-memory SQLite database")?;
 
-        let db = Self { pool };
+        let db = Self {
+            pool,
+            path: None,
+            backup_count: 0,
+        };
         db.init_tables().await
             .context("Failed to initialize database tables")?;
-        
-        tracing::info!("Database initialized successfully at {}", db_path.display());
+
+        tracing::info!("In-memory database initialized, heart rate history will not be saved");
         Ok(db)
     }
 
     /// Get the path to the database file
-    fn get_db_path() -> Result<PathBuf> {
+    pub fn get_db_path(configured_path: Option<&str>) -> Result<PathBuf> {
+        if let Some(path) = configured_path {
+            return Ok(PathBuf::from(path));
+        }
+
+        // Keep using the database next to the executable if an older version created one
         let exe_path = std::env::current_exe()
             .context("Failed to get current executable path")?;
         let exe_dir = exe_path.parent()
             .context("Failed to get executable directory")?;
-        let cache_dir = exe_dir.join("cache");
-        Ok(cache_dir.join("data.sqlite"))
+        let legacy_path = exe_dir.join("cache").join("data.sqlite");
+        if legacy_path.exists() {
+            return Ok(legacy_path);
+        }
+
+        let project_dirs = ProjectDirs::from("", "", "HeartIO")
+            .context("Failed to determine the user data directory")?;
+        Ok(project_dirs.data_dir().join("data.sqlite"))
     }
 
     /// Initialize database tables
     async fn init_tables(&self) -> Result<()> {
+        // WAL lets the history and stats queries read while the monitor inserts, instead of
+        // blocking on the writer. The mode is stored in the file, at the cost of the -wal and
+        // -shm files next to it. The in-memory database stays in its own "memory" mode.
+        let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode=WAL")
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to enable WAL mode")?;
+        tracing::debug!("Database journal mode: {}", journal_mode);
+
         // Create heart_rate table
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS heart_rate (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 bpm INTEGER NOT NULL,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                session_id INTEGER REFERENCES session (id)
             )
             "#,
         )
@@ -77,16 +205,68 @@ impl Database {
         .await
         .context("Failed to create index on heart_rate table")?;
 
+        // Sessions group readings, a new one starts at launch and on every reset
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS session (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                started_at DATETIME DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .context("Failed to create session table")?;
+
+        // Databases created by older versions lack the session column
+        let has_session_column = sqlx::query(
+            "SELECT 1 FROM pragma_table_info('heart_rate') WHERE name = 'session_id'"
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to inspect heart_rate table")?
+        .is_some();
+        if !has_session_column {
+            self.backup_before("upgrading the database schema").await;
+            sqlx::query(
+                "ALTER TABLE heart_rate ADD COLUMN session_id INTEGER REFERENCES session (id)"
+            )
+            .execute(&self.pool)
+            .await
+            .context("Failed to add session column to heart_rate table")?;
+        }
+
         tracing::info!("Database tables initialized");
         Ok(())
     }
 
-    /// Insert a new heart rate record
-    pub async fn insert_heart_rate(&self, bpm: i32) -> Result<i64> {
+    /// Start a new session and return its id
+    pub async fn start_session(&self) -> Result<i64> {
+        let result = sqlx::query("INSERT INTO session DEFAULT VALUES")
+            .execute(&self.pool)
+            .await
+            .context("Failed to start database session")?;
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Insert a new heart rate record, stamped with the measurement time if known
+    pub async fn insert_heart_rate(
+        &self,
+        bpm: i32,
+        measured_at: Option<DateTime<Utc>>,
+        session_id: Option<i64>,
+    ) -> Result<i64> {
+        // Same format as CURRENT_TIMESTAMP so time range queries keep working
+        let created_at = measured_at.map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string());
         let result = sqlx::query(
-            "INSERT INTO heart_rate (bpm) VALUES (?)"
+            r#"
+            INSERT INTO heart_rate (bpm, created_at, session_id)
+            VALUES (?, COALESCE(?, CURRENT_TIMESTAMP), ?)
+            "#,
         )
         .bind(bpm)
+        .bind(created_at)
+        .bind(session_id)
         .execute(&self.pool)
         .await
         .context("Failed to insert heart rate record")?;
@@ -96,32 +276,73 @@ impl Database {
         Ok(id)
     }
 
-    /// Get recent heart rate records
-    pub async fn get_recent_heart_rates(&self, limit: i32) -> Result<Vec<HeartRateRecord>> {
+    /// Get up to `limit` records older than `before_id`, newest first
+    ///
+    /// Also returns the `before_id` for the next page, `None` once the oldest record is reached.
+    /// `id` is the rowid, so paging walks the table's own B-tree without a separate index.
+    pub async fn get_heart_rates_page(
+        &self,
+        before_id: Option<i64>,
+        limit: u32,
+    ) -> Result<(Vec<HeartRateRecord>, Option<i64>)> {
         let rows = sqlx::query(
             r#"
-            SELECT id, bpm, created_at 
-            FROM heart_rate 
-            ORDER BY created_at DESC 
+            SELECT id, bpm, created_at
+            FROM heart_rate
+            WHERE ? IS NULL OR id < ?
+            ORDER BY id DESC
             LIMIT ?
             "#,
         )
+        .bind(before_id)
+        .bind(before_id)
         .bind(limit)
         .fetch_all(&self.pool)
         .await
-        .context("Failed to fetch recent heart rate records")?;
+        .context("Failed to fetch heart rate records")?;
 
-        let records = rows.into_iter().map(|row| {
-            HeartRateRecord {
-                id: row.get("id"),
-                bpm: row.get("bpm"),
-                created_at: row.get("created_at"),
-            }
-        }).collect();
+        let records: Vec<HeartRateRecord> = rows.iter().map(HeartRateRecord::from_row).collect();
+        // A short page means nothing older is left
+        let next_before_id = if records.len() < limit as usize {
+            None
+        } else {
+            records.last().map(|record| record.id)
+        };
+        Ok((records, next_before_id))
+    }
 
+    /// Get the newest `limit` records, oldest first
+    pub async fn get_recent_heart_rates(&self, limit: u32) -> Result<Vec<HeartRateRecord>> {
+        let (mut records, _) = self.get_heart_rates_page(None, limit).await?;
+        records.reverse();
         Ok(records)
     }
 
+    /// Get up to `limit` records newer than `after_id`, oldest first
+    #[cfg(test)]
+    pub async fn get_heart_rates_after(
+        &self,
+        after_id: i64,
+        limit: u32,
+    ) -> Result<Vec<HeartRateRecord>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, bpm, created_at
+            FROM heart_rate
+            WHERE id > ?
+            ORDER BY id ASC
+            LIMIT ?
+            "#,
+        )
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch heart rate records")?;
+
+        Ok(rows.iter().map(HeartRateRecord::from_row).collect())
+    }
+
     /// Get heart rate statistics
     pub async fn get_stats(&self) -> Result<HeartRateStats> {
         let row = sqlx::query(
@@ -139,14 +360,187 @@ impl Database {
         .await
         .context("Failed to fetch heart rate statistics")?;
 
+        let total_records: i32 = row.get("total_records");
         Ok(HeartRateStats {
-            total_records: row.get("total_records"),
+            total_records,
             avg_bpm: row.get::<Option<f64>, _>("avg_bpm").unwrap_or(0.0),
             min_bpm: row.get::<Option<i32>, _>("min_bpm").unwrap_or(0),
             max_bpm: row.get::<Option<i32>, _>("max_bpm").unwrap_or(0),
+            p50_bpm: self.percentile_24h(total_records.into(), 50.0).await?,
+            p95_bpm: self.percentile_24h(total_records.into(), 95.0).await?,
+            p99_bpm: self.percentile_24h(total_records.into(), 99.0).await?,
         })
     }
 
+    /// Percentile `p` (0-100) of the `count` readings of the last 24 hours
+    async fn percentile_24h(&self, count: i64, p: f64) -> Result<f64> {
+        let Some((offset, fraction)) = percentile_rank(count, p) else {
+            return Ok(0.0);
+        };
+        // percentile() is an optional extension the bundled SQLite is built without, so read
+        // just the two closest ranks in SQL and interpolate here
+        let values: Vec<i32> = sqlx::query_scalar(
+            r#"
+            SELECT bpm
+            FROM heart_rate
+            WHERE created_at >= datetime('now', '-24 hours')
+            ORDER BY bpm
+            LIMIT 2 OFFSET ?
+            "#,
+        )
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch heart rates for percentiles")?;
+
+        let Some(&lower) = values.first() else {
+            return Ok(0.0);
+        };
+        let upper = values.get(1).copied().unwrap_or(lower);
+        Ok(lower as f64 + (upper - lower) as f64 * fraction)
+    }
+
+    /// Readings per local calendar day over the last `days` days including today, oldest first
+    ///
+    /// Days without readings are included with a count of 0.
+    pub async fn get_daily_summary(&self, days: u32) -> Result<Vec<DailySummary>> {
+        // created_at is UTC, so convert both ways to group by the user's own days
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                strftime('%Y-%m-%d', created_at, 'localtime') AS day,
+                COUNT(*) AS count,
+                AVG(bpm) AS avg_bpm,
+                MIN(bpm) AS min_bpm,
+                MAX(bpm) AS max_bpm,
+                CAST((julianday(MAX(created_at)) - julianday(MIN(created_at))) * 86400 AS INTEGER)
+                    AS total_duration_secs
+            FROM heart_rate
+            WHERE created_at >= datetime(date('now', 'localtime', ?), 'utc')
+            GROUP BY day
+            ORDER BY day
+            "#,
+        )
+        .bind(format!("-{} days", days.saturating_sub(1)))
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to fetch the daily heart rate summary")?;
+
+        let mut by_day: HashMap<NaiveDate, DailySummary> = rows
+            .iter()
+            .filter_map(|row| {
+                let date = NaiveDate::parse_from_str(row.get("day"), "%Y-%m-%d").ok()?;
+                Some((
+                    date,
+                    DailySummary {
+                        date,
+                        count: row.get::<i64, _>("count") as u32,
+                        avg_bpm: row.get("avg_bpm"),
+                        min_bpm: row.get("min_bpm"),
+                        max_bpm: row.get("max_bpm"),
+                        total_duration_secs: row.get("total_duration_secs"),
+                    },
+                ))
+            })
+            .collect();
+        let today = chrono::Local::now().date_naive();
+        Ok((0..days)
+            .rev()
+            .map(|days_ago| {
+                let date = today - chrono::Days::new(days_ago.into());
+                by_day.remove(&date).unwrap_or_else(|| DailySummary::empty(date))
+            })
+            .collect())
+    }
+
+    /// Write a consistent copy of the database to `dest`, which must not exist yet
+    pub async fn backup(&self, dest: &Path) -> Result<()> {
+        sqlx::query("VACUUM INTO ?")
+            .bind(dest.to_string_lossy().into_owned())
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("Failed to back up the database to {}", dest.display()))?;
+        Ok(())
+    }
+
+    /// Insert a reading and read it back inside a transaction that is rolled back
+    pub async fn self_test(&self) -> Result<()> {
+        let mut transaction = self.pool.begin().await.context("Failed to start a transaction")?;
+        let id = sqlx::query("INSERT INTO heart_rate (bpm) VALUES (?)")
+            .bind(72)
+            .execute(&mut *transaction)
+            .await
+            .context("Failed to write a heart rate")?
+            .last_insert_rowid();
+        let bpm: i32 = sqlx::query_scalar("SELECT bpm FROM heart_rate WHERE id = ?")
+            .bind(id)
+            .fetch_one(&mut *transaction)
+            .await
+            .context("Failed to read the heart rate back")?;
+        transaction.rollback().await.context("Failed to roll back the test write")?;
+        anyhow::ensure!(bpm == 72, "Read back {} instead of the written heart rate", bpm);
+        Ok(())
+    }
+
+    /// Import heart rates exported from the Node.js version, keeping their timestamps
+    ///
+    /// Rows are inserted in one transaction, malformed ones are skipped and counted.
+    pub async fn import_csv(&self, path: &Path) -> Result<ImportSummary> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let (rows, skipped) = parse_import_csv(&content);
+
+        self.backup_before("importing heart rates").await;
+        let mut transaction = self.pool.begin().await.context("Failed to start the import")?;
+        for (bpm, created_at) in &rows {
+            sqlx::query("INSERT INTO heart_rate (bpm, created_at) VALUES (?, ?)")
+                .bind(bpm)
+                .bind(created_at)
+                .execute(&mut *transaction)
+                .await
+                .context("Failed to import heart rate")?;
+        }
+        transaction.commit().await.context("Failed to save the imported heart rates")?;
+
+        tracing::info!(
+            "Imported {} heart rates from {}, skipped {} malformed rows",
+            rows.len(),
+            path.display(),
+            skipped
+        );
+        Ok(ImportSummary {
+            imported: rows.len(),
+            skipped,
+        })
+    }
+
+    /// Back up the database file before a risky change, keeping the newest `backup_count` copies.
+    /// A failed backup is only logged, it doesn't hold up the change.
+    async fn backup_before(&self, reason: &str) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if self.backup_count == 0 {
+            return;
+        }
+
+        // Basic ISO 8601, colons are not allowed in Windows file names
+        let timestamp = Utc::now().format("%Y%m%dT%H%M%S%.3fZ");
+        let mut dest = path.clone().into_os_string();
+        dest.push(format!(".backup.{}", timestamp));
+        let dest = PathBuf::from(dest);
+        if let Err(e) = self.backup(&dest).await {
+            tracing::warn!("Failed to back up the database before {}: {:#}", reason, e);
+            return;
+        }
+        tracing::info!("Backed up the database to {} before {}", dest.display(), reason);
+
+        if let Err(e) = prune_backups(path, self.backup_count).await {
+            tracing::warn!("Failed to prune old database backups: {:#}", e);
+        }
+    }
+
     /// Close database connection
     pub async fn close(self) {
         self.pool.close().await;
@@ -154,10 +548,310 @@ impl Database {
     }
 }
 
-#[derive(Debug)]
+/// Delete all but the newest `keep` backups of a database file
+async fn prune_backups(path: &Path, keep: usize) -> Result<()> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Ok(());
+    };
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let prefix = format!("{}.backup.", name.to_string_lossy());
+
+    let mut backups = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir)
+        .await
+        .with_context(|| format!("Failed to list database backups in {}", dir.display()))?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            backups.push(entry.path());
+        }
+    }
+
+    // Timestamps sort chronologically by name
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for old in &backups[..excess] {
+        match tokio::fs::remove_file(old).await {
+            Ok(()) => tracing::info!("Removed old database backup {}", old.display()),
+            Err(e) => {
+                tracing::warn!("Failed to remove old database backup {}: {}", old.display(), e)
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Row counts of a CSV import
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub skipped: usize,
+}
+
+/// Parse a `heart_rate` table export into BPM and `created_at` pairs, plus the skipped row count
+///
+/// Columns are found by a `bpm` and `created_at` header, without one the `SELECT *` order
+/// `id,bpm,created_at` is assumed. Timestamps are SQLite UTC times or RFC 3339.
+fn parse_import_csv(content: &str) -> (Vec<(i32, String)>, usize) {
+    let mut lines = content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let field = |value: &str| value.trim().trim_matches('"').to_string();
+
+    let mut columns = (1, 2);
+    let mut first = lines.next();
+    if let Some((_, header)) = first {
+        let names: Vec<String> = header.split(',').map(|name| field(name).to_lowercase()).collect();
+        let position = |wanted: &str| names.iter().position(|name| name == wanted);
+        if let (Some(bpm), Some(created_at)) = (position("bpm"), position("created_at")) {
+            columns = (bpm, created_at);
+            first = None;
+        }
+    }
+
+    let mut rows = Vec::new();
+    let mut skipped = 0;
+    for (index, line) in first.into_iter().chain(lines) {
+        let fields: Vec<String> = line.split(',').map(field).collect();
+        let bpm = fields.get(columns.0).and_then(|bpm| bpm.parse::<i32>().ok());
+        let created_at = fields.get(columns.1).and_then(|time| parse_import_time(time));
+        match (bpm, created_at) {
+            (Some(bpm), Some(created_at)) if bpm > 0 => rows.push((bpm, created_at)),
+            _ => {
+                tracing::debug!("Skipping malformed import row {}: {}", index + 1, line);
+                skipped += 1;
+            }
+        }
+    }
+    (rows, skipped)
+}
+
+/// Normalize an imported timestamp to the `CURRENT_TIMESTAMP` format
+fn parse_import_time(value: &str) -> Option<String> {
+    let time = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .map(|time| time.and_utc())
+        .or_else(|_| DateTime::parse_from_rfc3339(value).map(|time| time.with_timezone(&Utc)))
+        .ok()?;
+    Some(time.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+#[derive(Debug, Clone)]
 pub struct HeartRateStats {
     pub total_records: i32,
     pub avg_bpm: f64,
     pub min_bpm: i32,
     pub max_bpm: i32,
+    pub p50_bpm: f64,
+    pub p95_bpm: f64,
+    pub p99_bpm: f64,
+}
+
+/// Heart rates of one calendar day
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailySummary {
+    pub date: NaiveDate,
+    pub count: u32,
+    pub avg_bpm: f64,
+    pub min_bpm: i32,
+    pub max_bpm: i32,
+    /// Time between the first and last reading of the day
+    pub total_duration_secs: i64,
+}
+
+impl DailySummary {
+    /// A day without readings
+    fn empty(date: NaiveDate) -> Self {
+        Self {
+            date,
+            count: 0,
+            avg_bpm: 0.0,
+            min_bpm: 0,
+            max_bpm: 0,
+            total_duration_secs: 0,
+        }
+    }
+}
+
+/// Where percentile `p` (0-100) of `count` ascending values falls, like SQLite's percentile():
+/// the offset of the closest lower rank and how far to interpolate towards the next one.
+/// `None` without values.
+fn percentile_rank(count: i64, p: f64) -> Option<(i64, f64)> {
+    let last = count.checked_sub(1).filter(|last| *last >= 0)?;
+    let rank = p.clamp(0.0, 100.0) / 100.0 * last as f64;
+    let lower = rank.floor();
+    Some((lower as i64, rank - lower))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn database_with(count: i32) -> Database {
+        let database = Database::in_memory().await.unwrap();
+        for bpm in 0..count {
+            database.insert_heart_rate(60 + bpm, None, None).await.unwrap();
+        }
+        database
+    }
+
+    #[tokio::test]
+    async fn pages_walk_backwards_until_exhausted() {
+        let database = database_with(5).await;
+
+        let (page, next) = database.get_heart_rates_page(None, 2).await.unwrap();
+        assert_eq!(page.iter().map(|r| r.id).collect::<Vec<_>>(), [5, 4]);
+        assert_eq!(next, Some(4));
+
+        let (page, next) = database.get_heart_rates_page(next, 2).await.unwrap();
+        assert_eq!(page.iter().map(|r| r.id).collect::<Vec<_>>(), [3, 2]);
+
+        let (page, next) = database.get_heart_rates_page(next, 2).await.unwrap();
+        assert_eq!(page.iter().map(|r| r.id).collect::<Vec<_>>(), [1]);
+        assert_eq!(next, None);
+    }
+
+    #[tokio::test]
+    async fn daily_summary_includes_empty_days() {
+        let database = Database::in_memory().await.unwrap();
+        let now = Utc::now();
+        let two_days_ago = now - chrono::Duration::days(2);
+        for (bpm, at) in [(60, now), (80, now), (70, two_days_ago)] {
+            database.insert_heart_rate(bpm, Some(at), None).await.unwrap();
+        }
+
+        let summary = database.get_daily_summary(7).await.unwrap();
+        assert_eq!(summary.len(), 7);
+        let today = chrono::Local::now().date_naive();
+        assert_eq!(summary[6].date, today);
+        assert_eq!(summary[0].date, today - chrono::Days::new(6));
+
+        let local_day = |time: DateTime<Utc>| time.with_timezone(&chrono::Local).date_naive();
+        let recorded: Vec<&DailySummary> = summary.iter().filter(|day| day.count > 0).collect();
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[0].date, local_day(two_days_ago));
+        assert_eq!((recorded[1].count, recorded[1].avg_bpm), (2, 70.0));
+        assert_eq!((recorded[1].min_bpm, recorded[1].max_bpm), (60, 80));
+        assert!(summary.iter().all(|day| day.count > 0 || day.avg_bpm == 0.0));
+    }
+
+    #[tokio::test]
+    async fn recent_records_are_oldest_first() {
+        let database = database_with(5).await;
+        let recent = database.get_recent_heart_rates(3).await.unwrap();
+        assert_eq!(recent.iter().map(|r| r.bpm).collect::<Vec<_>>(), [62, 63, 64]);
+    }
+
+    #[test]
+    fn percentiles_interpolate_between_ranks() {
+        assert_eq!(percentile_rank(0, 95.0), None);
+        assert_eq!(percentile_rank(1, 99.0), Some((0, 0.0)));
+        assert_eq!(percentile_rank(100, 0.0), Some((0, 0.0)));
+        assert_eq!(percentile_rank(100, 50.0), Some((49, 0.5)));
+        let (offset, fraction) = percentile_rank(100, 95.0).unwrap();
+        assert_eq!(offset, 94);
+        assert!((fraction - 0.05).abs() < 1e-9);
+        assert_eq!(percentile_rank(100, 100.0), Some((99, 0.0)));
+    }
+
+    #[tokio::test]
+    async fn stats_include_percentiles() {
+        let stats = database_with(11).await.get_stats().await.unwrap();
+        assert_eq!(stats.total_records, 11);
+        assert_eq!(stats.p50_bpm, 65.0);
+        assert_eq!(stats.p95_bpm, 69.5);
+    }
+
+    #[tokio::test]
+    async fn records_after_id_are_oldest_first() {
+        let database = database_with(5).await;
+
+        let records = database.get_heart_rates_after(2, 2).await.unwrap();
+        assert_eq!(records.iter().map(|r| r.id).collect::<Vec<_>>(), [3, 4]);
+        assert_eq!(records[0].bpm, 62);
+        assert!(database.get_heart_rates_after(5, 2).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn retries_until_the_operation_succeeds() {
+        let mut attempts = 0;
+        let result = with_retries("Test", || {
+            attempts += 1;
+            let attempt = attempts;
+            async move {
+                if attempt < 2 {
+                    anyhow::bail!("database is locked");
+                }
+                Ok(attempt)
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[test]
+    fn import_rows_follow_header_or_table_order() {
+        let (rows, skipped) = parse_import_csv(
+            "created_at,bpm\n2024-05-01 10:00:00,72\n\n\"2024-05-01T12:00:01+02:00\",75\n",
+        );
+        assert_eq!(
+            rows,
+            [
+                (72, "2024-05-01 10:00:00".to_string()),
+                (75, "2024-05-01 10:00:01".to_string())
+            ]
+        );
+        assert_eq!(skipped, 0);
+
+        let (rows, skipped) =
+            parse_import_csv("1,72,2024-05-01 10:00:00\n2,abc,2024-05-01 10:00:01\n3,80\n");
+        assert_eq!(rows, [(72, "2024-05-01 10:00:00".to_string())]);
+        assert_eq!(skipped, 2);
+    }
+
+    #[tokio::test]
+    async fn self_test_leaves_no_rows() {
+        let database = database_with(0).await;
+        database.self_test().await.unwrap();
+        assert!(database.get_heart_rates_after(0, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn import_keeps_original_timestamps() {
+        let path =
+            std::env::temp_dir().join(format!("heartio-import-{}.csv", uuid::Uuid::new_v4()));
+        tokio::fs::write(&path, "id,bpm,created_at\n1,72,2024-05-01 10:00:00\n2,0,bad\n")
+            .await
+            .unwrap();
+        let database = Database::in_memory().await.unwrap();
+
+        let summary = database.import_csv(&path).await.unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+        assert_eq!(summary, ImportSummary { imported: 1, skipped: 1 });
+        let records = database.get_heart_rates_after(0, 10).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].bpm, 72);
+    }
+
+    #[tokio::test]
+    async fn backups_are_pruned_to_the_newest() {
+        let dir = std::env::temp_dir().join(format!("heartio-test-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("data.sqlite");
+        let database = Database::new(Some(path.to_str().unwrap()), 2).await.unwrap();
+        database.insert_heart_rate(72, None, None).await.unwrap();
+
+        for _ in 0..3 {
+            database.backup_before("testing").await;
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        let mut backups = Vec::new();
+        let mut entries = tokio::fs::read_dir(&dir).await.unwrap();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with("data.sqlite.backup.") {
+                backups.push(name);
+            }
+        }
+        database.close().await;
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+        assert_eq!(backups.len(), 2);
+    }
 }
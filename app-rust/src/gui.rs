@@ -2,10 +2,63 @@
 use anyhow::Result;
 use chrono::{DateTime, Local};
 use eframe::egui;
+use egui_plot::{Bar, BarChart, Plot};
 use std::collections::VecDeque;
 use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc as tokio_mpsc;
 
-const MAX_LOG_ENTRIES: usize = 1000;
+use crate::bluetooth::{ScanError, ScanProgress};
+use crate::config::{
+    AppTheme, BluetoothFavorite, Config, GUI_LOG_ENTRIES_RANGE, MonitorMode, UiPrefs, WindowPrefs,
+};
+use crate::database::{DailySummary, HeartRateStats};
+use crate::heart_rate::{HeartRateReading, MonitorCommand};
+use crate::osc::OscClient;
+use crate::stats::{EnergyAccumulator, IntervalStats, WelfordStats};
+use crate::tray::{self, Tray};
+use crate::zones::HeartRateZone;
+
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+const SPARKLINE_LEN: usize = 60;
+const SPARKLINE_SIZE: egui::Vec2 = egui::vec2(200.0, 30.0);
+/// Tallest the daily history chart gets
+const DAILY_CHART_HEIGHT: f32 = 240.0;
+/// Size of the reading confidence bar next to the BPM
+const CONFIDENCE_BAR_SIZE: egui::Vec2 = egui::vec2(30.0, 6.0);
+const ZONE_FADE: Duration = Duration::from_secs(1);
+const HEART_ICON_SIZE: f32 = 18.0;
+/// Animation frame interval, about 30 frames per second
+const ANIMATION_FRAME: Duration = Duration::from_millis(33);
+/// How often the monitor channels are polled while nothing animates
+const IDLE_POLL: Duration = Duration::from_millis(250);
+/// Polling and animation frame intervals while the window is unfocused or minimized
+const BACKGROUND_POLL: Duration = Duration::from_millis(500);
+const BACKGROUND_FRAME: Duration = Duration::from_millis(50);
+const MINIMIZED_POLL: Duration = Duration::from_secs(2);
+
+const SHORTCUT_CLEAR_VIEW: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::L);
+const SHORTCUT_SAVE_CONFIG: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::S);
+const SHORTCUT_TOGGLE_DEBUG: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::D);
+const SHORTCUT_EXPORT_LOGS: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::E);
+const SHORTCUT_TOGGLE_SETTINGS: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::W);
+const SHORTCUT_QUIT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Q);
+
+/// Shortcuts listed in the help popup
+const SHORTCUTS: [(egui::KeyboardShortcut, &str); 6] = [
+    (SHORTCUT_CLEAR_VIEW, "Clear log view"),
+    (SHORTCUT_SAVE_CONFIG, "Open or save settings"),
+    (SHORTCUT_TOGGLE_DEBUG, "Toggle debug logs"),
+    (SHORTCUT_EXPORT_LOGS, "Export logs"),
+    (SHORTCUT_TOGGLE_SETTINGS, "Show or hide settings"),
+    (SHORTCUT_QUIT, "Quit"),
+];
 
 #[derive(Debug, Clone)]
 pub struct LogEntry {
@@ -14,6 +67,18 @@ pub struct LogEntry {
     pub message: String,
 }
 
+impl LogEntry {
+    /// Format the entry as a single line with level and timestamp
+    pub fn format_line(&self) -> String {
+        format!(
+            "[{}] {} - {}",
+            self.level.icon(),
+            self.timestamp.format("%H:%M:%S"),
+            self.message
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum LogLevel {
     Info,
@@ -23,6 +88,13 @@ pub enum LogLevel {
 }
 
 impl LogLevel {
+    pub const ALL: [LogLevel; 4] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+    ];
+
     pub fn color(&self) -> egui::Color32 {
         match self {
             LogLevel::Info => egui::Color32::from_rgb(70, 130, 180), // Steel blue
@@ -42,15 +114,100 @@ impl LogLevel {
     }
 }
 
+/// Temporary notification shown in the corner of the window
+struct Toast {
+    message: String,
+    level: LogLevel,
+    shown_at: Instant,
+}
+
+/// Editable copy of the configuration shown in the settings window
+struct SettingsForm {
+    config: Config,
+    device_name: String,
+    device_address: String,
+}
+
+impl SettingsForm {
+    fn from_config(config: &Config) -> Self {
+        Self {
+            config: config.clone(),
+            device_name: config.heart_rate_device_name.clone().unwrap_or_default(),
+            device_address: config.heart_rate_device_address.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Build the configuration to save, empty device fields mean auto-detection
+    fn to_config(&self) -> Config {
+        let non_empty = |value: &str| {
+            let value = value.trim();
+            (!value.is_empty()).then(|| value.to_string())
+        };
+
+        let mut config = self.config.clone();
+        config.heart_rate_device_name = non_empty(&self.device_name);
+        config.heart_rate_device_address = non_empty(&self.device_address);
+        config
+    }
+}
+
 pub struct HeartIOApp {
     log_entries: VecDeque<LogEntry>,
+    max_log_entries: usize,
     log_receiver: mpsc::Receiver<LogEntry>,
     auto_scroll: bool,
+    show_info: bool,
+    show_warn: bool,
+    show_error: bool,
     show_debug: bool,
+    log_filter: String,
+    log_view_cleared_at: Option<DateTime<Local>>,
+    scroll_to_top: bool,
     current_heart_rate: Option<u32>,
-    heart_rate_receiver: mpsc::Receiver<u32>,
+    /// Latest reading and when it arrived, to tell a steady heart rate from a frozen one
+    last_good: Option<(u32, Instant)>,
+    current_source: Option<MonitorMode>,
+    /// Confidence score of the latest reading, 0-100
+    current_confidence: Option<u8>,
+    heart_rate_history: VecDeque<f32>,
+    current_zone_color: egui::Color32,
+    /// Heart icon animation phase in radians, one beat per full turn
+    pulse_phase: f32,
+    /// Zone tint fade as (start color, target color, start time)
+    zone_fade: (egui::Color32, egui::Color32, Instant),
+    heart_rate_receiver: mpsc::Receiver<HeartRateReading>,
+    connection_status_receiver: mpsc::Receiver<ConnectionStatus>,
+    history_receiver: mpsc::Receiver<HistoryUpdate>,
+    /// Database statistics over the last 24 hours
+    history_stats: Option<HeartRateStats>,
+    /// Readings per day for the history tabs, oldest first
+    daily_summary: Vec<DailySummary>,
     connection_status: ConnectionStatus,
     stats: AppStats,
+    toast: Option<Toast>,
+    notification_sender: mpsc::Sender<(LogLevel, String)>,
+    notification_receiver: mpsc::Receiver<(LogLevel, String)>,
+    config: Config,
+    settings: Option<SettingsForm>,
+    command_sender: tokio_mpsc::UnboundedSender<MonitorCommand>,
+    window_prefs: Option<WindowPrefs>,
+    window_clamped: bool,
+    ui_prefs: UiPrefs,
+    system_dark_mode: bool,
+    show_about: bool,
+    central_tab: CentralTab,
+    persist_history: bool,
+    /// Created once the event loop runs, `None` where tray icons are unsupported
+    tray: Option<Tray>,
+}
+
+/// Heart rate history read from the database, sent apart from the connection status
+#[derive(Debug, Clone)]
+pub enum HistoryUpdate {
+    /// Database statistics over the last 24 hours, refreshed every minute
+    Stats(HeartRateStats),
+    /// One entry per day for the last 30 days, oldest first, refreshed every minute
+    DailySummary(Vec<DailySummary>),
 }
 
 #[derive(Debug, Clone)]
@@ -59,15 +216,39 @@ pub struct ConnectionStatus {
     pub osc_connected: bool,
     pub database_connected: bool,
     pub apple_watch_server_running: bool,
+    pub device_name: Option<String>,
+    pub device_address: Option<String>,
+    /// No device name or address is configured
+    pub device_auto_detected: bool,
+    /// Progress of an ongoing Bluetooth scan
+    pub scan_progress: Option<ScanProgress>,
+    /// Why the last Bluetooth scan failed, when the user has to act on it
+    pub scan_error: Option<ScanError>,
+    /// Where the Bluetooth sensor is worn, e.g. "Chest"
+    pub sensor_location: Option<&'static str>,
+    /// Recent OSC sends kept failing, cleared by the next successful send
+    pub osc_unreachable: bool,
+    /// Readings are not saved or sent while paused
+    pub paused: bool,
+    /// PERSIST is on but the database file could not be used
+    pub persist_failed: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct AppStats {
     pub total_heart_rates: u32,
     pub session_duration: std::time::Duration,
-    pub session_start_time: Option<std::time::Instant>,
+    pub session_start_time: Option<DateTime<Local>>,
     pub last_heart_rate_time: Option<DateTime<Local>>,
-    pub avg_heart_rate: f32,
+    pub heart_rate_stats: WelfordStats,
+    pub energy: EnergyAccumulator,
+    /// Estimated from heart rate and the user's body data
+    pub calories_burned: f32,
+    /// Reading inter-arrival times over the last minute
+    pub intervals: IntervalStats,
+    pub current_heart_rate: Option<u32>,
+    pub min_heart_rate: Option<u32>,
+    pub max_heart_rate: Option<u32>,
 }
 
 impl Default for AppStats {
@@ -77,7 +258,13 @@ impl Default for AppStats {
             session_duration: std::time::Duration::new(0, 0),
             session_start_time: None,
             last_heart_rate_time: None,
-            avg_heart_rate: 0.0,
+            heart_rate_stats: WelfordStats::default(),
+            energy: EnergyAccumulator::default(),
+            calories_burned: 0.0,
+            intervals: IntervalStats::default(),
+            current_heart_rate: None,
+            min_heart_rate: None,
+            max_heart_rate: None,
         }
     }
 }
@@ -85,30 +272,507 @@ impl Default for AppStats {
 impl HeartIOApp {
     /// Create a new HeartIO GUI application
     pub fn new(
+        config: Config,
         log_receiver: mpsc::Receiver<LogEntry>,
-        heart_rate_receiver: mpsc::Receiver<u32>,
+        heart_rate_receiver: mpsc::Receiver<HeartRateReading>,
+        connection_status_receiver: mpsc::Receiver<ConnectionStatus>,
+        history_receiver: mpsc::Receiver<HistoryUpdate>,
+        command_sender: tokio_mpsc::UnboundedSender<MonitorCommand>,
+        window_prefs: Option<WindowPrefs>,
     ) -> Self {
+        let (notification_sender, notification_receiver) = mpsc::channel();
+        let persist_history = config.persist;
+        let max_log_entries = config.gui_max_log_entries;
+
         Self {
             log_entries: VecDeque::new(),
+            max_log_entries,
             log_receiver,
             auto_scroll: true,
+            show_info: true,
+            show_warn: true,
+            show_error: true,
             show_debug: false,
+            log_filter: String::new(),
+            log_view_cleared_at: None,
+            scroll_to_top: false,
             current_heart_rate: None,
+            last_good: None,
+            current_source: None,
+            current_confidence: None,
+            heart_rate_history: VecDeque::with_capacity(SPARKLINE_LEN),
+            current_zone_color: egui::Color32::TRANSPARENT,
+            pulse_phase: 0.0,
+            zone_fade: (
+                egui::Color32::TRANSPARENT,
+                egui::Color32::TRANSPARENT,
+                Instant::now(),
+            ),
             heart_rate_receiver,
+            connection_status_receiver,
+            history_receiver,
+            history_stats: None,
+            daily_summary: Vec::new(),
             connection_status: ConnectionStatus {
                 bluetooth_connected: false,
                 osc_connected: false,
                 database_connected: false,
                 apple_watch_server_running: false,
+                device_name: None,
+                device_address: None,
+                device_auto_detected: false,
+                scan_progress: None,
+                scan_error: None,
+                sensor_location: None,
+                osc_unreachable: false,
+                paused: false,
+                persist_failed: false,
             },
             stats: AppStats::default(),
+            toast: None,
+            notification_sender,
+            notification_receiver,
+            config,
+            settings: None,
+            command_sender,
+            window_prefs,
+            window_clamped: false,
+            ui_prefs: UiPrefs::load(),
+            system_dark_mode: AppTheme::system_prefers_dark(),
+            show_about: false,
+            central_tab: CentralTab::Logs,
+            persist_history,
+            tray: None,
         }
     }
 
+    /// Keep the tray tooltip and Pause item current, the tray handles its menu itself
+    fn handle_tray(&mut self, ctx: &egui::Context) {
+        let Some(tray) = &mut self.tray else {
+            return;
+        };
+        tray.set_heart_rate(self.current_heart_rate);
+        tray.set_paused(self.connection_status.paused);
+        if tray.take_reset_request() {
+            self.reset_stats();
+        }
+
+        if self.config.minimize_to_tray && ctx.input(|i| i.viewport().minimized == Some(true)) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+        }
+    }
+
+    /// Apply the selected theme, following the OS preference on System
+    fn apply_theme(&self, ctx: &egui::Context) {
+        let dark_mode = match self.ui_prefs.theme {
+            AppTheme::Dark => true,
+            AppTheme::Light => false,
+            // The windowing backend reports OS theme changes, fall back to the startup probe
+            AppTheme::System => ctx
+                .system_theme()
+                .map_or(self.system_dark_mode, |theme| theme == egui::Theme::Dark),
+        };
+
+        if ctx.style().visuals.dark_mode != dark_mode {
+            ctx.set_visuals(if dark_mode {
+                egui::Visuals::dark()
+            } else {
+                egui::Visuals::light()
+            });
+        }
+    }
+
+    /// Change the theme and remember the choice
+    fn set_theme(&mut self, theme: AppTheme) {
+        if self.ui_prefs.theme == theme {
+            return;
+        }
+        self.ui_prefs.theme = theme;
+
+        if let Err(e) = self.ui_prefs.save() {
+            self.show_toast(LogLevel::Error, format!("Failed to save theme: {}", e));
+        }
+    }
+
+    /// Track the window rect so it can be saved on exit
+    fn track_window(&mut self, ctx: &egui::Context) {
+        let (outer_rect, inner_rect, monitor_size) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (viewport.outer_rect, viewport.inner_rect, viewport.monitor_size)
+        });
+
+        // Move restored windows back on screen once the monitor size is known
+        if !self.window_clamped {
+            let Some(monitor_size) = monitor_size else {
+                return;
+            };
+            self.window_clamped = true;
+
+            if let Some(prefs) = self.window_prefs {
+                let clamped = prefs.clamp_to_screen(monitor_size.x, monitor_size.y);
+                if clamped != prefs {
+                    tracing::info!("Restored window was off-screen, moving it back into view");
+                    ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(egui::pos2(
+                        clamped.x, clamped.y,
+                    )));
+                    ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(
+                        clamped.width,
+                        clamped.height,
+                    )));
+                    self.window_prefs = Some(clamped);
+                    return;
+                }
+            }
+        }
+
+        if let (Some(outer), Some(inner)) = (outer_rect, inner_rect) {
+            self.window_prefs = Some(WindowPrefs {
+                x: outer.min.x,
+                y: outer.min.y,
+                width: inner.width(),
+                height: inner.height(),
+            });
+        }
+    }
+
+    /// Handle keyboard shortcuts for common actions
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        let pressed =
+            |shortcut: egui::KeyboardShortcut| ctx.input_mut(|i| i.consume_shortcut(&shortcut));
+
+        if pressed(SHORTCUT_CLEAR_VIEW) {
+            // Only hides the entries, they are still kept for Save Logs
+            self.log_view_cleared_at = Some(Local::now());
+            self.scroll_to_top = true;
+        }
+        if pressed(SHORTCUT_SAVE_CONFIG) {
+            if self.settings.is_some() {
+                self.submit_settings();
+            } else {
+                self.settings = Some(SettingsForm::from_config(&self.config));
+            }
+        }
+        if pressed(SHORTCUT_TOGGLE_DEBUG) {
+            self.show_debug = !self.show_debug;
+        }
+        if pressed(SHORTCUT_EXPORT_LOGS) {
+            self.save_logs();
+        }
+        if pressed(SHORTCUT_TOGGLE_SETTINGS) {
+            self.settings = match self.settings {
+                Some(_) => None,
+                None => Some(SettingsForm::from_config(&self.config)),
+            };
+        }
+        if pressed(SHORTCUT_QUIT) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
+
+    /// Ask for a destination and write all in-memory log entries to it
+    fn save_logs(&mut self) {
+        let default_name = format!("heartio-logs-{}.txt", Local::now().format("%Y%m%d-%H%M%S"));
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name(default_name)
+            .add_filter("Text", &["txt"])
+            .save_file()
+        else {
+            return;
+        };
+
+        // Snapshot regardless of the current search/debug filters
+        let content: String = self
+            .log_entries
+            .iter()
+            .map(|entry| entry.format_line() + "\n")
+            .collect();
+
+        let sender = self.notification_sender.clone();
+        tokio::task::spawn_blocking(move || {
+            let notification = match std::fs::write(&path, content) {
+                Ok(_) => (LogLevel::Info, format!("Logs saved to {}", path.display())),
+                Err(e) => (LogLevel::Error, format!("Failed to save logs: {}", e)),
+            };
+            let _ = sender.send(notification);
+        });
+    }
+
+    /// Send a test chatbox message to every OSC endpoint and report the result as a toast
+    fn test_osc(&self) {
+        let endpoints = self.config.osc_endpoints();
+        let bind_addr = self.config.osc_bind_addr.clone();
+        let sender = self.notification_sender.clone();
+        tokio::spawn(async move {
+            for (host, port) in endpoints {
+                let result = match OscClient::new(host.clone(), port, false, bind_addr.as_deref()) {
+                    Ok(client) => client.test_connection().await,
+                    Err(e) => Err(e),
+                };
+                let notification = match result {
+                    Ok(_) => (LogLevel::Info, format!("OSC test sent to {}:{}", host, port)),
+                    Err(e) => (
+                        LogLevel::Error,
+                        format!("OSC test to {}:{} failed: {:#}", host, port, e),
+                    ),
+                };
+                let _ = sender.send(notification);
+            }
+        });
+    }
+
+    /// Show a toast notification for a few seconds
+    fn show_toast(&mut self, level: LogLevel, message: String) {
+        self.toast = Some(Toast {
+            message,
+            level,
+            shown_at: Instant::now(),
+        });
+    }
+
+    /// Move the background tint towards the color of the current heart rate zone
+    fn fade_zone_color(&mut self, ctx: &egui::Context) {
+        let target = self
+            .current_heart_rate
+            .map(|hr| zone_bg_color(self.config.zone_config().zone(hr)))
+            .unwrap_or(egui::Color32::TRANSPARENT);
+        if target != self.zone_fade.1 {
+            self.zone_fade = (self.current_zone_color, target, Instant::now());
+        }
+
+        let (from, to, started) = self.zone_fade;
+        let t = (started.elapsed().as_secs_f32() / ZONE_FADE.as_secs_f32()).min(1.0);
+        self.current_zone_color = from.lerp_to_gamma(to, t);
+        if t < 1.0 {
+            request_animation_frame(ctx);
+        }
+    }
+
+    /// Clear the session statistics here and in the monitor
+    fn reset_session(&mut self) {
+        let _ = self.command_sender.send(MonitorCommand::ResetSession);
+        self.reset_stats();
+    }
+
+    /// Clear the session statistics shown here
+    fn reset_stats(&mut self) {
+        self.stats = AppStats {
+            session_start_time: Some(Local::now()),
+            ..AppStats::default()
+        };
+    }
+
+    /// Save the edited settings and forward them to the running monitor
+    fn save_settings(&mut self, config: Config) {
+        let _ = self
+            .command_sender
+            .send(MonitorCommand::UpdateConfig(Box::new(config.clone())));
+        self.config = config.clone();
+        self.max_log_entries = self.config.gui_max_log_entries;
+        self.trim_log_entries();
+
+        let sender = self.notification_sender.clone();
+        tokio::spawn(async move {
+            let notification = match config.save().await {
+                Ok(_) => (LogLevel::Info, "Settings saved".to_string()),
+                Err(e) => (LogLevel::Error, format!("Failed to save settings: {}", e)),
+            };
+            let _ = sender.send(notification);
+        });
+    }
+
+    fn draw_settings(&mut self, ctx: &egui::Context) {
+        let Some(form) = &mut self.settings else {
+            return;
+        };
+
+        let restart_marker = egui::RichText::new("(restart required)")
+            .size(11.0)
+            .color(LogLevel::Warn.color());
+        let mut open = true;
+        let mut save = false;
+
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("settings_grid")
+                    .num_columns(2)
+                    .spacing([20.0, 6.0])
+                    .show(ui, |ui| {
+                        ui.label("OSC Host:");
+                        ui.text_edit_singleline(&mut form.config.osc_host);
+                        ui.end_row();
+
+                        ui.label("OSC Port:");
+                        ui.add(egui::DragValue::new(&mut form.config.osc_port).range(1..=65535));
+                        ui.end_row();
+
+                        ui.label("Send Interval:");
+                        ui.add(
+                            egui::DragValue::new(&mut form.config.osc_send_interval_ms)
+                                .range(500..=60000)
+                                .suffix(" ms"),
+                        );
+                        ui.end_row();
+
+                        ui.label("Skip Unchanged:");
+                        ui.checkbox(&mut form.config.skip_duplicate_messages, "")
+                            .on_hover_text("Don't resend the same label for the same BPM");
+                        ui.end_row();
+
+                        ui.label("Typing Indicator:");
+                        ui.checkbox(&mut form.config.osc_typing_indicator, "");
+                        ui.end_row();
+
+                        ui.label("Mode:");
+                        ui.horizontal(|ui| {
+                            let mut mode = form.config.mode();
+                            egui::ComboBox::from_id_salt("settings_mode")
+                                .selected_text(mode.label())
+                                .show_ui(ui, |ui| {
+                                    for option in [
+                                        MonitorMode::Bluetooth,
+                                        MonitorMode::AppleWatch,
+                                        MonitorMode::XiaomiBand,
+                                    ] {
+                                        ui.selectable_value(&mut mode, option, option.label());
+                                    }
+                                });
+                            form.config.set_mode(mode);
+                        });
+                        ui.end_row();
+
+                        ui.label("Device Name:");
+                        ui.text_edit_singleline(&mut form.device_name);
+                        ui.end_row();
+
+                        ui.label("Device Address:");
+                        ui.text_edit_singleline(&mut form.device_address);
+                        ui.end_row();
+
+                        ui.label("Save History:");
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut form.config.persist, "");
+                            ui.label(restart_marker.clone());
+                        });
+                        ui.end_row();
+
+                        ui.label("Log Entries:");
+                        ui.add(
+                            egui::Slider::new(
+                                &mut form.config.gui_max_log_entries,
+                                GUI_LOG_ENTRIES_RANGE,
+                            )
+                            .logarithmic(true),
+                        );
+                        ui.end_row();
+                    });
+
+                ui.label(
+                    egui::RichText::new("Leave device name and address empty for auto-detection")
+                        .size(11.0)
+                        .color(egui::Color32::GRAY),
+                );
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    save = ui.button("Save").clicked();
+                });
+            });
+
+        if save {
+            self.submit_settings();
+        } else if !open {
+            self.settings = None;
+        }
+    }
+
+    /// Save the open settings form and close it
+    fn submit_settings(&mut self) {
+        if let Some(form) = self.settings.take() {
+            self.save_settings(form.to_config());
+        }
+    }
+
+    fn draw_about(&mut self, ctx: &egui::Context) {
+        if !self.show_about {
+            return;
+        }
+
+        let mut open = true;
+        egui::Window::new("About HeartIO")
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("about_grid")
+                    .num_columns(2)
+                    .spacing([20.0, 4.0])
+                    .show(ui, |ui| {
+                        for (name, value) in build_info() {
+                            ui.label(name);
+                            ui.label(value);
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                ui.label(egui::RichText::new("Dependencies").strong());
+                egui::Grid::new("about_dependencies_grid")
+                    .num_columns(2)
+                    .spacing([20.0, 4.0])
+                    .show(ui, |ui| {
+                        for (name, version) in dependency_versions() {
+                            ui.label(name);
+                            ui.label(version);
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                if ui.button("Copy to Clipboard").clicked() {
+                    ctx.copy_text(about_text());
+                    self.show_toast(LogLevel::Info, "Build info copied".to_string());
+                }
+            });
+
+        if !open {
+            self.show_about = false;
+        }
+    }
+
+    fn draw_toast(&mut self, ctx: &egui::Context) {
+        let Some(toast) = &self.toast else {
+            return;
+        };
+
+        let elapsed = toast.shown_at.elapsed();
+        if elapsed >= TOAST_DURATION {
+            self.toast = None;
+            return;
+        }
+        ctx.request_repaint_after(TOAST_DURATION - elapsed);
+
+        egui::Window::new("toast")
+            .title_bar(false)
+            .resizable(false)
+            .collapsible(false)
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -12.0])
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new(&toast.message).color(toast.level.color()));
+            });
+    }
+
     /// Add a log entry to the display
     pub fn add_log_entry(&mut self, entry: LogEntry) {
         self.log_entries.push_back(entry);
-        if self.log_entries.len() > MAX_LOG_ENTRIES {
+        self.trim_log_entries();
+    }
+
+    /// Drop the oldest log entries beyond the configured limit
+    fn trim_log_entries(&mut self) {
+        while self.log_entries.len() > self.max_log_entries {
             self.log_entries.pop_front();
         }
     }
@@ -128,6 +792,12 @@ impl eframe::App for HeartIOApp {
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
         tracing::info!("Application exiting - performing final cleanup");
         crate::system::SystemUtils::immediate_cleanup();
+
+        if let Some(prefs) = self.window_prefs {
+            if let Err(e) = prefs.save() {
+                tracing::warn!("Failed to save window prefs: {}", e);
+            }
+        }
     }
 
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
@@ -139,33 +809,84 @@ impl eframe::App for HeartIOApp {
             crate::system::SystemUtils::immediate_cleanup();
         }
 
+        self.track_window(ctx);
+        self.apply_theme(ctx);
+        self.handle_shortcuts(ctx);
+        self.handle_tray(ctx);
+
+        let mut received = false;
+
         // Process incoming log entries
         while let Ok(entry) = self.log_receiver.try_recv() {
             self.add_log_entry(entry);
+            received = true;
+        }
+
+        // Process connection status updates
+        while let Ok(status) = self.connection_status_receiver.try_recv() {
+            self.update_connection_status(status);
+            received = true;
+        }
+
+        // Process history refreshes
+        while let Ok(update) = self.history_receiver.try_recv() {
+            match update {
+                HistoryUpdate::Stats(stats) => self.history_stats = Some(stats),
+                HistoryUpdate::DailySummary(summary) => self.daily_summary = summary,
+            }
+            received = true;
+        }
+
+        // Process notifications from background tasks
+        while let Ok((level, message)) = self.notification_receiver.try_recv() {
+            self.show_toast(level, message);
+            received = true;
         }
 
         // Update session duration
         let now = std::time::Instant::now();
         if let Some(start) = self.stats.session_start_time {
-            self.stats.session_duration = now.duration_since(start);
+            self.stats.session_duration = (Local::now() - start).to_std().unwrap_or_default();
         } else {
-            self.stats.session_start_time = Some(now);
+            self.stats.session_start_time = Some(Local::now());
         }
 
         // Process incoming heart rate data
-        while let Ok(heart_rate) = self.heart_rate_receiver.try_recv() {
+        while let Ok(reading) = self.heart_rate_receiver.try_recv() {
+            received = true;
+            let heart_rate = reading.bpm;
+            if let Some(kcal) = reading.session_kcal {
+                self.stats.calories_burned = kcal;
+            }
             self.current_heart_rate = Some(heart_rate);
+            self.last_good = Some((heart_rate, now));
+            self.current_source = Some(reading.source);
+            self.current_confidence = reading.confidence;
+            if self.heart_rate_history.len() >= SPARKLINE_LEN {
+                self.heart_rate_history.pop_front();
+            }
+            self.heart_rate_history.push_back(heart_rate as f32);
             self.stats.total_heart_rates += 1;
             self.stats.last_heart_rate_time = Some(Local::now());
+            self.stats.heart_rate_stats.update(heart_rate as f64);
+            if let Some(energy_kj) = reading.energy_expended_kj {
+                self.stats.energy.update(now, energy_kj);
+            }
+            self.stats.intervals.record(now);
+        }
 
-            // Update average (simple running average)
-            if self.stats.total_heart_rates == 1 {
-                self.stats.avg_heart_rate = heart_rate as f32;
-            } else {
-                let alpha = 0.1; // Smoothing factor
-                self.stats.avg_heart_rate =
-                    alpha * heart_rate as f32 + (1.0 - alpha) * self.stats.avg_heart_rate;
+        self.fade_zone_color(ctx);
+
+        // Advance the heart icon animation at the BPM rate, the repaint loop is capped by
+        // request_animation_frame so the beat doesn't redraw faster than ANIMATION_FRAME
+        match self.current_heart_rate {
+            Some(hr) => {
+                let dt = ctx.input(|i| i.stable_dt);
+                let step = dt * hr as f32 / 60.0 * std::f32::consts::TAU;
+                self.pulse_phase = (self.pulse_phase + step) % std::f32::consts::TAU;
+                request_animation_frame(ctx);
             }
+            None => self.pulse_phase = 0.0,
         }
 
         // Top panel with status and controls
@@ -176,12 +897,38 @@ impl eframe::App for HeartIOApp {
                 ui.separator();
 
                 // Current heart rate display
-                if let Some(hr) = self.current_heart_rate {
-                    ui.label(
-                        egui::RichText::new(format!("{} BPM", hr))
-                            .size(18.0)
-                            .color(egui::Color32::from_rgb(220, 20, 60)),
-                    );
+                let scale = if self.current_heart_rate.is_some() {
+                    1.0 + 0.15 * self.pulse_phase.sin().max(0.0)
+                } else {
+                    1.0
+                };
+                let heart_color = if self.current_heart_rate.is_some() {
+                    egui::Color32::from_rgb(220, 20, 60)
+                } else {
+                    egui::Color32::GRAY
+                };
+                draw_pulsing_heart(ui, scale, heart_color);
+
+                if let Some((hr, received_at)) = self.last_good {
+                    let stale_after = Duration::from_secs(self.config.stale_after_secs);
+                    let silence = received_at.elapsed();
+                    let mut color = zone_color(self.config.zone_config().zone(hr));
+                    if silence >= stale_after {
+                        color = color.gamma_multiply(0.4);
+                    }
+                    ui.label(egui::RichText::new(format!("{} BPM", hr)).size(18.0).color(color));
+                    if silence >= stale_after {
+                        ui.label(
+                            egui::RichText::new(format!("({}s ago)", silence.as_secs())).weak(),
+                        )
+                        .on_hover_text("No new readings, showing the last one");
+                    }
+                    if let Some(source) = self.current_source {
+                        ui.label(egui::RichText::new(source.label()).small().weak());
+                    }
+                    if let Some(confidence) = self.current_confidence {
+                        draw_confidence_bar(ui, confidence);
+                    }
                 } else {
                     ui.label(
                         egui::RichText::new("-- BPM")
@@ -190,24 +937,85 @@ impl eframe::App for HeartIOApp {
                     );
                 }
 
+                draw_sparkline(
+                    ui,
+                    &self.heart_rate_history,
+                    egui::Color32::from_rgb(220, 20, 60),
+                );
+
                 ui.separator();
 
                 // Connection status indicators
                 self.draw_connection_status(ui);
 
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.checkbox(&mut self.show_debug, "Show Debug");
+                    if ui.button("About").clicked() {
+                        self.show_about = true;
+                    }
+                    ui.menu_button("?", |ui| {
+                        ui.label(egui::RichText::new("Keyboard Shortcuts").strong());
+                        egui::Grid::new("shortcuts_grid")
+                            .num_columns(2)
+                            .spacing([20.0, 4.0])
+                            .show(ui, |ui| {
+                                for (shortcut, description) in SHORTCUTS {
+                                    ui.label(ctx.format_shortcut(&shortcut));
+                                    ui.label(description);
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                    let theme_icon = if ctx.style().visuals.dark_mode { "🌙" } else { "☀" };
+                    ui.menu_button(theme_icon, |ui| {
+                        for theme in AppTheme::ALL {
+                            if ui
+                                .selectable_label(self.ui_prefs.theme == theme, theme.label())
+                                .clicked()
+                            {
+                                self.set_theme(theme);
+                                ui.close_menu();
+                            }
+                        }
+                    })
+                    .response
+                    .on_hover_text("Theme");
+                    if ui.button("Settings").clicked() && self.settings.is_none() {
+                        self.settings = Some(SettingsForm::from_config(&self.config));
+                    }
                     ui.checkbox(&mut self.auto_scroll, "Auto Scroll");
                 });
             });
         });
 
+        if self.connection_status.osc_unreachable {
+            egui::TopBottomPanel::top("osc_banner")
+                .frame(egui::Frame::default().fill(LogLevel::Warn.color()).inner_margin(6.0))
+                .show(ctx, |ui| {
+                    ui.label(
+                        egui::RichText::new("OSC target unreachable, is VRChat running?")
+                            .strong()
+                            .color(egui::Color32::BLACK),
+                    );
+                });
+        }
+
         // Side panel with statistics
         egui::SidePanel::right("stats_panel")
             .resizable(true)
             .default_width(250.0)
             .show(ctx, |ui| {
-                ui.heading("Statistics");
+                ui.horizontal(|ui| {
+                    ui.heading("Statistics");
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui
+                            .button("Reset")
+                            .on_hover_text("Start a new session, devices stay connected")
+                            .clicked()
+                        {
+                            self.reset_session();
+                        }
+                    });
+                });
                 ui.separator();
 
                 egui::Grid::new("stats_grid")
@@ -219,14 +1027,87 @@ impl eframe::App for HeartIOApp {
                         ui.label(self.stats.total_heart_rates.to_string());
                         ui.end_row();
 
-                        ui.label("Average BPM:");
-                        ui.label(format!("{:.1}", self.stats.avg_heart_rate));
+                        let average = ui.label("Average BPM:");
+                        match self
+                            .history_stats
+                            .as_ref()
+                            .filter(|stats| stats.total_records > 0)
+                        {
+                            Some(stats) => average.on_hover_text(format!(
+                                "Last 24 hours: {:.1} BPM average, {}-{} BPM\n\
+                                 P50 {:.0} / P95 {:.0} / P99 {:.0} BPM",
+                                stats.avg_bpm,
+                                stats.min_bpm,
+                                stats.max_bpm,
+                                stats.p50_bpm,
+                                stats.p95_bpm,
+                                stats.p99_bpm
+                            )),
+                            None => average,
+                        };
+                        ui.label(format!(
+                            "{:.1} ± {:.1} BPM",
+                            self.stats.heart_rate_stats.mean(),
+                            self.stats.heart_rate_stats.stddev()
+                        ));
                         ui.end_row();
 
                         ui.label("Session Time:");
                         ui.label(format!("{:.0}s", self.stats.session_duration.as_secs()));
                         ui.end_row();
 
+                        if self.stats.energy.has_data() {
+                            ui.label("Energy:");
+                            match self.stats.energy.kcal_per_min() {
+                                Some(rate) => ui.label(format!(
+                                    "{:.0} kcal ({:.1} kcal/min)",
+                                    self.stats.energy.total_kcal(),
+                                    rate
+                                )),
+                                None => ui.label(format!(
+                                    "{:.0} kcal",
+                                    self.stats.energy.total_kcal()
+                                )),
+                            };
+                            ui.end_row();
+
+                            ui.label("");
+                            if ui
+                                .small_button("Reset Energy")
+                                .on_hover_text(
+                                    "Reset the strap's energy counter, which stops counting \
+                                     once full. The session total is kept.",
+                                )
+                                .clicked()
+                            {
+                                let _ = self
+                                    .command_sender
+                                    .send(MonitorCommand::ResetEnergyExpended);
+                            }
+                            ui.end_row();
+                        }
+
+                        if self.config.calorie_profile().is_some() {
+                            ui.label("Burned:").on_hover_text(
+                                "Estimated from heart rate, weight, age and sex",
+                            );
+                            ui.label(format!("{:.0} kcal", self.stats.calories_burned));
+                            ui.end_row();
+                        }
+
+                        if let Some(summary) = self.stats.intervals.summary() {
+                            ui.label("Intervals:").on_hover_text(
+                                "Min / median / max time between readings, last minute",
+                            );
+                            let text = egui::RichText::new(summary.to_string());
+                            ui.label(if summary.dropped > 0 {
+                                text.color(LogLevel::Warn.color())
+                            } else {
+                                text
+                            });
+                            ui.end_row();
+                        }
+
                         if let Some(last_time) = &self.stats.last_heart_rate_time {
                             ui.label("Last Reading:");
                             ui.label(last_time.format("%H:%M:%S").to_string());
@@ -237,43 +1118,234 @@ impl eframe::App for HeartIOApp {
                 ui.separator();
                 ui.heading("Connection");
 
+                self.draw_source_switch(ui);
                 self.draw_detailed_connection_status(ui);
+                self.draw_bluetooth_favorites(ui);
             });
 
         // Central panel with logs
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Logs");
+            ui.painter()
+                .rect_filled(ui.max_rect(), 0.0, self.current_zone_color);
 
-            egui::ScrollArea::vertical()
-                .auto_shrink([false; 2])
-                .stick_to_bottom(self.auto_scroll)
-                .show(ui, |ui| {
-                    for entry in &self.log_entries {
-                        if !self.show_debug && entry.level == LogLevel::Debug {
-                            continue;
+            ui.horizontal(|ui| {
+                for tab in CentralTab::ALL {
+                    let title = egui::RichText::new(tab.label()).heading();
+                    ui.selectable_value(&mut self.central_tab, tab, title);
+                }
+                if self.central_tab == CentralTab::Logs {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("Save Logs").clicked() {
+                            self.save_logs();
                         }
+                    });
+                }
+            });
+            if let Some(days) = self.central_tab.days() {
+                self.draw_daily_summary(ui, days);
+                return;
+            }
 
-                        ui.horizontal(|ui| {
-                            ui.label(entry.level.icon());
-                            ui.label(
-                                egui::RichText::new(entry.timestamp.format("%H:%M:%S").to_string())
-                                    .size(11.0)
-                                    .color(egui::Color32::GRAY),
-                            );
-                            ui.label(
-                                egui::RichText::new(&entry.message).color(entry.level.color()),
-                            );
-                        });
-                    }
-                });
+            // Level toggles and search bar (view filters only, entries are kept in memory)
+            ui.horizontal(|ui| {
+                ui.label("Levels:");
+                for level in LogLevel::ALL {
+                    let text = egui::RichText::new(level.icon()).color(level.color());
+                    ui.toggle_value(self.level_shown(&level), text);
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Search:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.log_filter)
+                        .hint_text("Filter log messages")
+                        .desired_width(f32::INFINITY),
+                );
+                if ui.small_button("×").clicked() {
+                    self.log_filter.clear();
+                }
+                if self.log_view_cleared_at.is_some() && ui.small_button("Show All").clicked() {
+                    self.log_view_cleared_at = None;
+                }
+            });
+
+            let filter = self.log_filter.to_lowercase();
+
+            let mut scroll_area = egui::ScrollArea::vertical()
+                .auto_shrink([false; 2])
+                .stick_to_bottom(self.auto_scroll);
+            if std::mem::take(&mut self.scroll_to_top) {
+                scroll_area = scroll_area.vertical_scroll_offset(0.0);
+            }
+
+            let visible: Vec<&LogEntry> = self
+                .log_entries
+                .iter()
+                .filter(|entry| self.is_log_visible(entry, &filter))
+                .collect();
+
+            scroll_area.show(ui, |ui| {
+                for entry in &visible {
+                    let row = ui.horizontal(|ui| {
+                        ui.label(entry.level.icon());
+                        ui.label(
+                            egui::RichText::new(entry.timestamp.format("%H:%M:%S").to_string())
+                                .size(11.0)
+                                .color(egui::Color32::GRAY),
+                        );
+                        ui.label(highlight_matches(
+                            ui,
+                            &entry.message,
+                            &filter,
+                            entry.level.color(),
+                        ));
+                    });
+
+                    // Right-click a row to copy it, e.g. for pasting into an issue
+                    row.response.interact(egui::Sense::click()).context_menu(|ui| {
+                        if ui.button("Copy Message").clicked() {
+                            ui.ctx().copy_text(entry.message.clone());
+                            ui.close_menu();
+                        }
+                        if ui.button("Copy Full Line").clicked() {
+                            ui.ctx().copy_text(entry.format_line());
+                            ui.close_menu();
+                        }
+                        if ui.button("Copy All Visible").clicked() {
+                            let lines: Vec<String> =
+                                visible.iter().map(|entry| entry.format_line()).collect();
+                            ui.ctx().copy_text(lines.join("\n"));
+                            ui.close_menu();
+                        }
+                    });
+                }
+            });
         });
 
-        // Request repaint for real-time updates
-        ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        self.draw_settings(ctx);
+        self.draw_about(ctx);
+        self.draw_toast(ctx);
+
+        // The monitor doesn't wake the GUI, so poll its channels, less often in the background.
+        // After new data run one more frame right away, e.g. to settle the log auto-scroll.
+        if received {
+            ctx.request_repaint();
+        } else {
+            ctx.request_repaint_after(match window_activity(ctx) {
+                WindowActivity::Focused => IDLE_POLL,
+                WindowActivity::Background => BACKGROUND_POLL,
+                WindowActivity::Minimized => MINIMIZED_POLL,
+            });
+        }
+    }
+}
+
+/// Views of the central panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CentralTab {
+    Logs,
+    Last7Days,
+    Last30Days,
+}
+
+impl CentralTab {
+    const ALL: [CentralTab; 3] = [CentralTab::Logs, CentralTab::Last7Days, CentralTab::Last30Days];
+
+    fn label(&self) -> &'static str {
+        match self {
+            CentralTab::Logs => "Logs",
+            CentralTab::Last7Days => "Last 7 Days",
+            CentralTab::Last30Days => "Last 30 Days",
+        }
+    }
+
+    /// Days of history shown, `None` for the log view
+    fn days(&self) -> Option<usize> {
+        match self {
+            CentralTab::Logs => None,
+            CentralTab::Last7Days => Some(7),
+            CentralTab::Last30Days => Some(30),
+        }
+    }
+}
+
+/// How visible the window is, animations and polling slow down when it isn't in front
+enum WindowActivity {
+    Focused,
+    Background,
+    Minimized,
+}
+
+fn window_activity(ctx: &egui::Context) -> WindowActivity {
+    ctx.input(|i| {
+        let viewport = i.viewport();
+        if viewport.minimized == Some(true) {
+            WindowActivity::Minimized
+        } else if viewport.focused == Some(false) {
+            WindowActivity::Background
+        } else {
+            WindowActivity::Focused
+        }
+    })
+}
+
+/// Ask for the next animation frame, slower in the background and skipped when minimized
+fn request_animation_frame(ctx: &egui::Context) {
+    match window_activity(ctx) {
+        WindowActivity::Focused => ctx.request_repaint_after(ANIMATION_FRAME),
+        WindowActivity::Background => ctx.request_repaint_after(BACKGROUND_FRAME),
+        WindowActivity::Minimized => {}
     }
 }
 
 impl HeartIOApp {
+    /// Visibility toggle for a log level
+    fn level_shown(&mut self, level: &LogLevel) -> &mut bool {
+        match level {
+            LogLevel::Info => &mut self.show_info,
+            LogLevel::Warn => &mut self.show_warn,
+            LogLevel::Error => &mut self.show_error,
+            LogLevel::Debug => &mut self.show_debug,
+        }
+    }
+
+    /// Whether a log entry passes the level toggles, clear-view and search filters
+    fn is_log_visible(&self, entry: &LogEntry, filter: &str) -> bool {
+        let level_shown = match entry.level {
+            LogLevel::Info => self.show_info,
+            LogLevel::Warn => self.show_warn,
+            LogLevel::Error => self.show_error,
+            LogLevel::Debug => self.show_debug,
+        };
+        if !level_shown {
+            return false;
+        }
+        if self
+            .log_view_cleared_at
+            .is_some_and(|cleared_at| entry.timestamp <= cleared_at)
+        {
+            return false;
+        }
+        filter.is_empty() || entry.message.to_lowercase().contains(filter)
+    }
+
+    /// Average BPM per day as a bar chart, hover a bar for the details
+    fn draw_daily_summary(&self, ui: &mut egui::Ui, days: usize) {
+        let summary = &self.daily_summary;
+        if !self.connection_status.database_connected {
+            ui.label("No database, heart rate history is unavailable");
+            return;
+        }
+        if summary.iter().all(|day| day.count == 0) {
+            ui.label("No heart rates saved in this period yet");
+            return;
+        }
+        if !self.persist_history {
+            ui.label(egui::RichText::new("PERSIST is off, only this session is shown").weak());
+        }
+        draw_daily_bars(ui, &summary[summary.len().saturating_sub(days)..]);
+    }
+
     fn draw_connection_status(&self, ui: &mut egui::Ui) {
         let status_color = |connected: bool| {
             if connected {
@@ -287,13 +1359,148 @@ impl HeartIOApp {
             egui::RichText::new("BlueTooth")
                 .color(status_color(self.connection_status.bluetooth_connected)),
         );
-        ui.label(
-            egui::RichText::new("OSC").color(status_color(self.connection_status.osc_connected)),
-        );
+        if let Some(scan_error) = self.connection_status.scan_error {
+            ui.label(egui::RichText::new(scan_error.guidance()).color(LogLevel::Error.color()))
+                .on_hover_text(scan_error.to_string());
+        }
+        let osc_color = if self.connection_status.osc_unreachable {
+            LogLevel::Warn.color()
+        } else {
+            status_color(self.connection_status.osc_connected)
+        };
+        ui.label(egui::RichText::new("OSC").color(osc_color));
+        if ui
+            .small_button("Test")
+            .on_hover_text("Send a test message to the VRChat chatbox")
+            .clicked()
+        {
+            self.test_osc();
+        }
 
         if self.connection_status.apple_watch_server_running {
             ui.label(egui::RichText::new("AW").color(status_color(true)));
         }
+
+        if !self.persist_history {
+            ui.label(egui::RichText::new("Not Saved").color(LogLevel::Warn.color()))
+                .on_hover_text("PERSIST is off, heart rate history is kept in memory only");
+        } else if self.connection_status.persist_failed {
+            ui.label(egui::RichText::new("Not Saved").color(LogLevel::Error.color()))
+                .on_hover_text("The database could not be used, see the log for details");
+        }
+
+        if self.connection_status.paused {
+            ui.label(egui::RichText::new("Paused").color(LogLevel::Warn.color()))
+                .on_hover_text("Readings are not saved or sent over OSC");
+        }
+
+        if let Some(progress) = self.connection_status.scan_progress {
+            ui.separator();
+            ui.spinner();
+            ui.label(format!(
+                "Scanning... {}s, {} devices seen",
+                progress.elapsed.as_secs(),
+                progress.devices_seen
+            ));
+            if ui.small_button("Cancel").clicked() {
+                let _ = self.command_sender.send(MonitorCommand::CancelScan);
+            }
+        }
+    }
+
+    /// Dropdown switching the heart rate source while monitoring keeps running
+    fn draw_source_switch(&mut self, ui: &mut egui::Ui) {
+        let sources = self.config.sources();
+        let current = match sources.as_slice() {
+            [source] => source.label().to_string(),
+            _ => sources.iter().map(|source| source.label()).collect::<Vec<_>>().join(" + "),
+        };
+
+        let mut switch_to = None;
+        ui.horizontal(|ui| {
+            ui.label("Source:");
+            egui::ComboBox::from_id_salt("source_switch")
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    for mode in [
+                        MonitorMode::Bluetooth,
+                        MonitorMode::AppleWatch,
+                        MonitorMode::XiaomiBand,
+                    ] {
+                        if ui.selectable_label(sources == [mode], mode.label()).clicked() {
+                            switch_to = Some(mode);
+                        }
+                    }
+                });
+        });
+
+        if let Some(mode) = switch_to.filter(|mode| sources != [*mode]) {
+            let mut config = self.config.clone();
+            config.set_mode(mode);
+            // A single picked source replaces the priority list
+            config.heart_rate_sources.clear();
+            self.save_settings(config);
+        }
+    }
+
+    /// Favorites dropdown to switch devices, plus a button to save the connected device
+    fn draw_bluetooth_favorites(&mut self, ui: &mut egui::Ui) {
+        let mut switch_to = None;
+        let mut save = None;
+
+        if let (Some(name), Some(address)) = (
+            &self.connection_status.device_name,
+            &self.connection_status.device_address,
+        ) {
+            let saved = self
+                .config
+                .bluetooth_favorites
+                .iter()
+                .any(|favorite| favorite.address.eq_ignore_ascii_case(address));
+            if !saved
+                && ui
+                    .small_button("⭐ Save")
+                    .on_hover_text("Add this device to the favorites")
+                    .clicked()
+            {
+                save = Some(BluetoothFavorite {
+                    name: name.clone(),
+                    address: address.clone(),
+                    label: name.clone(),
+                });
+            }
+        }
+
+        if !self.config.bluetooth_favorites.is_empty() {
+            let current = self.config.heart_rate_device_address.as_deref().unwrap_or_default();
+            egui::ComboBox::from_id_salt("bluetooth_favorites")
+                .selected_text("Switch device...")
+                .show_ui(ui, |ui| {
+                    for favorite in &self.config.bluetooth_favorites {
+                        let selected = favorite.address.eq_ignore_ascii_case(current);
+                        if ui
+                            .selectable_label(selected, &favorite.label)
+                            .on_hover_text(format!("{} ({})", favorite.name, favorite.address))
+                            .clicked()
+                        {
+                            switch_to = Some(favorite.clone());
+                        }
+                    }
+                });
+        }
+
+        if let Some(favorite) = save {
+            let mut config = self.config.clone();
+            config.bluetooth_favorites.push(favorite);
+            self.save_settings(config);
+        }
+        if let Some(favorite) = switch_to {
+            let mut config = self.config.clone();
+            config.heart_rate_device_name = Some(favorite.name).filter(|name| !name.is_empty());
+            config.heart_rate_device_address =
+                Some(favorite.address).filter(|address| !address.is_empty());
+            self.save_settings(config);
+        }
     }
 
     fn draw_detailed_connection_status(&self, ui: &mut egui::Ui) {
@@ -301,6 +1508,28 @@ impl HeartIOApp {
             ui.label("Bluetooth");
         });
 
+        if let (Some(name), Some(address)) = (
+            &self.connection_status.device_name,
+            &self.connection_status.device_address,
+        ) {
+            ui.horizontal(|ui| {
+                ui.label(name);
+                ui.label(egui::RichText::new(address).monospace().color(egui::Color32::GRAY));
+            });
+            if let Some(location) = self.connection_status.sensor_location {
+                ui.label(format!("Sensor location: {}", location));
+            }
+            if self.connection_status.device_auto_detected {
+                ui.label(
+                    egui::RichText::new(
+                        "Auto-detected device. Set HEART_RATE_DEVICE_ADDRESS to pin it.",
+                    )
+                    .size(11.0)
+                    .color(LogLevel::Warn.color()),
+                );
+            }
+        }
+
         ui.horizontal(|ui| {
             ui.label("OSC Server");
         });
@@ -314,25 +1543,273 @@ impl HeartIOApp {
     }
 }
 
+/// Application build info shown in the About dialog
+fn build_info() -> [(&'static str, &'static str); 5] {
+    [
+        ("Version", env!("CARGO_PKG_VERSION")),
+        ("Build Time", env!("VERGEN_BUILD_TIMESTAMP")),
+        ("Target", env!("VERGEN_CARGO_TARGET_TRIPLE")),
+        ("Rust", env!("VERGEN_RUSTC_SEMVER")),
+        ("Commit", env!("VERGEN_GIT_SHA")),
+    ]
+}
+
+/// Resolved versions of key runtime dependencies, embedded by build.rs
+fn dependency_versions() -> [(&'static str, &'static str); 5] {
+    [
+        ("btleplug", env!("HEARTIO_DEP_BTLEPLUG")),
+        ("egui", env!("HEARTIO_DEP_EGUI")),
+        ("sqlx", env!("HEARTIO_DEP_SQLX")),
+        ("rosc", env!("HEARTIO_DEP_ROSC")),
+        ("axum", env!("HEARTIO_DEP_AXUM")),
+    ]
+}
+
+/// Plain text version of the About dialog for bug reports
+fn about_text() -> String {
+    let mut text = String::from("HeartIO\n");
+    for (name, value) in build_info().into_iter().chain(dependency_versions()) {
+        text.push_str(&format!("{}: {}\n", name, value));
+    }
+    text
+}
+
+/// Draw a heart icon scaled around its center, the space it takes stays fixed
+pub fn draw_pulsing_heart(ui: &mut egui::Ui, scale: f32, color: egui::Color32) {
+    // Leave room for the largest scale so the layout doesn't jump while beating
+    let size = egui::Vec2::splat(HEART_ICON_SIZE * 1.2);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    ui.painter().text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        "❤",
+        egui::FontId::proportional(HEART_ICON_SIZE * scale),
+        color,
+    );
+}
+
+/// Color for a heart rate zone, from blue at rest to red at max
+pub fn zone_color(zone: HeartRateZone) -> egui::Color32 {
+    match zone {
+        HeartRateZone::Rest => egui::Color32::from_rgb(100, 149, 237),
+        HeartRateZone::FatBurn => egui::Color32::from_rgb(60, 179, 113),
+        HeartRateZone::Cardio => egui::Color32::from_rgb(255, 215, 0),
+        HeartRateZone::Peak => egui::Color32::from_rgb(255, 140, 0),
+        HeartRateZone::Max => egui::Color32::from_rgb(220, 20, 60),
+    }
+}
+
+/// Faint background tint for a heart rate zone (10% opacity)
+pub fn zone_bg_color(zone: HeartRateZone) -> egui::Color32 {
+    let [r, g, b, _] = zone_color(zone).to_array();
+    egui::Color32::from_rgba_unmultiplied(r, g, b, 26)
+}
+
+/// Draw a small line chart of recent readings, scaled to their range plus 5% padding
+pub fn draw_sparkline(ui: &mut egui::Ui, history: &VecDeque<f32>, color: egui::Color32) {
+    let (rect, _) = ui.allocate_exact_size(SPARKLINE_SIZE, egui::Sense::hover());
+    if history.len() < 2 {
+        return;
+    }
+
+    let values = history.iter().skip(history.len().saturating_sub(SPARKLINE_LEN));
+    let count = history.len().min(SPARKLINE_LEN);
+    let (min, max) = values
+        .clone()
+        .fold((f32::MAX, f32::MIN), |(min, max), &v| (min.min(v), max.max(v)));
+    // Keep a flat line centered instead of dividing by zero
+    let padding = ((max - min) * 0.05).max(1.0);
+    let (low, high) = (min - padding, max + padding);
+
+    let points: Vec<egui::Pos2> = values
+        .enumerate()
+        .map(|(i, &v)| {
+            let x = rect.left() + rect.width() * i as f32 / (count - 1) as f32;
+            let y = rect.bottom() - rect.height() * (v - low) / (high - low);
+            egui::pos2(x, y)
+        })
+        .collect();
+
+    let stroke = egui::Stroke::new(1.5, color);
+    let painter = ui.painter();
+    for segment in points.windows(2) {
+        painter.line_segment([segment[0], segment[1]], stroke);
+    }
+}
+
+/// One bar per day, its height the average BPM, hovering a bar shows the details of its day
+fn draw_daily_bars(ui: &mut egui::Ui, days: &[DailySummary]) {
+    let bars = days
+        .iter()
+        .enumerate()
+        .map(|(index, day)| Bar::new(index as f64, day.avg_bpm).width(0.7))
+        .collect();
+    let hovered_days = days.to_vec();
+    let chart = BarChart::new(bars)
+        .color(egui::Color32::from_rgb(220, 20, 60))
+        .element_formatter(Box::new(move |bar, _| {
+            let Some(day) = hovered_days.get(bar.argument as usize) else {
+                return String::new();
+            };
+            let date = day.date.format("%A, %Y-%m-%d");
+            if day.count == 0 {
+                return format!("{}\nNo readings", date);
+            }
+            let duration = day.total_duration_secs.max(0);
+            format!(
+                "{}\nAverage: {:.0} BPM\nRange: {}-{} BPM\nReadings: {}\nSpan: {}h {:02}m",
+                date,
+                day.avg_bpm,
+                day.min_bpm,
+                day.max_bpm,
+                day.count,
+                duration / 3600,
+                duration / 60 % 60
+            )
+        }));
+
+    let dates: Vec<_> = days.iter().map(|day| day.date).collect();
+    Plot::new(("daily_summary", days.len()))
+        .height(ui.available_height().min(DAILY_CHART_HEIGHT))
+        .allow_zoom(false)
+        .allow_drag(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .allow_double_click_reset(false)
+        .show_x(false)
+        .show_y(false)
+        .include_y(0.0)
+        .y_axis_label("BPM")
+        .x_axis_formatter(move |mark, _| {
+            // Only whole positions are days
+            if mark.value.fract() != 0.0 || mark.value < 0.0 {
+                return String::new();
+            }
+            dates
+                .get(mark.value as usize)
+                .map(|date| date.format("%m-%d").to_string())
+                .unwrap_or_default()
+        })
+        .show(ui, |plot_ui| plot_ui.bar_chart(chart));
+}
+
+/// Small bar filled to the confidence of the latest reading, red to green
+fn draw_confidence_bar(ui: &mut egui::Ui, confidence: u8) {
+    let (rect, response) = ui.allocate_exact_size(CONFIDENCE_BAR_SIZE, egui::Sense::hover());
+    let fraction = confidence.min(100) as f32 / 100.0;
+    let color = if confidence >= 70 {
+        egui::Color32::from_rgb(60, 180, 75)
+    } else if confidence >= 40 {
+        egui::Color32::from_rgb(230, 180, 30)
+    } else {
+        egui::Color32::from_rgb(220, 50, 50)
+    };
+
+    let painter = ui.painter();
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    let filled = egui::Rect::from_min_size(
+        rect.min,
+        egui::vec2(rect.width() * fraction, rect.height()),
+    );
+    painter.rect_filled(filled, 2.0, color);
+    response.on_hover_text(format!(
+        "Confidence {}%, from sensor contact, steady readings and agreement with recent ones",
+        confidence
+    ));
+}
+
+/// Build a label layout with the parts matching `filter` (already lowercased) highlighted
+fn highlight_matches(
+    ui: &egui::Ui,
+    message: &str,
+    filter: &str,
+    color: egui::Color32,
+) -> egui::text::LayoutJob {
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    let normal = egui::TextFormat {
+        font_id: font_id.clone(),
+        color,
+        ..Default::default()
+    };
+    let highlighted = egui::TextFormat {
+        font_id,
+        color: ui.visuals().strong_text_color(),
+        background: color.gamma_multiply(0.35),
+        ..Default::default()
+    };
+
+    let mut job = egui::text::LayoutJob::default();
+    let lowercase = message.to_lowercase();
+
+    // Lowercasing can change byte lengths for some characters, skip highlighting then
+    if filter.is_empty() || lowercase.len() != message.len() {
+        job.append(message, 0.0, normal);
+        return job;
+    }
+
+    let mut last_end = 0;
+    for (start, matched) in lowercase.match_indices(filter) {
+        let end = start + matched.len();
+        if !message.is_char_boundary(start) || !message.is_char_boundary(end) {
+            continue;
+        }
+        if start > last_end {
+            job.append(&message[last_end..start], 0.0, normal.clone());
+        }
+        job.append(&message[start..end], 0.0, highlighted.clone());
+        last_end = end;
+    }
+    if last_end < message.len() {
+        job.append(&message[last_end..], 0.0, normal);
+    }
+
+    job
+}
+
 /// Create and run the GUI application
 pub async fn run_gui_app(
+    config: Config,
     log_receiver: mpsc::Receiver<LogEntry>,
-    heart_rate_receiver: mpsc::Receiver<u32>,
+    heart_rate_receiver: mpsc::Receiver<HeartRateReading>,
+    connection_status_receiver: mpsc::Receiver<ConnectionStatus>,
+    history_receiver: mpsc::Receiver<HistoryUpdate>,
+    command_sender: tokio_mpsc::UnboundedSender<MonitorCommand>,
 ) -> Result<()> {
+    let window_prefs = WindowPrefs::load();
+
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size([800.0, 600.0])
+        .with_min_inner_size([600.0, 400.0])
+        .with_icon(eframe::icon_data::from_png_bytes(&[]).unwrap_or_default());
+    if let Some(prefs) = window_prefs {
+        viewport = viewport
+            .with_position([prefs.x, prefs.y])
+            .with_inner_size([prefs.width, prefs.height]);
+    }
+
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([800.0, 600.0])
-            .with_min_inner_size([600.0, 400.0])
-            .with_icon(eframe::icon_data::from_png_bytes(&[]).unwrap_or_default()),
+        viewport,
         ..Default::default()
     };
 
-    let app = HeartIOApp::new(log_receiver, heart_rate_receiver);
+    let app = HeartIOApp::new(
+        config,
+        log_receiver,
+        heart_rate_receiver,
+        connection_status_receiver,
+        history_receiver,
+        command_sender,
+        window_prefs,
+    );
 
     eframe::run_native(
         "HeartIO - Heart Rate Monitor",
         options,
-        Box::new(|_cc| Ok(Box::new(app))),
+        Box::new(move |cc| {
+            let mut app = app;
+            app.tray = tray::create(cc, app.command_sender.clone());
+            Ok(Box::new(app))
+        }),
     )
     .map_err(|e| anyhow::anyhow!("GUI application error: {}", e))?;
 
@@ -1,33 +1,224 @@
 // Heart rate monitoring and processing for HeartIO
 use anyhow::Result;
-use std::sync::mpsc;
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use rosc::OscType;
+use std::sync::{mpsc, Arc, RwLock};
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc as tokio_mpsc;
-use tokio::time::interval;
+use tokio::sync::oneshot;
+use std::collections::{HashMap, HashSet};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, timeout, Interval};
 
-use crate::bluetooth::BluetoothHeartRateMonitor;
-use crate::config::Config;
-use crate::database::Database;
-use crate::gui::{AppStats, ConnectionStatus, LogEntry, LogLevel};
+use crate::bluetooth::{BluetoothHeartRateMonitor, ConnectionState, ScanError, ScanProgress};
+use crate::config::{render_template, Config, HeartRateShareMode, MonitorMode, TemplateContext};
+use crate::database::{with_retries, Database};
+use crate::gui::{AppStats, ConnectionStatus, HistoryUpdate, LogEntry, LogLevel};
+use crate::multicast::MulticastSender;
+use crate::obs_server::ObsServer;
 use crate::osc::OscClient;
-use crate::server::AppleWatchServer;
+use crate::server::{AppleWatchSource, APPLE_WATCH_PORT};
+use crate::share::{ShareClient, ShareServer, SharedReading};
+use crate::source::{HeartRateSource, SimulatedSource};
+use crate::stats::{ConfidenceScorer, EnergyAccumulator, IntervalStats, WelfordStats};
 use crate::system::SystemUtils;
 use crate::xiaomi_band::XiaomiBandMonitor;
+use crate::zones::{AlarmTracker, HeartRateZone, ZoneTracker};
+
+/// A heart rate reading tagged with the source it came from
+#[derive(Debug, Clone)]
+pub struct HeartRateReading {
+    pub source: MonitorMode,
+    pub bpm: u32,
+    /// When the sensor measured the value, if the source reports it
+    pub measured_at: Option<DateTime<Utc>>,
+    /// Name of the sending device or app, if the source reports it
+    pub device: Option<String>,
+    /// Cumulative energy expended in kJ, if the device reports it
+    pub energy_expended_kj: Option<u16>,
+    /// Whether the sensor has skin contact, if the device reports it
+    pub sensor_contact: Option<bool>,
+    /// 0-100, scored by the monitor before the reading is passed on
+    pub confidence: Option<u8>,
+    /// Estimated kcal burned this session, filled in by the monitor when a calorie profile is set
+    pub session_kcal: Option<f32>,
+}
+
+impl HeartRateReading {
+    /// Create a reading measured just now
+    pub fn new(source: MonitorMode, bpm: u32) -> Self {
+        Self {
+            source,
+            bpm,
+            measured_at: None,
+            device: None,
+            energy_expended_kj: None,
+            sensor_contact: None,
+            confidence: None,
+            session_kcal: None,
+        }
+    }
+}
+
+/// Events sent from running sources to the monitor
+#[derive(Debug, Clone)]
+enum SourceEvent {
+    Reading(HeartRateReading),
+    /// A Bluetooth device was connected, with its name and address
+    DeviceConnected { name: String, address: String },
+    /// Bluetooth scan progress, or `None` once the scan has finished
+    ScanProgress(Option<ScanProgress>),
+    /// Body sensor location reported by the connected Bluetooth device
+    SensorLocation(&'static str),
+    /// Bluetooth could not scan for a reason the user has to fix
+    ScanFailed(ScanError),
+    /// The Apple Watch server started or stopped listening
+    ServerListening(bool),
+}
+
+/// Background task running a source until told to stop
+struct SourceTask {
+    stop_sender: oneshot::Sender<()>,
+    command_sender: tokio_mpsc::UnboundedSender<SourceCommand>,
+    handle: JoinHandle<()>,
+}
+
+/// Requests passed on to a running source
+#[derive(Debug, Clone, Copy)]
+enum SourceCommand {
+    ResetEnergyExpended,
+}
+
+/// Background task toggling the heartbeat avatar parameters
+struct HeartbeatTask {
+    bpm: u32,
+    handle: JoinHandle<()>,
+}
+
+/// Heart rate label last sent to the chatbox
+struct SentLabel {
+    bpm: u32,
+    text: String,
+    sent_at: Instant,
+}
+
+impl SentLabel {
+    /// Whether sending `text` for `bpm` would only repeat this label, younger than `max_age`
+    fn repeats(&self, bpm: u32, text: &str, now: Instant, max_age: Duration) -> bool {
+        self.bpm == bpm && self.text == text && now.duration_since(self.sent_at) < max_age
+    }
+}
+
+/// Avatar state parameter values last sent over OSC
+#[derive(Debug, Default)]
+struct StateParams {
+    connected: Option<bool>,
+    paused: Option<bool>,
+    zone: Option<HeartRateZone>,
+}
+
+/// BPM change that restarts the heartbeat task to avoid drift
+const HEARTBEAT_RESTART_DELTA: u32 = 5;
+
+/// How often the reading interval statistics are logged
+const INTERVAL_LOG_PERIOD: Duration = Duration::from_secs(60);
+
+/// How often the database statistics shown in the GUI are refreshed
+const HISTORY_STATS_PERIOD: Duration = Duration::from_secs(60);
+/// Days of daily summaries sent to the GUI, the longest history view
+const DAILY_SUMMARY_DAYS: u32 = 30;
+
+/// Longest a single shutdown step may take before it is abandoned
+const SHUTDOWN_STEP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Commands sent from the GUI to the running monitor
+#[derive(Debug)]
+pub enum MonitorCommand {
+    /// Apply an edited configuration
+    UpdateConfig(Box<Config>),
+    /// Cancel an ongoing Bluetooth scan
+    CancelScan,
+    /// Start a new session, clearing the statistics
+    ResetSession,
+    /// Pause or resume monitoring, like SIGTSTP and SIGCONT
+    SetPaused(bool),
+    /// Reset the energy expended counter of the connected Bluetooth strap
+    ResetEnergyExpended,
+}
 
 pub struct HeartRateMonitor {
     config: Config,
     database: Option<Database>,
-    osc_client: Option<OscClient>,
-    bluetooth_monitor: Option<BluetoothHeartRateMonitor>,
-    xiaomi_band_monitor: Option<XiaomiBandMonitor>,
+    session_id: Option<i64>,
+    /// A heart rate was saved, later writes are no longer retried
+    database_written: bool,
+    /// The database file could not be used, history is kept in memory only
+    persist_failed: bool,
+    osc_clients: Vec<OscClient>,
+    /// Chatbox sends in a row that reached no OSC target
+    osc_failures: u32,
+    osc_unreachable: bool,
+    multicast: Option<MulticastSender>,
+    share_server: Option<ShareServer>,
+    share_client: Option<ShareClient>,
+    remote_receiver: Option<tokio_mpsc::UnboundedReceiver<SharedReading>>,
+    obs_server: Option<ObsServer>,
     system_utils: SystemUtils,
     log_sender: mpsc::Sender<LogEntry>,
-    gui_heart_rate_sender: mpsc::Sender<u32>,
+    gui_heart_rate_sender: mpsc::Sender<HeartRateReading>,
+    connection_status_sender: mpsc::Sender<ConnectionStatus>,
+    history_sender: mpsc::Sender<HistoryUpdate>,
+    command_receiver: tokio_mpsc::UnboundedReceiver<MonitorCommand>,
+    sources: Vec<MonitorMode>,
+    source_last_seen: HashMap<MonitorMode, Instant>,
+    active_source: Option<MonitorMode>,
+    connected_device: Option<(String, String)>,
+    scan_progress: Option<ScanProgress>,
+    scan_error: Option<ScanError>,
+    /// The Apple Watch server is bound and accepting requests
+    apple_watch_listening: bool,
+    sensor_location: Option<&'static str>,
+    scan_cancel_sender: Option<oneshot::Sender<()>>,
+    source_tasks: HashMap<MonitorMode, SourceTask>,
+    /// Weak so the event channel still closes once every source has stopped
+    event_sender: Option<tokio_mpsc::WeakUnboundedSender<SourceEvent>>,
+    heartbeat: Option<HeartbeatTask>,
+    state_params: StateParams,
+    zone_tracker: ZoneTracker,
+    alarm_tracker: AlarmTracker,
+    /// Changed only through `SetPaused`, readings skip the database and OSC while set
+    paused: bool,
+    /// Sources stopped by the pause while still scanning, started again on resume
+    paused_scans: HashSet<MonitorMode>,
+    warmup_ends_at: Option<Instant>,
+    warmup_finished: bool,
     last_send_time: Instant,
+    /// Cleared by any other chatbox message, so the label is restored after it
+    last_sent_label: Option<SentLabel>,
+    /// Newest reading held back by the rate limit, sent once the interval elapses
+    pending_heart_rate: Option<u32>,
     last_receive_time: Option<Instant>,
+    offline_sent: bool,
+    /// The zero reading message was sent for the current run of 0 BPM readings
+    zero_reading_sent: bool,
+    stale_sent: bool,
     start_time: Instant,
-    heart_rate_count: u32,
-    heart_rate_sum: u32,
+    /// Wall clock time of `start_time`, for display and export
+    session_started_at: chrono::DateTime<chrono::Local>,
+    heart_rate_stats: WelfordStats,
+    energy: EnergyAccumulator,
+    calories_burned: f32,
+    reading_intervals: IntervalStats,
+    confidence: ConfidenceScorer,
+    last_interval_log: Instant,
+    history_stats_at: Option<Instant>,
+    last_heart_rate: Option<u32>,
+    previous_heart_rate: Option<u32>,
+    last_heart_rate_at: Option<chrono::DateTime<chrono::Local>>,
+    min_heart_rate: Option<u32>,
+    max_heart_rate: Option<u32>,
+    shared_stats: Arc<RwLock<AppStats>>,
 }
 
 impl HeartRateMonitor {
@@ -35,83 +226,316 @@ impl HeartRateMonitor {
     pub fn new(
         config: Config,
         log_sender: mpsc::Sender<LogEntry>,
-        gui_heart_rate_sender: mpsc::Sender<u32>,
+        gui_heart_rate_sender: mpsc::Sender<HeartRateReading>,
+        connection_status_sender: mpsc::Sender<ConnectionStatus>,
+        history_sender: mpsc::Sender<HistoryUpdate>,
+        command_receiver: tokio_mpsc::UnboundedReceiver<MonitorCommand>,
     ) -> Self {
+        let sources = config.sources();
+        let zone_tracker = ZoneTracker::new(Duration::from_secs(config.zone_change_debounce_secs));
+        let start_time = Instant::now();
+        let session_started_at = chrono::Local::now();
+        let shared_stats = AppStats {
+            session_start_time: Some(session_started_at),
+            ..AppStats::default()
+        };
+
         Self {
             config,
             database: None,
-            osc_client: None,
-            bluetooth_monitor: None,
-            xiaomi_band_monitor: None,
+            session_id: None,
+            database_written: false,
+            persist_failed: false,
+            osc_clients: Vec::new(),
+            osc_failures: 0,
+            osc_unreachable: false,
+            multicast: None,
+            share_server: None,
+            share_client: None,
+            remote_receiver: None,
+            obs_server: None,
             system_utils: SystemUtils::new(),
             log_sender,
             gui_heart_rate_sender,
+            connection_status_sender,
+            history_sender,
+            command_receiver,
+            sources,
+            source_last_seen: HashMap::new(),
+            active_source: None,
+            connected_device: None,
+            scan_progress: None,
+            scan_error: None,
+            apple_watch_listening: false,
+            sensor_location: None,
+            scan_cancel_sender: None,
+            source_tasks: HashMap::new(),
+            event_sender: None,
+            heartbeat: None,
+            state_params: StateParams::default(),
+            zone_tracker,
+            alarm_tracker: AlarmTracker::default(),
+            paused: false,
+            paused_scans: HashSet::new(),
+            warmup_ends_at: None,
+            warmup_finished: false,
             last_send_time: Instant::now() - Duration::from_secs(10), // Allow immediate first send
+            last_sent_label: None,
+            pending_heart_rate: None,
             last_receive_time: None,
-            start_time: Instant::now(),
-            heart_rate_count: 0,
-            heart_rate_sum: 0,
+            offline_sent: false,
+            zero_reading_sent: false,
+            stale_sent: false,
+            start_time,
+            session_started_at,
+            heart_rate_stats: WelfordStats::default(),
+            energy: EnergyAccumulator::default(),
+            calories_burned: 0.0,
+            reading_intervals: IntervalStats::default(),
+            confidence: ConfidenceScorer::default(),
+            last_interval_log: Instant::now(),
+            history_stats_at: None,
+            last_heart_rate: None,
+            previous_heart_rate: None,
+            last_heart_rate_at: None,
+            min_heart_rate: None,
+            max_heart_rate: None,
+            shared_stats: Arc::new(RwLock::new(shared_stats)),
         }
     }
 
     /// Start the heart rate monitoring system
-    pub async fn start(&mut self) -> Result<()> {
+    /// Uses `source_override` instead of the configured sources when given, e.g. a mock in tests
+    pub async fn start(&mut self, source_override: Option<Box<dyn HeartRateSource>>) -> Result<()> {
         self.log_info("Starting HeartIO heart rate monitor...".to_string());
 
         // Initialize database
-        self.init_database().await?;
+        self.init_database().await;
 
         // Initialize OSC client
         self.init_osc_client().await?;
+        self.init_multicast();
+        self.init_sharing().await;
+        self.init_obs_server().await;
 
         // Keep system awake
         self.keep_system_awake()?;
 
-        // Start monitoring based on configuration
-        if self.config.xiaomi_band.is_some_and(|enabled| enabled) {
-            self.start_xiaomi_band_mode().await?;
-        } else if self.config.apple_watch {
-            self.start_apple_watch_mode().await?;
-        } else {
-            self.start_bluetooth_mode().await?;
-        }
+        // Monitoring starts unpaused, the sensor reports connected with its first reading
+        self.set_paused_param(false).await;
+        self.set_connected_param(false).await;
 
-        Ok(())
+        // Start monitoring based on configuration
+        self.run_sources(source_override).await
     }
 
     /// Initialize database connection
-    async fn init_database(&mut self) -> Result<()> {
-        match Database::new().await {
+    /// Falls back to an in-memory database, or none at all, so monitoring keeps running
+    async fn init_database(&mut self) {
+        let database = if self.config.persist {
+            let backup_count = self.config.db_backup_count;
+            match Database::new(self.config.db_path.as_deref(), backup_count).await {
+                Ok(db) => Ok(db),
+                Err(e) => {
+                    self.log_warn(format!(
+                        "Failed to open the database, heart rate history will not be saved: {:#}",
+                        e
+                    ));
+                    self.persist_failed = true;
+                    Database::in_memory().await
+                }
+            }
+        } else {
+            Database::in_memory().await
+        };
+
+        match database {
             Ok(db) => {
+                self.session_id = match db.start_session().await {
+                    Ok(id) => Some(id),
+                    Err(e) => {
+                        self.log_warn(format!("Failed to start database session: {}", e));
+                        None
+                    }
+                };
                 self.database = Some(db);
-                self.log_info("Database initialized successfully".to_string());
-                Ok(())
+                if self.config.persist {
+                    if !self.persist_failed {
+                        self.log_info("Database initialized successfully".to_string());
+                    }
+                } else {
+                    self.log_warn("Persistence is off, heart rate history will not be saved".to_string());
+                }
             }
             Err(e) => {
-                self.log_error(format!("Failed to initialize database: {}", e));
-                Err(e)
+                self.log_error(format!("Failed to initialize database, continuing without: {}", e));
             }
         }
+        self.send_connection_status();
+    }
+
+    /// Switch to an in-memory database after the database file turned out to be unusable
+    async fn degrade_to_memory_database(&mut self, error: anyhow::Error) {
+        self.log_warn(format!(
+            "Saving heart rates failed, continuing without saving history: {:#}",
+            error
+        ));
+        self.persist_failed = true;
+        self.database = Database::in_memory().await.ok();
+        self.session_id = None;
+        if let Some(db) = &self.database {
+            self.session_id = db.start_session().await.ok();
+        }
+        self.send_connection_status();
     }
 
     /// Initialize OSC client
     async fn init_osc_client(&mut self) -> Result<()> {
-        match OscClient::new(self.config.osc_host.clone(), self.config.osc_port) {
-            Ok(client) => {
-                self.osc_client = Some(client);
-                self.log_info(format!(
-                    "OSC client initialized for {}:{}",
-                    self.config.osc_host, self.config.osc_port
-                ));
-                Ok(())
+        let mut clients = Vec::new();
+
+        for (index, (host, port)) in self.config.osc_endpoints().into_iter().enumerate() {
+            let bind_addr = self.config.osc_bind_addr.as_deref();
+            match OscClient::new(host.clone(), port, self.config.osc_typing_indicator, bind_addr) {
+                Ok(client) => {
+                    self.log_info(format!("OSC client initialized for {}:{}", host, port));
+                    clients.push(client);
+                }
+                // The primary target is required, extra targets are skipped
+                Err(e) if index == 0 => {
+                    self.log_error(format!("Failed to initialize OSC client: {}", e));
+                    return Err(e);
+                }
+                Err(e) => {
+                    self.log_warn(format!(
+                        "Skipping OSC target {}:{}, failed to initialize: {}",
+                        host, port, e
+                    ));
+                }
             }
-            Err(e) => {
-                self.log_error(format!("Failed to initialize OSC client: {}", e));
-                Err(e)
+        }
+
+        self.osc_clients = clients;
+        self.send_connection_status();
+        Ok(())
+    }
+
+    /// Set up the LAN multicast sender if enabled, monitoring works without it
+    fn init_multicast(&mut self) {
+        self.multicast = None;
+        if !self.config.multicast_enabled {
+            return;
+        }
+        match MulticastSender::new(&self.config.multicast_group, self.config.multicast_port) {
+            Ok(sender) => {
+                self.log_info(format!("Broadcasting heart rate to multicast {}", sender.target()));
+                self.multicast = Some(sender);
+            }
+            Err(e) => self.log_warn(format!("Multicast disabled: {:#}", e)),
+        }
+    }
+
+    /// Start the OBS overlay server if enabled, monitoring works without it
+    async fn init_obs_server(&mut self) {
+        self.obs_server = None;
+        if !self.config.obs_server_enabled {
+            return;
+        }
+        let port = self.config.obs_server_port;
+        match ObsServer::start(port).await {
+            Ok(server) => {
+                self.log_info(format!("OBS overlay available at http://localhost:{}/", port));
+                self.obs_server = Some(server);
+            }
+            Err(e) => self.log_warn(format!("OBS overlay disabled: {:#}", e)),
+        }
+    }
+
+    /// Start the sharing server or client for the configured share mode, failures are not fatal
+    async fn init_sharing(&mut self) {
+        self.share_server = None;
+        self.share_client = None;
+        self.remote_receiver = None;
+
+        match self.config.share_mode.clone() {
+            HeartRateShareMode::None => {}
+            HeartRateShareMode::Server { port } => match ShareServer::start(port).await {
+                Ok(server) => {
+                    self.log_info(format!("Sharing heart rate on TCP port {}", port));
+                    self.share_server = Some(server);
+                }
+                Err(e) => self.log_warn(format!("Heart rate sharing disabled: {:#}", e)),
+            },
+            HeartRateShareMode::Client { host, port } => {
+                self.log_info(format!("Receiving shared heart rate from {}:{}", host, port));
+                let (sender, receiver) = tokio_mpsc::unbounded_channel();
+                self.share_client = Some(ShareClient::start(host, port, sender));
+                self.remote_receiver = Some(receiver);
             }
         }
     }
 
+    /// Forward the heart rate of the remote instance to its avatar parameter
+    async fn handle_remote_reading(&mut self, reading: SharedReading) {
+        self.log_debug(format!("Received shared heart rate: {} BPM", reading.bpm));
+        if self.paused {
+            return;
+        }
+        self.send_avatar_param(
+            self.config.osc_avatar_param_remote.clone(),
+            OscType::Int(reading.bpm as i32),
+        )
+        .await;
+    }
+
+    /// Send a chatbox message to every OSC target, returns whether any send succeeded
+    async fn broadcast_message(&mut self, text: &str) -> bool {
+        self.last_sent_label = None;
+        let sends = self.osc_clients.iter().map(|client| client.send_message(text));
+        let results = join_all(sends).await;
+
+        let mut sent = false;
+        for (client, result) in self.osc_clients.iter().zip(results) {
+            match result {
+                Ok(_) => sent = true,
+                Err(e) => {
+                    let message =
+                        format!("Failed to send OSC message to {}: {}", client.target(), e);
+                    // Already reported as unreachable, keep the repeats out of the main log
+                    if self.osc_unreachable {
+                        self.log_debug(message);
+                    } else {
+                        self.log_error(message);
+                    }
+                }
+            }
+        }
+        self.track_osc_failures(sent);
+        sent
+    }
+
+    /// Count failed sends and flag the OSC target as unreachable after a burst of failures
+    fn track_osc_failures(&mut self, sent: bool) {
+        if sent {
+            self.osc_failures = 0;
+            if self.osc_unreachable {
+                self.osc_unreachable = false;
+                self.log_info("OSC target reachable again".to_string());
+                self.send_connection_status();
+            }
+            return;
+        }
+
+        self.osc_failures = self.osc_failures.saturating_add(1);
+        if !self.osc_unreachable && self.osc_failures >= self.config.osc_failure_threshold.max(1) {
+            self.osc_unreachable = true;
+            self.log_warn(format!(
+                "OSC target unreachable after {} failed sends, is VRChat running?",
+                self.osc_failures
+            ));
+            self.send_connection_status();
+        }
+    }
+
     /// Keep system awake
     fn keep_system_awake(&mut self) -> Result<()> {
         match self.system_utils.keep_system_awake() {
@@ -126,40 +550,92 @@ impl HeartRateMonitor {
         }
     }
 
-    /// Start Apple Watch server mode
-    async fn start_apple_watch_mode(&mut self) -> Result<()> {
-        self.log_info("Starting Apple Watch server mode...".to_string());
+    /// Start the heart rate sources and process their readings
+    async fn run_sources(
+        &mut self,
+        source_override: Option<Box<dyn HeartRateSource>>,
+    ) -> Result<()> {
+        let (event_sender, mut event_receiver) = tokio_mpsc::unbounded_channel();
+        self.event_sender = Some(event_sender.downgrade());
 
-        let (heart_rate_sender, mut heart_rate_receiver) = tokio_mpsc::unbounded_channel();
+        // Held until monitoring ends, dropping it stops the source
+        let mut _override_task = None;
+        if let Some(source) = source_override {
+            let log_sender = self.log_sender.clone();
+            let task = spawn_source(source, "Heart rate source", event_sender.clone(), log_sender);
+            _override_task = Some(task);
+        } else {
+            if self.sources.len() > 1 {
+                let names: Vec<&str> = self.sources.iter().map(|source| source.label()).collect();
+                self.log_info(format!(
+                    "Starting heart rate sources in priority order: {}",
+                    names.join(", ")
+                ));
+            }
 
-        // Start Apple Watch server
-        let server = AppleWatchServer::new(heart_rate_sender);
-        let mut server_task = tokio::spawn(async move {
-            if let Err(e) = server.start(2333).await {
-                tracing::error!("Apple Watch server error: {}", e);
+            for source in self.sources.clone() {
+                self.start_source(source, event_sender.clone());
             }
-        });
+        }
 
-        self.log_info("Apple Watch server started on port 2333".to_string());
+        // Only the source tasks hold senders now, so the channel closes once all of them stop
+        drop(event_sender);
 
         // Start timeout checker
-        let mut timeout_task = self.start_timeout_checker().await;
+        let mut timeout_checker = self.start_timeout_checker();
 
         // Process heart rate data
         loop {
             tokio::select! {
-                heart_rate = heart_rate_receiver.recv() => {
-                    if let Some(heart_rate) = heart_rate {
-                        self.process_heart_rate(heart_rate).await?;
+                event = event_receiver.recv() => match event {
+                    Some(SourceEvent::Reading(reading)) => self.handle_reading(reading).await?,
+                    Some(SourceEvent::DeviceConnected { name, address }) => {
+                        self.handle_device_connected(name, address).await;
                     }
+                    Some(SourceEvent::ScanProgress(progress)) => {
+                        self.handle_scan_progress(progress);
+                    }
+                    Some(SourceEvent::ServerListening(listening)) => {
+                        if listening {
+                            self.log_info(format!(
+                                "Apple Watch server started on port {}",
+                                APPLE_WATCH_PORT
+                            ));
+                        }
+                        self.apple_watch_listening = listening;
+                        self.send_connection_status();
+                    }
+                    Some(SourceEvent::SensorLocation(location)) => {
+                        self.log_info(format!("Sensor location: {}", location));
+                        self.sensor_location = Some(location);
+                        self.send_connection_status();
+                    }
+                    Some(SourceEvent::ScanFailed(scan_error)) => {
+                        self.scan_error = Some(scan_error);
+                        self.send_connection_status();
+                    }
+                    None => {
+                        self.log_error("All heart rate sources stopped".to_string());
+                        break;
+                    }
+                },
+                _ = timeout_checker.tick() => {
+                    self.set_paused_param(self.paused).await;
+                    self.check_timeout().await;
+                    self.refresh_history_stats().await;
                 }
-                _ = &mut timeout_task => {
-                    self.log_error("Timeout checker completed".to_string());
-                    break;
+                _ = tokio::time::sleep_until(self.next_send_time().into()),
+                    if self.pending_heart_rate.is_some() =>
+                {
+                    if let Some(heart_rate) = self.pending_heart_rate.take() {
+                        self.send_osc_message(heart_rate).await?;
+                    }
+                }
+                Some(command) = self.command_receiver.recv() => {
+                    self.handle_command(command).await;
                 }
-                _ = &mut server_task => {
-                    self.log_error("Apple Watch server stopped".to_string());
-                    break;
+                Some(reading) = recv_optional(&mut self.remote_receiver) => {
+                    self.handle_remote_reading(reading).await;
                 }
             }
         }
@@ -167,163 +643,770 @@ impl HeartRateMonitor {
         Ok(())
     }
 
-    /// Start Bluetooth monitoring mode
-    async fn start_bluetooth_mode(&mut self) -> Result<()> {
-        self.log_info("Starting Bluetooth monitoring mode...".to_string());
+    /// Start a source in the background, remembering its task so it can be stopped later
+    fn start_source(
+        &mut self,
+        mode: MonitorMode,
+        event_sender: tokio_mpsc::UnboundedSender<SourceEvent>,
+    ) {
+        if self.paused && self.scans_for_device(mode) {
+            self.log_info(format!("{} starts once monitoring is resumed", mode.label()));
+            self.paused_scans.insert(mode);
+            return;
+        }
+        let task = match mode {
+            MonitorMode::Bluetooth => self.start_bluetooth_source(event_sender),
+            MonitorMode::AppleWatch => self.start_apple_watch_source(event_sender),
+            MonitorMode::XiaomiBand => self.start_xiaomi_band_source(event_sender),
+            MonitorMode::Simulated => self.start_simulated_source(event_sender),
+        };
+        self.source_tasks.insert(mode, task);
+    }
 
-        // Initialize Bluetooth monitor
-        let bluetooth_monitor = BluetoothHeartRateMonitor::new().await?;
+    /// Stop a running source and wait for it to release its device or port
+    async fn stop_source(&mut self, mode: MonitorMode) {
+        self.paused_scans.remove(&mode);
+        let Some(task) = self.source_tasks.remove(&mode) else {
+            return;
+        };
+        if mode == MonitorMode::Bluetooth {
+            if let Some(sender) = self.scan_cancel_sender.take() {
+                let _ = sender.send(());
+            }
+        }
+        let _ = task.stop_sender.send(());
+        let mut handle = task.handle;
+        if timeout(SHUTDOWN_STEP_TIMEOUT, &mut handle).await.is_err() {
+            self.log_warn(format!("{} source did not stop in time, aborting it", mode.label()));
+            handle.abort();
+        }
+        self.source_last_seen.remove(&mode);
 
-        // Connect to device
-        let device_name = self.config.heart_rate_device_name.as_deref();
-        let device_address = self.config.heart_rate_device_address.as_deref();
+        if mode == MonitorMode::AppleWatch {
+            self.apple_watch_listening = false;
+            self.send_connection_status();
+        }
+        if mode == MonitorMode::Bluetooth {
+            self.connected_device = None;
+            self.sensor_location = None;
+            self.scan_progress = None;
+            self.scan_error = None;
+            self.send_connection_status();
+        }
+    }
 
-        // Use a separate variable to connect, then store it
-        let mut connected_monitor = bluetooth_monitor;
-        connected_monitor
-            .connect(device_name, device_address)
-            .await?;
-        self.log_info("Connected to Bluetooth heart rate device".to_string());
+    /// Whether a source is still looking for its device rather than receiving readings
+    fn scans_for_device(&self, mode: MonitorMode) -> bool {
+        match mode {
+            MonitorMode::Bluetooth => self.connected_device.is_none(),
+            // Readings arrive in the advertisements, so the scan never ends
+            MonitorMode::XiaomiBand => true,
+            MonitorMode::AppleWatch | MonitorMode::Simulated => false,
+        }
+    }
 
-        // Store the bluetooth monitor to prevent it from being dropped
-        self.bluetooth_monitor = Some(connected_monitor);
+    /// Pause or resume monitoring. Pausing also stops the sources still scanning for a device
+    /// and resuming starts them again, a connected strap stays connected.
+    async fn set_paused(&mut self, paused: bool) {
+        if self.paused == paused {
+            return;
+        }
+        self.paused = paused;
+        let message = if paused { "Monitoring paused" } else { "Monitoring resumed" };
+        self.log_info(message.to_string());
+        self.set_paused_param(paused).await;
 
-        // Start timeout checker
-        let _timeout_task = self.start_timeout_checker().await;
-
-        // Start monitoring with callback
-        let (heart_rate_sender, mut heart_rate_receiver) = tokio_mpsc::unbounded_channel();
-
-        // Take the bluetooth monitor out of self to move it into the task
-        if let Some(bluetooth_monitor) = self.bluetooth_monitor.take() {
-            let mut monitoring_task = tokio::spawn(async move {
-                if let Err(e) = bluetooth_monitor
-                    .start_monitoring(move |heart_rate| {
-                        let _ = heart_rate_sender.send(heart_rate);
-                    })
-                    .await
-                {
-                    tracing::error!("Bluetooth monitoring error: {}", e);
-                }
-            });
+        if paused {
+            let scanning: Vec<MonitorMode> = self
+                .source_tasks
+                .keys()
+                .copied()
+                .filter(|mode| self.scans_for_device(*mode))
+                .collect();
+            for mode in scanning {
+                self.log_info(format!("Stopping the {} scan while paused", mode.label()));
+                self.stop_source(mode).await;
+                self.paused_scans.insert(mode);
+            }
+        } else if let Some(event_sender) =
+            self.event_sender.as_ref().and_then(|sender| sender.upgrade())
+        {
+            for mode in std::mem::take(&mut self.paused_scans) {
+                self.log_info(format!("Resuming the {} scan", mode.label()));
+                self.start_source(mode, event_sender.clone());
+            }
+        }
+        self.send_connection_status();
+    }
 
-            // Process heart rate data
-            loop {
-                tokio::select! {
-                    heart_rate = heart_rate_receiver.recv() => {
-                        if let Some(heart_rate) = heart_rate {
-                            self.process_heart_rate(heart_rate).await?;
-                        } else {
-                            // Channel closed, break the loop
-                            break;
-                        }
+    /// Stop a running source and start it again with the current settings, keeping the GUI,
+    /// database and OSC clients alive
+    async fn restart_source(&mut self, mode: MonitorMode) {
+        let Some(event_sender) = self.event_sender.as_ref().and_then(|sender| sender.upgrade())
+        else {
+            self.log_warn(format!("Cannot restart {}, monitoring has stopped", mode.label()));
+            return;
+        };
+
+        self.log_info(format!("Restarting {} with the new settings...", mode.label()));
+        self.stop_source(mode).await;
+        self.start_source(mode, event_sender);
+    }
+
+    /// Stop sources that are no longer wanted and start the new ones, e.g. when the mode is
+    /// switched in the GUI
+    async fn switch_sources(&mut self, sources: Vec<MonitorMode>) {
+        let Some(event_sender) = self.event_sender.as_ref().and_then(|sender| sender.upgrade())
+        else {
+            self.log_warn("Cannot switch sources, monitoring has stopped".to_string());
+            return;
+        };
+
+        let names: Vec<&str> = sources.iter().map(|source| source.label()).collect();
+        self.log_info(format!("Switching heart rate sources to {}", names.join(", ")));
+
+        for mode in self.sources.clone() {
+            if !sources.contains(&mode) {
+                self.stop_source(mode).await;
+            }
+        }
+        let previous = std::mem::replace(&mut self.sources, sources.clone());
+        for mode in sources {
+            if !previous.contains(&mode) {
+                self.start_source(mode, event_sender.clone());
+            }
+        }
+        if self
+            .active_source
+            .is_some_and(|active| !self.sources.contains(&active))
+        {
+            self.active_source = None;
+        }
+
+        self.send_connection_status();
+    }
+
+    /// Start Apple Watch server source
+    fn start_apple_watch_source(
+        &self,
+        event_sender: tokio_mpsc::UnboundedSender<SourceEvent>,
+    ) -> SourceTask {
+        self.log_info("Starting Apple Watch server mode...".to_string());
+
+        let listening_sender = event_sender.clone();
+        let source = AppleWatchSource::start(
+            self.config.bpm_range(),
+            self.shared_stats.clone(),
+            self.config.apple_watch_bind_address.clone(),
+            APPLE_WATCH_PORT,
+            self.config.apple_watch_max_rps,
+            self.database.clone(),
+            move |listening| {
+                let _ = listening_sender.send(SourceEvent::ServerListening(listening));
+            },
+        );
+        let log_sender = self.log_sender.clone();
+        spawn_source(Box::new(source), "Apple Watch server", event_sender, log_sender)
+    }
+
+    /// Start Bluetooth source, connecting in the background so other sources are not blocked
+    fn start_bluetooth_source(
+        &mut self,
+        event_sender: tokio_mpsc::UnboundedSender<SourceEvent>,
+    ) -> SourceTask {
+        self.log_info("Starting Bluetooth monitoring mode...".to_string());
+
+        let bpm_range = self.config.bpm_range();
+        let adapter = self.config.ble_adapter.clone();
+        let strict_scan = self.config.bluetooth_strict_scan;
+        let post_connect_delay = Duration::from_millis(self.config.ble_post_connect_delay_ms);
+        let discovery_attempts = self.config.ble_discovery_attempts;
+        let discovery_retry = Duration::from_millis(self.config.ble_discovery_retry_ms);
+        let device_name = self.config.heart_rate_device_name.clone();
+        let device_address = self.config.heart_rate_device_address.clone();
+        let log_sender = self.log_sender.clone();
+        let (cancel_sender, mut cancel_receiver) = oneshot::channel();
+        self.scan_cancel_sender = Some(cancel_sender);
+        let (stop_sender, stop_receiver) = oneshot::channel();
+        let (command_sender, command_receiver) = tokio_mpsc::unbounded_channel();
+
+        let handle = tokio::spawn(async move {
+            let connected = async {
+                // Initialize Bluetooth monitor and connect to device
+                let mut bluetooth_monitor =
+                    BluetoothHeartRateMonitor::new(bpm_range, adapter.as_deref()).await?;
+                bluetooth_monitor.set_strict_scan(strict_scan);
+                bluetooth_monitor.set_discovery_timing(
+                    post_connect_delay,
+                    discovery_attempts,
+                    discovery_retry,
+                );
+                let progress_sender = event_sender.clone();
+                bluetooth_monitor.on_scan_progress(move |progress| {
+                    let _ = progress_sender.send(SourceEvent::ScanProgress(Some(progress)));
+                });
+                let state_log_sender = log_sender.clone();
+                bluetooth_monitor.on_state_change(move |old, new| {
+                    let message = match old {
+                        ConnectionState::Scanning { since } => format!(
+                            "Bluetooth state: {} -> {} after {:.1}s scanning",
+                            old.name(),
+                            new.name(),
+                            since.elapsed().as_secs_f64()
+                        ),
+                        _ => format!("Bluetooth state: {} -> {}", old.name(), new.name()),
+                    };
+                    send_log(&state_log_sender, LogLevel::Debug, message);
+                });
+
+                let connected = tokio::select! {
+                    result = bluetooth_monitor
+                        .connect(device_name.as_deref(), device_address.as_deref()) => result,
+                    _ = &mut cancel_receiver => {
+                        let _ = bluetooth_monitor.stop_scan().await;
+                        Err(anyhow::anyhow!("Bluetooth scan cancelled"))
                     }
-                    result = &mut monitoring_task => {
-                        match result {
-                            Ok(()) => self.log_info("Bluetooth monitoring completed".to_string()),
-                            Err(e) => self.log_error(format!("Bluetooth monitoring task error: {}", e)),
-                        }
-                        break;
+                };
+                // Closing the receiver tells the monitor this scan can no longer be cancelled
+                drop(cancel_receiver);
+                let _ = event_sender.send(SourceEvent::ScanProgress(None));
+                connected?;
+                if let Some((name, address)) = bluetooth_monitor.connected_device().await {
+                    let _ = event_sender.send(SourceEvent::DeviceConnected { name, address });
+                }
+
+                bluetooth_monitor.subscribe().await?;
+                if let Some(location) = bluetooth_monitor.sensor_location() {
+                    let _ = event_sender.send(SourceEvent::SensorLocation(location));
+                }
+                let state = bluetooth_monitor.state();
+                if let ConnectionState::Connected { subscribed_char, .. } = state {
+                    send_log(
+                        &log_sender,
+                        LogLevel::Debug,
+                        format!("Subscribed to characteristic {}", subscribed_char.uuid),
+                    );
+                }
+                Ok::<_, anyhow::Error>(bluetooth_monitor)
+            }
+            .await;
+
+            match connected {
+                Ok(bluetooth_monitor) => {
+                    run_source(
+                        Box::new(bluetooth_monitor),
+                        "Bluetooth monitoring",
+                        event_sender,
+                        log_sender,
+                        async {
+                            let _ = stop_receiver.await;
+                        },
+                        command_receiver,
+                    )
+                    .await;
+                }
+                Err(e) => {
+                    if let Some(scan_error) = e.downcast_ref::<ScanError>() {
+                        let _ = event_sender.send(SourceEvent::ScanFailed(*scan_error));
                     }
+                    tracing::error!("Bluetooth monitoring error: {}", e);
+                    send_log(&log_sender, LogLevel::Error, format!("Bluetooth monitoring error: {}", e));
                 }
             }
+        });
+        SourceTask {
+            stop_sender,
+            command_sender,
+            handle,
         }
-
-        Ok(())
     }
 
-    /// Start Xiaomi Band monitoring mode
-    async fn start_xiaomi_band_mode(&mut self) -> Result<()> {
+    /// Start Xiaomi Band advertisement source
+    fn start_xiaomi_band_source(
+        &self,
+        event_sender: tokio_mpsc::UnboundedSender<SourceEvent>,
+    ) -> SourceTask {
         self.log_info("Starting Xiaomi Band monitoring mode...".to_string());
         self.log_info("Listening for Xiaomi Smart Band advertisements...".to_string());
 
-        let (heart_rate_sender, mut heart_rate_receiver) = tokio_mpsc::unbounded_channel();
+        let bpm_range = self.config.bpm_range();
+        let adapter = self.config.ble_adapter.clone();
+        let heart_rate_field = self.config.xiaomi_hr_field();
+        let scan_watchdog = Duration::from_secs(self.config.xiaomi_scan_watchdog_secs);
+        let log_sender = self.log_sender.clone();
+        let (stop_sender, stop_receiver) = oneshot::channel();
+        let (command_sender, command_receiver) = tokio_mpsc::unbounded_channel();
 
-        // Create Xiaomi Band monitor
-        let mut xiaomi_monitor = XiaomiBandMonitor::new(heart_rate_sender).await?;
+        // Create and run the Xiaomi Band monitor in a separate task
+        let handle = tokio::spawn(async move {
+            let started = async {
+                let mut xiaomi_monitor =
+                    XiaomiBandMonitor::new(bpm_range, adapter.as_deref(), heart_rate_field)
+                        .await?;
+                xiaomi_monitor.set_scan_watchdog(scan_watchdog);
+                let restart_log_sender = log_sender.clone();
+                xiaomi_monitor.on_scan_restart(move |silence, restarts| {
+                    send_log(
+                        &restart_log_sender,
+                        LogLevel::Warn,
+                        format!(
+                            "No Bluetooth advertisements for {}s, restarting the Xiaomi Band \
+                             scan (restart {})",
+                            silence.as_secs(),
+                            restarts
+                        ),
+                    );
+                });
+                xiaomi_monitor.start_monitoring().await?;
+                Ok::<_, anyhow::Error>(xiaomi_monitor)
+            }
+            .await;
 
-        // Start monitoring in a separate task
-        let mut monitoring_task = tokio::spawn(async move {
-            if let Err(e) = xiaomi_monitor.start_monitoring().await {
-                tracing::error!("Xiaomi Band monitoring error: {}", e);
+            match started {
+                Ok(xiaomi_monitor) => {
+                    send_log(
+                        &log_sender,
+                        LogLevel::Info,
+                        "Xiaomi Band monitor started. Waiting for advertisements...".to_string(),
+                    );
+                    run_source(
+                        Box::new(xiaomi_monitor),
+                        "Xiaomi Band monitoring",
+                        event_sender,
+                        log_sender,
+                        async {
+                            let _ = stop_receiver.await;
+                        },
+                        command_receiver,
+                    )
+                    .await;
+                }
+                Err(e) => {
+                    if let Some(scan_error) = e.downcast_ref::<ScanError>() {
+                        let _ = event_sender.send(SourceEvent::ScanFailed(*scan_error));
+                    }
+                    tracing::error!("Xiaomi Band monitoring error: {}", e);
+                    send_log(&log_sender, LogLevel::Error, format!("Xiaomi Band monitoring error: {}", e));
+                }
             }
         });
+        SourceTask {
+            stop_sender,
+            command_sender,
+            handle,
+        }
+    }
 
-        self.log_info("Xiaomi Band monitor started. Waiting for advertisements...".to_string());
+    /// Start the simulated source
+    fn start_simulated_source(
+        &self,
+        event_sender: tokio_mpsc::UnboundedSender<SourceEvent>,
+    ) -> SourceTask {
+        self.log_warn("Starting simulated heart rate source, readings are not real".to_string());
+        spawn_source(
+            Box::new(SimulatedSource::new()),
+            "Simulated source",
+            event_sender,
+            self.log_sender.clone(),
+        )
+    }
 
-        // Start timeout checker
-        let mut timeout_task = self.start_timeout_checker().await;
+    /// Record the connected Bluetooth device and report it to the GUI
+    async fn handle_device_connected(&mut self, name: String, address: String) {
+        self.log_info(format!("Connected to Bluetooth device {} ({})", name, address));
+        self.connected_device = Some((name, address));
+        self.scan_error = None;
+        self.send_connection_status();
 
-        // Process heart rate data
-        loop {
-            tokio::select! {
-                heart_rate = heart_rate_receiver.recv() => {
-                    if let Some(heart_rate) = heart_rate {
-                        self.process_heart_rate(heart_rate).await?;
-                    } else {
-                        // Channel closed, break the loop
-                        break;
-                    }
-                }
-                _ = &mut timeout_task => {
-                    self.log_error("Timeout checker completed".to_string());
-                    break;
+        // One-off event, sent right away instead of going through the rate limiter
+        if let Some(text) = self.config.osc_connect_message.clone() {
+            if self.broadcast_message(&text).await {
+                // Keep the message up for one send interval before the first label
+                self.last_send_time = Instant::now();
+            }
+        }
+    }
+
+    /// Track Bluetooth scan progress and forward it to the GUI
+    fn handle_scan_progress(&mut self, progress: Option<ScanProgress>) {
+        match progress {
+            Some(progress) => {
+                // Log every 5 seconds so the log isn't flooded by the scan loop
+                let bucket = progress.elapsed.as_secs() / 5;
+                let logged_bucket = self.scan_progress.map(|p| p.elapsed.as_secs() / 5);
+                if bucket > 0 && logged_bucket != Some(bucket) {
+                    self.log_info(format!(
+                        "Scanning... {}s elapsed, {} devices seen",
+                        progress.elapsed.as_secs(),
+                        progress.devices_seen
+                    ));
                 }
-                _ = &mut monitoring_task => {
-                    self.log_error("Xiaomi Band monitor stopped".to_string());
-                    break;
+                self.scan_progress = Some(progress);
+            }
+            None => {
+                self.scan_progress = None;
+                // May come from a source that was just replaced, keep the new scan cancellable
+                if self
+                    .scan_cancel_sender
+                    .as_ref()
+                    .is_some_and(|sender| sender.is_closed())
+                {
+                    self.scan_cancel_sender = None;
                 }
             }
         }
+        self.send_connection_status();
+    }
 
-        Ok(())
+    /// Apply the source priority policy before processing a reading
+    async fn handle_reading(&mut self, reading: HeartRateReading) -> Result<()> {
+        if reading.bpm == 0 {
+            self.handle_zero_reading(reading.source).await;
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        self.source_last_seen.insert(reading.source, now);
+
+        // Ignore lower priority sources while a preferred one is still delivering data
+        let fallback = Duration::from_secs(self.config.source_fallback_secs);
+        let preferred = self
+            .sources
+            .iter()
+            .take_while(|source| **source != reading.source)
+            .find(|source| {
+                self.source_last_seen
+                    .get(*source)
+                    .is_some_and(|last_seen| now.duration_since(*last_seen) < fallback)
+            });
+        if let Some(preferred) = preferred {
+            self.log_debug(format!(
+                "Ignoring {} reading, preferring {}",
+                reading.source.label(),
+                preferred.label()
+            ));
+            return Ok(());
+        }
+
+        if self.active_source != Some(reading.source) {
+            match self.active_source {
+                Some(previous) => self.log_warn(format!(
+                    "Switching heart rate source from {} to {}",
+                    previous.label(),
+                    reading.source.label()
+                )),
+                None => self.log_info(format!("Using heart rate from {}", reading.source.label())),
+            }
+            self.active_source = Some(reading.source);
+        }
+
+        self.process_heart_rate(reading).await
+    }
+
+    /// Send the zero reading message once when the active sensor reports 0 BPM
+    ///
+    /// Zero readings never reach stats or the database, and don't count as data for the
+    /// source priority, so a fallback source can take over.
+    async fn handle_zero_reading(&mut self, source: MonitorMode) {
+        if self.active_source.is_some_and(|active| active != source) {
+            self.log_debug(format!("Ignoring 0 BPM reading from {}", source.label()));
+            return;
+        }
+        let Some(text) = self.config.zero_reading_message.clone() else {
+            self.log_debug(format!("Ignoring 0 BPM reading from {}", source.label()));
+            return;
+        };
+
+        // The sensor is still connected, keep the offline message for real data loss
+        let now = Instant::now();
+        self.last_receive_time = Some(now);
+        if self.zero_reading_sent || self.paused {
+            return;
+        }
+        self.stop_heartbeat();
+        self.pending_heart_rate = None;
+
+        // Retried on the next zero reading
+        if self.is_rate_limited(now) {
+            self.log_debug("Zero reading message rate limited, retrying later".to_string());
+            return;
+        }
+
+        self.log_warn(format!("{} reports 0 BPM, sending zero reading message", source.label()));
+        if self.broadcast_message(&text).await {
+            self.last_send_time = now;
+            self.zero_reading_sent = true;
+            self.log_info(format!("Sent OSC zero reading message: {}", text));
+        }
     }
 
     /// Process incoming heart rate data
-    async fn process_heart_rate(&mut self, heart_rate: u32) -> Result<()> {
+    async fn process_heart_rate(&mut self, mut reading: HeartRateReading) -> Result<()> {
+        let heart_rate = reading.bpm;
+        let confidence = self.confidence.score(heart_rate, reading.sensor_contact, Instant::now());
+        reading.confidence = Some(confidence);
         self.last_receive_time = Some(Instant::now());
-        self.heart_rate_count += 1;
-        self.heart_rate_sum += heart_rate;
+        self.stale_sent = false;
+        if self.zero_reading_sent {
+            self.zero_reading_sent = false;
+            self.log_info("Heart rate signal restored".to_string());
+        }
+        if self.offline_sent {
+            self.offline_sent = false;
+            self.log_info("Heart rate data resumed".to_string());
+        }
+        self.heart_rate_stats.update(heart_rate as f64);
+        if let Some(energy_kj) = reading.energy_expended_kj {
+            self.energy.update(Instant::now(), energy_kj);
+        }
+        self.reading_intervals.record(Instant::now());
+        self.log_interval_stats();
+        if let Some(profile) = self.config.calorie_profile() {
+            let elapsed = self
+                .last_heart_rate_at
+                .and_then(|at| (chrono::Local::now() - at).to_std().ok());
+            self.calories_burned += profile.kcal_since(heart_rate, elapsed);
+            reading.session_kcal = Some(self.calories_burned);
+        }
+        self.previous_heart_rate = self.last_heart_rate;
+        self.last_heart_rate = Some(heart_rate);
+        self.last_heart_rate_at = Some(chrono::Local::now());
+        self.min_heart_rate = Some(
+            self.min_heart_rate
+                .map_or(heart_rate, |min| min.min(heart_rate)),
+        );
+        self.max_heart_rate = Some(
+            self.max_heart_rate
+                .map_or(heart_rate, |max| max.max(heart_rate)),
+        );
 
-        self.log_debug(format!("Received heart rate: {} BPM", heart_rate));
+        // Publish stats for the HTTP server
+        if let Ok(mut stats) = self.shared_stats.write() {
+            *stats = self.get_stats();
+        }
+
+        match &reading.device {
+            Some(device) => self.log_debug(format!(
+                "Received heart rate: {} BPM from {} ({}), confidence {}%",
+                heart_rate,
+                reading.source.label(),
+                device,
+                confidence
+            )),
+            None => self.log_debug(format!(
+                "Received heart rate: {} BPM from {}, confidence {}%",
+                heart_rate,
+                reading.source.label(),
+                confidence
+            )),
+        }
+        let measured_at = reading.measured_at;
 
         // Send to GUI
-        let _ = self.gui_heart_rate_sender.send(heart_rate);
+        let _ = self.gui_heart_rate_sender.send(reading);
+
+        self.set_paused_param(self.paused).await;
+        if self.paused {
+            self.log_debug("Monitoring paused, skipping database and OSC".to_string());
+            self.pending_heart_rate = None;
+            self.stop_heartbeat();
+            return Ok(());
+        }
+
+        if let Some(multicast) = &self.multicast {
+            if let Err(e) = multicast.send(heart_rate) {
+                self.log_debug(format!("Failed to send multicast reading: {:#}", e));
+            }
+        }
+        if let Some(server) = &self.share_server {
+            server.send(&SharedReading {
+                bpm: heart_rate,
+                measured_at: measured_at.unwrap_or_else(Utc::now),
+            });
+        }
+        if let Some(server) = &self.obs_server {
+            server.send(heart_rate);
+        }
 
-        // Save to database
+        // Save to database, retrying the first write in case the file is locked briefly
         if let Some(db) = &self.database {
-            if let Err(e) = db.insert_heart_rate(heart_rate as i32).await {
-                self.log_error(format!("Failed to save heart rate to database: {}", e));
+            let session_id = self.session_id;
+            let result = if self.database_written {
+                db.insert_heart_rate(heart_rate as i32, measured_at, session_id).await
+            } else {
+                with_retries("Saving the first heart rate", || {
+                    db.insert_heart_rate(heart_rate as i32, measured_at, session_id)
+                })
+                .await
+            };
+            match result {
+                Ok(_) => self.database_written = true,
+                Err(e) if !self.database_written && !self.persist_failed && self.config.persist => {
+                    self.degrade_to_memory_database(e).await;
+                }
+                Err(e) => self.log_error(format!("Failed to save heart rate to database: {}", e)),
             }
         }
 
+        // Noisy readings while the strap settles are kept off OSC
+        if self.is_warming_up(Instant::now()) {
+            self.log_debug("Warmup active, skipping OSC".to_string());
+            return Ok(());
+        }
+        if confidence < self.config.osc_min_confidence {
+            self.log_debug(format!(
+                "Confidence {}% is below OSC_MIN_CONFIDENCE, skipping OSC",
+                confidence
+            ));
+            return Ok(());
+        }
+
         // Send OSC message (with rate limiting)
         self.send_osc_message(heart_rate).await?;
 
+        self.update_heartbeat(heart_rate);
+
+        // Cheap enough to send with every reading, unlike the chatbox
+        let normalized = self.config.normalized_heart_rate(heart_rate);
+        self.send_avatar_param(
+            self.config.osc_normalized_param.clone(),
+            OscType::Float(normalized),
+        )
+        .await;
+        if self.config.osc_prefab_compat {
+            self.send_avatar_param(
+                Some(self.config.osc_prefab_bpm_param.clone()),
+                OscType::Int(heart_rate as i32),
+            )
+            .await;
+            self.send_avatar_param(
+                Some(self.config.osc_prefab_float_param.clone()),
+                OscType::Float(normalized),
+            )
+            .await;
+        }
+
+        self.set_connected_param(true).await;
+        self.set_zone_param(self.config.zone_config().zone(heart_rate)).await;
+        self.report_zone_crossing(heart_rate).await;
+        self.check_max_hr_alarm(heart_rate).await;
+
         Ok(())
     }
 
+    /// Warn once when the heart rate crosses MAX_HR_ALARM, re-armed once it has come back down
+    async fn check_max_hr_alarm(&mut self, heart_rate: u32) {
+        let Some(threshold) = self.config.max_hr_alarm else {
+            return;
+        };
+        let Some(active) = self.alarm_tracker.update(heart_rate, threshold) else {
+            return;
+        };
+        self.send_avatar_param(self.config.osc_alarm_param.clone(), OscType::Bool(active))
+            .await;
+        if !active {
+            self.log_info(format!("Heart rate back below the {} BPM alarm", threshold));
+            return;
+        }
+
+        self.log_warn(format!(
+            "Heart rate {} BPM is above the {} BPM alarm",
+            heart_rate, threshold
+        ));
+        if self.config.max_hr_alarm_sound {
+            SystemUtils::play_alert_sound();
+        }
+        if let Some(template) = self.config.max_hr_alarm_message.clone() {
+            let text = render_template(&template, &self.template_context(heart_rate));
+            if self.broadcast_message(&text).await {
+                // Keep the warning up for one send interval before the next label
+                self.last_send_time = Instant::now();
+            }
+        }
+    }
+
+    /// Log and announce zone crossings once the new zone has held for the debounce period
+    async fn report_zone_crossing(&mut self, heart_rate: u32) {
+        let zone = self.config.zone_config().zone(heart_rate);
+        let Some((from, to)) = self.zone_tracker.update(zone, Instant::now()) else {
+            return;
+        };
+        self.log_info(format!("Entered {} zone from {}", to.label(), from.label()));
+        self.send_avatar_param(
+            self.config.osc_zone_event_param.clone(),
+            OscType::Int(to.index() as i32),
+        )
+        .await;
+
+        if let Some(template) = self.config.zone_change_message.clone() {
+            let text = render_template(&template, &self.template_context(heart_rate));
+            if self.broadcast_message(&text).await {
+                // Keep the announcement up for one send interval before the next label
+                self.last_send_time = Instant::now();
+                self.log_info(format!("Sent zone change message: {}", text));
+            }
+        }
+    }
+
+    /// Check whether the warmup period after the first reading is still running
+    fn is_warming_up(&mut self, now: Instant) -> bool {
+        match self.warmup_ends_at {
+            None => {
+                let warmup = Duration::from_secs(self.config.warmup_secs);
+                self.warmup_ends_at = Some(now + warmup);
+                if warmup.is_zero() {
+                    self.warmup_finished = true;
+                    return false;
+                }
+                self.log_info(format!(
+                    "Warmup active for {} seconds, readings are not sent over OSC",
+                    self.config.warmup_secs
+                ));
+                true
+            }
+            Some(ends_at) if now < ends_at => true,
+            Some(_) => {
+                if !self.warmup_finished {
+                    self.warmup_finished = true;
+                    self.log_info("Warmup finished, sending readings over OSC".to_string());
+                }
+                false
+            }
+        }
+    }
+
     /// Send OSC message with rate limiting
     async fn send_osc_message(&mut self, heart_rate: u32) -> Result<()> {
         let now = Instant::now();
-        let gap = now.duration_since(self.last_send_time);
 
-        if gap < Duration::from_millis(1500) {
-            self.log_debug("OSC send rate limited, skipping".to_string());
+        // Keep only the newest value so the chatbox is current once the interval elapses
+        if self.is_rate_limited(now) {
+            self.log_debug("OSC send rate limited, sending latest value later".to_string());
+            self.pending_heart_rate = Some(heart_rate);
             return Ok(());
         }
+        self.pending_heart_rate = None;
 
-        if let Some(text) = self.config.get_heart_rate_text(heart_rate) {
-            if let Some(osc_client) = &self.osc_client {
-                match osc_client.send_message(&text).await {
-                    Ok(_) => {
-                        self.last_send_time = now;
-                        self.log_info(format!("Sent OSC message: {}", text));
-                    }
-                    Err(e) => {
-                        self.log_error(format!("Failed to send OSC message: {}", e));
-                    }
-                }
+        if let Some(text) = self.config.get_heart_rate_text(&self.template_context(heart_rate)) {
+            // Resending the same text restarts the chatbox timeout and makes it flicker
+            let refresh = Duration::from_secs(self.config.duplicate_refresh_secs);
+            if self.config.skip_duplicate_messages
+                && self
+                    .last_sent_label
+                    .as_ref()
+                    .is_some_and(|last| last.repeats(heart_rate, &text, now, refresh))
+            {
+                self.log_debug(format!("Skipping unchanged OSC message: {}", text));
+                return Ok(());
+            }
+            if self.broadcast_message(&text).await {
+                self.last_send_time = now;
+                self.log_info(format!("Sent OSC message: {}", text));
+                self.last_sent_label = Some(SentLabel {
+                    bpm: heart_rate,
+                    text,
+                    sent_at: now,
+                });
             }
         } else {
             self.log_error(format!("Invalid heart rate value: {}", heart_rate));
@@ -332,48 +1415,434 @@ impl HeartRateMonitor {
         Ok(())
     }
 
-    /// Start timeout checker task
-    async fn start_timeout_checker(&self) -> tokio::task::JoinHandle<()> {
-        let log_sender = self.log_sender.clone();
+    /// Start or retune the heartbeat parameter task for the current BPM
+    fn update_heartbeat(&mut self, heart_rate: u32) {
+        let params = self.config.heartbeat_params();
+        if params.is_empty() {
+            return;
+        }
+        if self
+            .heartbeat
+            .as_ref()
+            .is_some_and(|task| task.bpm.abs_diff(heart_rate) <= HEARTBEAT_RESTART_DELTA)
+        {
+            return;
+        }
+
+        self.stop_heartbeat();
 
-        tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(5));
+        // The task uses its own clients so the beat is not held up by chatbox sends
+        let clients: Result<Vec<OscClient>> = self
+            .config
+            .osc_endpoints()
+            .into_iter()
+            .map(|(host, port)| {
+                OscClient::new(host, port, false, self.config.osc_bind_addr.as_deref())
+            })
+            .collect();
+        let clients = match clients {
+            Ok(clients) => clients,
+            Err(e) => {
+                self.log_error(format!("Failed to start OSC heartbeat: {}", e));
+                return;
+            }
+        };
 
+        // Toggle twice per beat so the parameter completes one cycle per beat
+        let period = Duration::from_millis(30_000 / heart_rate.max(1) as u64);
+        let handle = tokio::spawn(async move {
+            let targets: Vec<(&OscClient, &str)> = clients
+                .iter()
+                .flat_map(|client| params.iter().map(move |param| (client, param.as_str())))
+                .collect();
+            let mut ticker = interval(period);
+            let mut beat = false;
             loop {
-                interval.tick().await;
+                ticker.tick().await;
+                beat = !beat;
+                let sends = targets
+                    .iter()
+                    .map(|(client, param)| client.send_parameter(param, OscType::Bool(beat)));
+                for ((client, _), result) in targets.iter().zip(join_all(sends).await) {
+                    if let Err(e) = result {
+                        tracing::warn!(
+                            "Failed to send OSC heartbeat to {}: {}",
+                            client.target(),
+                            e
+                        );
+                    }
+                }
+            }
+        });
 
-                let _ = log_sender.send(LogEntry {
-                    timestamp: chrono::Local::now(),
-                    level: LogLevel::Debug,
-                    message: "Checking for timeout...".to_string(),
-                });
+        self.log_debug(format!("OSC heartbeat running at {} BPM", heart_rate));
+        self.heartbeat = Some(HeartbeatTask {
+            bpm: heart_rate,
+            handle,
+        });
+    }
+
+    /// Send the sensor connected parameter when it changes
+    async fn set_connected_param(&mut self, connected: bool) {
+        if self.state_params.connected == Some(connected) {
+            return;
+        }
+        self.state_params.connected = Some(connected);
+        self.send_avatar_param(self.config.osc_connected_param.clone(), OscType::Bool(connected))
+            .await;
+    }
+
+    /// Send the monitoring paused parameter when it changes
+    async fn set_paused_param(&mut self, paused: bool) {
+        if self.state_params.paused == Some(paused) {
+            return;
+        }
+        self.state_params.paused = Some(paused);
+        self.send_connection_status();
+        self.send_avatar_param(self.config.osc_paused_param.clone(), OscType::Bool(paused))
+            .await;
+    }
+
+    /// Send the zone index parameter when the zone changes
+    async fn set_zone_param(&mut self, zone: HeartRateZone) {
+        if self.state_params.zone == Some(zone) {
+            return;
+        }
+        self.state_params.zone = Some(zone);
+        self.log_debug(format!("Heart rate zone changed to {}", zone.label()));
+        self.send_avatar_param(
+            self.config.osc_zone_param.clone(),
+            OscType::Int(zone.index() as i32),
+        )
+        .await;
+    }
+
+    /// Send an avatar parameter if it is configured
+    async fn send_avatar_param(&self, param: Option<String>, value: OscType) {
+        let Some(param) = param else {
+            return;
+        };
+
+        let sends = self
+            .osc_clients
+            .iter()
+            .map(|client| client.send_parameter(&param, value.clone()));
+        let results = join_all(sends).await;
+
+        for (client, result) in self.osc_clients.iter().zip(results) {
+            match result {
+                Ok(_) => self.log_debug(format!(
+                    "Sent OSC parameter {} = {:?} to {}",
+                    param,
+                    value,
+                    client.target()
+                )),
+                Err(e) => self.log_warn(format!(
+                    "Failed to send OSC parameter {} to {}: {}",
+                    param,
+                    client.target(),
+                    e
+                )),
             }
-        })
+        }
+    }
+
+    /// Stop the heartbeat parameter task
+    fn stop_heartbeat(&mut self) {
+        if let Some(task) = self.heartbeat.take() {
+            task.handle.abort();
+        }
+    }
+
+    /// Build the values available to heart rate label templates
+    fn template_context(&self, heart_rate: u32) -> TemplateContext {
+        TemplateContext {
+            bpm: heart_rate,
+            avg: Some(self.heart_rate_stats.mean()),
+            zone: Some(self.config.zone_config().zone(heart_rate)),
+            min: self.min_heart_rate,
+            max: self.max_heart_rate,
+            duration: Some(self.start_time.elapsed()),
+            delta: self
+                .previous_heart_rate
+                .map(|previous| heart_rate as i64 - previous as i64),
+        }
+    }
+
+    /// Check whether the OSC send rate limit is still in effect
+    fn is_rate_limited(&self, now: Instant) -> bool {
+        now < self.next_send_time()
+    }
+
+    /// Earliest time the next OSC message may be sent
+    fn next_send_time(&self) -> Instant {
+        self.last_send_time + Duration::from_millis(self.config.osc_send_interval_ms)
+    }
+
+    /// Handle a command from the GUI
+    async fn handle_command(&mut self, command: MonitorCommand) {
+        match command {
+            MonitorCommand::UpdateConfig(config) => self.apply_config(*config).await,
+            MonitorCommand::ResetSession => self.reset_session().await,
+            MonitorCommand::SetPaused(paused) => self.set_paused(paused).await,
+            MonitorCommand::CancelScan => {
+                if let Some(sender) = self.scan_cancel_sender.take() {
+                    self.log_warn("Cancelling Bluetooth scan".to_string());
+                    let _ = sender.send(());
+                }
+            }
+            MonitorCommand::ResetEnergyExpended => {
+                match self.source_tasks.get(&MonitorMode::Bluetooth) {
+                    Some(task) => {
+                        let _ = task.command_sender.send(SourceCommand::ResetEnergyExpended);
+                    }
+                    None => self.log_warn(
+                        "Energy expended can only be reset on a Bluetooth strap".to_string(),
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Clear the session statistics, leaving sources and OSC connected
+    async fn reset_session(&mut self) {
+        self.start_time = Instant::now();
+        self.session_started_at = chrono::Local::now();
+        self.heart_rate_stats = WelfordStats::default();
+        self.energy = EnergyAccumulator::default();
+        self.calories_burned = 0.0;
+        self.reading_intervals = IntervalStats::default();
+        self.confidence = ConfidenceScorer::default();
+        self.last_heart_rate = None;
+        self.previous_heart_rate = None;
+        self.last_heart_rate_at = None;
+        self.min_heart_rate = None;
+        self.max_heart_rate = None;
+        if let Ok(mut stats) = self.shared_stats.write() {
+            *stats = self.get_stats();
+        }
+
+        if let Some(db) = &self.database {
+            match db.start_session().await {
+                Ok(id) => self.session_id = Some(id),
+                Err(e) => self.log_warn(format!("Failed to start database session: {}", e)),
+            }
+        }
+
+        self.log_info("Session reset".to_string());
+    }
+
+    /// Apply an edited configuration to the running monitor
+    async fn apply_config(&mut self, config: Config) {
+        let osc_changed = config.osc_endpoints() != self.config.osc_endpoints()
+            || config.osc_typing_indicator != self.config.osc_typing_indicator
+            || config.osc_bind_addr != self.config.osc_bind_addr;
+        let multicast_changed = config.multicast_enabled != self.config.multicast_enabled
+            || config.multicast_group != self.config.multicast_group
+            || config.multicast_port != self.config.multicast_port;
+        let share_changed = config.share_mode != self.config.share_mode;
+        let obs_changed = config.obs_server_enabled != self.config.obs_server_enabled
+            || config.obs_server_port != self.config.obs_server_port;
+        let source_changes = source_restarts(&self.config, &config);
+        let sources_changed = config.sources() != self.sources;
+        let restart_required = config.persist != self.config.persist;
+
+        self.config = config;
+        self.zone_tracker.set_debounce(Duration::from_secs(self.config.zone_change_debounce_secs));
+        self.log_info("Configuration updated".to_string());
+
+        // Restarted with the new settings on the next reading
+        self.stop_heartbeat();
+
+        if multicast_changed {
+            self.init_multicast();
+        }
+        if share_changed {
+            self.init_sharing().await;
+        }
+        if obs_changed {
+            self.init_obs_server().await;
+        }
+
+        if osc_changed {
+            // Keep the previous client if the new target cannot be used
+            if let Err(e) = self.init_osc_client().await {
+                self.log_warn(format!("Keeping previous OSC client: {}", e));
+            }
+        }
+
+        // Resend avatar state in case the parameter names or the target changed
+        let state_params = std::mem::take(&mut self.state_params);
+        if let Some(paused) = state_params.paused {
+            self.set_paused_param(paused).await;
+        }
+        if let Some(connected) = state_params.connected {
+            self.set_connected_param(connected).await;
+        }
+        if let Some(zone) = state_params.zone {
+            self.set_zone_param(zone).await;
+        }
+
+        for mode in source_changes {
+            if self.source_tasks.contains_key(&mode) {
+                self.restart_source(mode).await;
+            }
+        }
+        if sources_changed {
+            self.switch_sources(self.config.sources()).await;
+        }
+
+        if restart_required {
+            self.log_warn("Persistence changes take effect after restarting HeartIO".to_string());
+        }
+    }
+
+    /// Start timeout checker interval, polled from the mode loops
+    fn start_timeout_checker(&self) -> Interval {
+        interval(Duration::from_secs(5))
+    }
+
+    /// Send the offline message once when no data has arrived for the configured timeout
+    async fn check_timeout(&mut self) {
+        self.log_debug("Checking for timeout...".to_string());
+
+        let Some(last_receive_time) = self.last_receive_time else {
+            return;
+        };
+        if self.offline_sent {
+            return;
+        }
+
+        let timeout = Duration::from_secs(self.config.offline_timeout_secs);
+        let now = Instant::now();
+        if now.duration_since(last_receive_time) < timeout {
+            self.send_stale_marker(now.duration_since(last_receive_time)).await;
+            return;
+        }
+
+        // No beat without data
+        self.stop_heartbeat();
+        self.pending_heart_rate = None;
+        self.set_connected_param(false).await;
+
+        let Some(text) = self.config.offline_message.clone() else {
+            return;
+        };
+
+        // Retry on the next tick rather than breaking the rate limit
+        if self.is_rate_limited(now) {
+            self.log_debug("Offline message rate limited, retrying later".to_string());
+            return;
+        }
+
+        self.log_warn(format!(
+            "No heart rate data for {} seconds, sending offline message",
+            self.config.offline_timeout_secs
+        ));
+
+        if self.broadcast_message(&text).await {
+            self.last_send_time = now;
+            self.offline_sent = true;
+            self.log_info(format!("Sent OSC offline message: {}", text));
+        }
+    }
+
+    /// Resend the last label with the stale marker once readings stop for `STALE_AFTER_SECS`
+    async fn send_stale_marker(&mut self, silence: Duration) {
+        if self.stale_sent || silence < Duration::from_secs(self.config.stale_after_secs) {
+            return;
+        }
+        let (Some(marker), Some(heart_rate)) =
+            (self.config.stale_marker.clone(), self.last_heart_rate)
+        else {
+            return;
+        };
+        let now = Instant::now();
+        if self.is_rate_limited(now) {
+            return;
+        }
+
+        let Some(text) = self.config.get_heart_rate_text(&self.template_context(heart_rate)) else {
+            return;
+        };
+        let text = format!("{}{}", text, marker);
+        if self.broadcast_message(&text).await {
+            self.last_send_time = now;
+            self.stale_sent = true;
+            self.log_info(format!("Sent stale heart rate message: {}", text));
+        }
+    }
+
+    /// Report the current connection status to the GUI
+    fn send_connection_status(&self) {
+        let _ = self.connection_status_sender.send(self.get_connection_status());
     }
 
     /// Get current connection status
     pub fn get_connection_status(&self) -> ConnectionStatus {
         ConnectionStatus {
-            bluetooth_connected: self.bluetooth_monitor.is_some(),
-            osc_connected: self.osc_client.is_some(),
+            bluetooth_connected: self.connected_device.is_some()
+                || self.source_last_seen.contains_key(&MonitorMode::XiaomiBand),
+            osc_connected: !self.osc_clients.is_empty(),
             database_connected: self.database.is_some(),
-            apple_watch_server_running: self.config.apple_watch
-                || self.config.xiaomi_band.is_some_and(|enabled| enabled),
+            apple_watch_server_running: self.apple_watch_listening,
+            device_name: self.connected_device.as_ref().map(|(name, _)| name.clone()),
+            device_address: self
+                .connected_device
+                .as_ref()
+                .map(|(_, address)| address.clone()),
+            device_auto_detected: self.config.heart_rate_device_name.is_none()
+                && self.config.heart_rate_device_address.is_none(),
+            scan_progress: self.scan_progress,
+            scan_error: self.scan_error,
+            sensor_location: self.sensor_location,
+            osc_unreachable: self.osc_unreachable,
+            paused: self.paused,
+            persist_failed: self.persist_failed,
+        }
+    }
+
+    /// Query the last 24 hours and the daily summaries once per `HISTORY_STATS_PERIOD`
+    async fn refresh_history_stats(&mut self) {
+        let now = Instant::now();
+        if self
+            .history_stats_at
+            .is_some_and(|at| now.duration_since(at) < HISTORY_STATS_PERIOD)
+        {
+            return;
+        }
+        self.history_stats_at = Some(now);
+        let Some(db) = &self.database else {
+            return;
+        };
+        match db.get_stats().await {
+            Ok(stats) => {
+                let _ = self.history_sender.send(HistoryUpdate::Stats(stats));
+            }
+            Err(e) => self.log_debug(format!("Failed to fetch database statistics: {:#}", e)),
+        }
+        match db.get_daily_summary(DAILY_SUMMARY_DAYS).await {
+            Ok(summary) => {
+                let _ = self.history_sender.send(HistoryUpdate::DailySummary(summary));
+            }
+            Err(e) => self.log_debug(format!("Failed to fetch the daily summary: {:#}", e)),
         }
     }
 
     /// Get current statistics
     pub fn get_stats(&self) -> AppStats {
         AppStats {
-            total_heart_rates: self.heart_rate_count,
+            total_heart_rates: self.heart_rate_stats.count() as u32,
             session_duration: self.start_time.elapsed(),
-            session_start_time: Some(self.start_time),
-            last_heart_rate_time: self.last_receive_time.map(|_| chrono::Local::now()),
-            avg_heart_rate: if self.heart_rate_count > 0 {
-                self.heart_rate_sum as f32 / self.heart_rate_count as f32
-            } else {
-                0.0
-            },
+            session_start_time: Some(self.session_started_at),
+            last_heart_rate_time: self.last_heart_rate_at,
+            heart_rate_stats: self.heart_rate_stats,
+            energy: self.energy.clone(),
+            calories_burned: self.calories_burned,
+            intervals: self.reading_intervals.clone(),
+            current_heart_rate: self.last_heart_rate,
+            min_heart_rate: self.min_heart_rate,
+            max_heart_rate: self.max_heart_rate,
         }
     }
 
@@ -381,34 +1850,61 @@ impl HeartRateMonitor {
     pub async fn shutdown(&mut self) -> Result<()> {
         self.log_info("Shutting down HeartIO...".to_string());
 
+        self.stop_heartbeat();
+
         // Allow system to sleep
         if let Err(e) = self.system_utils.allow_system_sleep() {
             self.log_warn(format!("Failed to restore system sleep settings: {}", e));
         }
 
-        // Disconnect Bluetooth
-        if let Some(mut bluetooth_monitor) = self.bluetooth_monitor.take() {
-            if let Err(e) = bluetooth_monitor.disconnect().await {
-                self.log_warn(format!("Failed to disconnect Bluetooth device: {}", e));
+        if self.connected_device.is_some() {
+            if let Some(text) = self.config.osc_disconnect_message.clone() {
+                self.broadcast_message(&text).await;
             }
         }
 
-        // Stop Xiaomi Band monitor
-        if let Some(mut xiaomi_monitor) = self.xiaomi_band_monitor.take() {
-            if let Err(e) = xiaomi_monitor.stop().await {
-                self.log_warn(format!("Failed to stop Xiaomi Band monitor: {}", e));
-            }
+        // Stop the sources, which disconnects Bluetooth, stops the Xiaomi Band scan and lets
+        // the Apple Watch server drain its open connections
+        let modes: Vec<MonitorMode> = self.source_tasks.keys().copied().collect();
+        for mode in modes {
+            self.stop_source(mode).await;
         }
 
         // Close database
         if let Some(database) = self.database.take() {
-            database.close().await;
+            if timeout(SHUTDOWN_STEP_TIMEOUT, database.close()).await.is_err() {
+                self.log_shutdown_timeout("Database close");
+            }
         }
 
         self.log_info("HeartIO shutdown complete".to_string());
         Ok(())
     }
 
+    /// Log the reading interval statistics once per `INTERVAL_LOG_PERIOD`
+    fn log_interval_stats(&mut self) {
+        let now = Instant::now();
+        if now.duration_since(self.last_interval_log) < INTERVAL_LOG_PERIOD {
+            return;
+        }
+        if let Some(summary) = self.reading_intervals.summary() {
+            self.last_interval_log = now;
+            self.log_info(format!(
+                "Reading intervals over the last minute (min / median / max): {}",
+                summary
+            ));
+        }
+    }
+
+    /// Report a shutdown step that was abandoned after `SHUTDOWN_STEP_TIMEOUT`
+    fn log_shutdown_timeout(&self, step: &str) {
+        self.log_warn(format!(
+            "{} did not finish within {}s, skipping",
+            step,
+            SHUTDOWN_STEP_TIMEOUT.as_secs()
+        ));
+    }
+
     // Logging helper methods
     fn log_info(&self, message: String) {
         let _ = self.log_sender.send(LogEntry {
@@ -442,3 +1938,241 @@ impl HeartRateMonitor {
         });
     }
 }
+
+/// Send a log entry to the GUI from a background task
+fn send_log(log_sender: &mpsc::Sender<LogEntry>, level: LogLevel, message: String) {
+    let _ = log_sender.send(LogEntry {
+        timestamp: chrono::Local::now(),
+        level,
+        message,
+    });
+}
+
+/// Receive from a channel that may not exist, pending forever without one
+async fn recv_optional<T>(receiver: &mut Option<tokio_mpsc::UnboundedReceiver<T>>) -> Option<T> {
+    let Some(channel) = receiver else {
+        return std::future::pending().await;
+    };
+    let received = channel.recv().await;
+    if received.is_none() {
+        // Closed, stop polling it
+        *receiver = None;
+    }
+    received
+}
+
+/// Run a source in the background
+fn spawn_source(
+    source: Box<dyn HeartRateSource>,
+    name: &'static str,
+    event_sender: tokio_mpsc::UnboundedSender<SourceEvent>,
+    log_sender: mpsc::Sender<LogEntry>,
+) -> SourceTask {
+    let (stop_sender, stop_receiver) = oneshot::channel();
+    let (command_sender, command_receiver) = tokio_mpsc::unbounded_channel();
+    let stop = async {
+        let _ = stop_receiver.await;
+    };
+    let handle = tokio::spawn(run_source(
+        source,
+        name,
+        event_sender,
+        log_sender,
+        stop,
+        command_receiver,
+    ));
+    SourceTask {
+        stop_sender,
+        command_sender,
+        handle,
+    }
+}
+
+/// Sources whose settings differ between two configs and need a restart to pick them up
+fn source_restarts(old: &Config, new: &Config) -> Vec<MonitorMode> {
+    let range_changed = old.bpm_range() != new.bpm_range();
+    let adapter_changed = old.ble_adapter != new.ble_adapter;
+    let mut modes = Vec::new();
+    if range_changed
+        || adapter_changed
+        || old.bluetooth_strict_scan != new.bluetooth_strict_scan
+        || old.ble_post_connect_delay_ms != new.ble_post_connect_delay_ms
+        || old.ble_discovery_attempts != new.ble_discovery_attempts
+        || old.ble_discovery_retry_ms != new.ble_discovery_retry_ms
+        || old.heart_rate_device_name != new.heart_rate_device_name
+        || old.heart_rate_device_address != new.heart_rate_device_address
+    {
+        modes.push(MonitorMode::Bluetooth);
+    }
+    if range_changed
+        || old.apple_watch_bind_address != new.apple_watch_bind_address
+        || old.apple_watch_max_rps != new.apple_watch_max_rps
+    {
+        modes.push(MonitorMode::AppleWatch);
+    }
+    if range_changed
+        || adapter_changed
+        || old.xiaomi_hr_field() != new.xiaomi_hr_field()
+        || old.xiaomi_scan_watchdog_secs != new.xiaomi_scan_watchdog_secs
+    {
+        modes.push(MonitorMode::XiaomiBand);
+    }
+    modes
+}
+
+/// Forward samples from a source to the monitor until either side stops or `stop` completes,
+/// then shut it down. `commands` are carried out in between samples.
+async fn run_source(
+    mut source: Box<dyn HeartRateSource>,
+    name: &'static str,
+    event_sender: tokio_mpsc::UnboundedSender<SourceEvent>,
+    log_sender: mpsc::Sender<LogEntry>,
+    stop: impl std::future::Future<Output = ()>,
+    mut commands: tokio_mpsc::UnboundedReceiver<SourceCommand>,
+) {
+    tokio::pin!(stop);
+    let stopped = loop {
+        tokio::select! {
+            sample = source.next_sample() => match sample {
+                Ok(sample) => {
+                    if event_sender.send(SourceEvent::Reading(sample)).is_err() {
+                        break false;
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("{} error: {}", name, e);
+                    send_log(&log_sender, LogLevel::Error, format!("{} error: {}", name, e));
+                    break false;
+                }
+            },
+            Some(command) = commands.recv() => match command {
+                SourceCommand::ResetEnergyExpended => {
+                    // Not every device supports it, so a failure is only worth a warning
+                    match source.reset_energy_expended().await {
+                        Ok(()) => send_log(
+                            &log_sender,
+                            LogLevel::Info,
+                            format!("{}: energy expended reset", name),
+                        ),
+                        Err(e) => send_log(
+                            &log_sender,
+                            LogLevel::Warn,
+                            format!("{}: failed to reset energy expended: {:#}", name, e),
+                        ),
+                    }
+                }
+            },
+            _ = &mut stop => break true,
+        }
+    };
+
+    if let Err(e) = source.shutdown().await {
+        tracing::warn!("Failed to shut down {}: {}", name, e);
+    }
+    let level = if stopped { LogLevel::Info } else { LogLevel::Error };
+    send_log(&log_sender, level, format!("{} stopped", name));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::collections::VecDeque;
+
+    use crate::source::HeartRateSample;
+
+    /// Source replaying fixed readings, then stopping
+    struct MockSource {
+        readings: VecDeque<u32>,
+        shut_down: Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    #[async_trait]
+    impl HeartRateSource for MockSource {
+        async fn next_sample(&mut self) -> Result<HeartRateSample> {
+            let bpm = self
+                .readings
+                .pop_front()
+                .ok_or_else(|| anyhow::anyhow!("No more readings"))?;
+            Ok(HeartRateSample::new(MonitorMode::Bluetooth, bpm))
+        }
+
+        async fn shutdown(&mut self) -> Result<()> {
+            self.shut_down.store(true, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn readings_from_injected_source_reach_the_gui() {
+        let config = Config {
+            persist: false,
+            ..Config::default()
+        };
+        let (log_sender, _log_receiver) = mpsc::channel();
+        let (gui_sender, gui_receiver) = mpsc::channel();
+        let (status_sender, _status_receiver) = mpsc::channel();
+        let (history_sender, _history_receiver) = mpsc::channel();
+        let (_command_sender, command_receiver) = tokio_mpsc::unbounded_channel();
+        let mut monitor = HeartRateMonitor::new(
+            config,
+            log_sender,
+            gui_sender,
+            status_sender,
+            history_sender,
+            command_receiver,
+        );
+
+        let shut_down = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let source = MockSource {
+            readings: VecDeque::from([72, 80, 95]),
+            shut_down: shut_down.clone(),
+        };
+
+        // Returns once the mock source runs out of readings
+        monitor.start(Some(Box::new(source))).await.unwrap();
+
+        let bpms: Vec<u32> = gui_receiver.try_iter().map(|reading| reading.bpm).collect();
+        assert_eq!(bpms, vec![72, 80, 95]);
+        assert!(shut_down.load(std::sync::atomic::Ordering::SeqCst));
+
+        let stats = monitor.get_stats();
+        assert_eq!(stats.total_heart_rates, 3);
+        assert_eq!(stats.min_heart_rate, Some(72));
+        assert_eq!(stats.max_heart_rate, Some(95));
+    }
+
+    #[test]
+    fn only_affected_sources_are_restarted() {
+        let old = Config::default();
+        let new = Config {
+            heart_rate_device_name: Some("Polar H10".to_string()),
+            ..old.clone()
+        };
+        assert_eq!(source_restarts(&old, &new), vec![MonitorMode::Bluetooth]);
+
+        let new = Config {
+            max_bpm: old.max_bpm + 10,
+            ..old.clone()
+        };
+        assert_eq!(source_restarts(&old, &new).len(), 3);
+        assert!(source_restarts(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn unchanged_label_repeats_until_refresh() {
+        let sent_at = Instant::now();
+        let last = SentLabel {
+            bpm: 72,
+            text: "❤️ 72".to_string(),
+            sent_at,
+        };
+        let refresh = Duration::from_secs(30);
+        let soon = sent_at + Duration::from_secs(5);
+
+        assert!(last.repeats(72, "❤️ 72", soon, refresh));
+        assert!(!last.repeats(73, "❤️ 72", soon, refresh));
+        assert!(!last.repeats(72, "❤️ 72 (zone 2)", soon, refresh));
+        assert!(!last.repeats(72, "❤️ 72", sent_at + refresh, refresh));
+    }
+}
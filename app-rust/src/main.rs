@@ -6,18 +6,32 @@ mod config;
 mod database;
 mod gui;
 mod heart_rate;
+mod multicast;
+mod obs_server;
 mod osc;
+mod selftest;
 mod server;
+mod share;
 mod signals;
+mod source;
+mod stats;
 mod system;
+mod tray;
+mod update;
 mod xiaomi_band;
+mod zones;
 
 use anyhow::Result;
 use gui::{LogEntry, LogLevel};
 use std::sync::{mpsc, Arc};
-use tokio::sync::{Mutex, oneshot};
+use std::time::Duration;
+use tokio::sync::{mpsc as tokio_mpsc, Mutex, oneshot};
+use tokio::time::timeout;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+/// Longest the whole shutdown sequence may take before the process is forced to exit
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize logging
@@ -26,22 +40,63 @@ async fn main() -> Result<()> {
     // Print startup banner
     print_banner();
 
+    // btleplug's WinRT calls need COM on whichever worker thread they run
+    #[cfg(target_os = "windows")]
+    system::windows::init_com();
+
     // Load configuration
     let config = config::Config::load().await?;
+    if !print_config_warnings(&config.validate()) {
+        anyhow::bail!("Invalid configuration, fix the errors above and restart HeartIO");
+    }
     tracing::info!("Configuration loaded successfully");
 
+    // Command line modes that replace the GUI
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|arg| arg == "--selftest") {
+        let passed = selftest::run(&config).await;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
+
+    // One-off import of the history of the Node.js version
+    if let Some(path) = args
+        .iter()
+        .position(|arg| arg == "--import-csv")
+        .and_then(|index| args.get(index + 1))
+    {
+        return import_history(&config, path).await;
+    }
+
     // Create communication channels
     let (log_sender, log_receiver) = mpsc::channel();
     let (gui_heart_rate_sender, gui_heart_rate_receiver) = mpsc::channel();
+    let (connection_status_sender, connection_status_receiver) = mpsc::channel();
+    let (history_sender, history_receiver) = mpsc::channel();
+    let (command_sender, command_receiver) = tokio_mpsc::unbounded_channel();
 
     // Send initial log entries
     send_initial_logs(&log_sender);
 
+    // Check the environment before the GUI opens, so problems show up in both places
+    let startup_report = match selftest::StartupReport::run(&config).await {
+        Ok(report) => report,
+        Err(failure) => anyhow::bail!("{}", failure),
+    };
+    startup_report.print();
+    startup_report.send_logs(&log_sender);
+
+    if config.auto_update_check {
+        update::spawn_check(config.update_check_url.clone(), log_sender.clone());
+    }
+
     // Create heart rate monitor with Arc for sharing between tasks
     let heart_monitor = Arc::new(Mutex::new(heart_rate::HeartRateMonitor::new(
-        config,
+        config.clone(),
         log_sender.clone(),
         gui_heart_rate_sender.clone(),
+        connection_status_sender,
+        history_sender,
+        command_receiver,
     )));
 
     // Setup comprehensive signal handlers for graceful shutdown
@@ -61,23 +116,56 @@ async fn main() -> Result<()> {
             message: "Shutdown signal received, cleaning up...".to_string(),
         });
         
-        // Perform cleanup
-        {
+        // Perform cleanup, the final cleanup below retries if the monitor is still busy
+        let cleanup = async {
             let mut monitor = heart_monitor_signal.lock().await;
             if let Err(e) = monitor.shutdown().await {
                 tracing::error!("Error during shutdown: {}", e);
             }
+        };
+        if timeout(SHUTDOWN_TIMEOUT, cleanup).await.is_err() {
+            tracing::warn!("Shutdown cleanup timed out after {}s", SHUTDOWN_TIMEOUT.as_secs());
         }
         
         let _ = shutdown_sender.send(());
     });
 
+    // SIGTSTP and SIGCONT pause and resume monitoring through the same command as the GUI
+    let pause_command_sender = command_sender.clone();
+    let log_sender_pause = log_sender.clone();
+    tokio::spawn(async move {
+        loop {
+            let paused = tokio::select! {
+                result = signals::wait_for_pause_signal() => result.map(|()| true),
+                result = signals::wait_for_resume_signal() => result.map(|()| false),
+            };
+            match paused {
+                Ok(paused) => {
+                    if pause_command_sender
+                        .send(heart_rate::MonitorCommand::SetPaused(paused))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    let _ = log_sender_pause.send(LogEntry {
+                        timestamp: chrono::Local::now(),
+                        level: LogLevel::Warn,
+                        message: format!("Pause signals unavailable: {}", e),
+                    });
+                    break;
+                }
+            }
+        }
+    });
+
     // Start heart rate monitoring in background task
     let heart_monitor_clone = Arc::clone(&heart_monitor);
     let heart_monitor_handle = tokio::spawn(async move {
         {
             let mut monitor = heart_monitor_clone.lock().await;
-            if let Err(e) = monitor.start().await {
+            if let Err(e) = monitor.start(None).await {
                 tracing::error!("Heart rate monitor error: {}", e);
             }
         }
@@ -87,23 +175,37 @@ async fn main() -> Result<()> {
 
     // Run GUI on main thread (blocking call) with graceful shutdown handling
     let gui_result = tokio::select! {
-        result = gui::run_gui_app(log_receiver, gui_heart_rate_receiver) => result,
+        result = gui::run_gui_app(
+            config,
+            log_receiver,
+            gui_heart_rate_receiver,
+            connection_status_receiver,
+            history_receiver,
+            command_sender,
+        ) => result,
         _ = shutdown_receiver => {
             tracing::info!("Shutdown signal received during GUI execution");
             Ok(())
         }
     };
     
-    // Abort heart monitor task and perform cleanup
-    heart_monitor_handle.abort();
-    let _ = heart_monitor_handle.await;
-    
-    // Final cleanup - ensure resources are freed
-    {
+    let shutdown = async {
+        // Abort heart monitor task and perform cleanup
+        heart_monitor_handle.abort();
+        let _ = heart_monitor_handle.await;
+
+        // Final cleanup - ensure resources are freed
         let mut monitor = heart_monitor.lock().await;
         if let Err(e) = monitor.shutdown().await {
             tracing::error!("Error during final cleanup: {}", e);
         }
+    };
+    if timeout(SHUTDOWN_TIMEOUT, shutdown).await.is_err() {
+        tracing::error!(
+            "Shutdown did not finish within {}s, forcing exit",
+            SHUTDOWN_TIMEOUT.as_secs()
+        );
+        std::process::exit(0);
     }
     
     if let Err(e) = gui_result {
@@ -155,6 +257,39 @@ fn print_banner() {
     println!();
 }
 
+/// Print configuration problems in color, returns false if any of them is fatal
+fn print_config_warnings(warnings: &[config::ConfigWarning]) -> bool {
+    use config::ConfigSeverity;
+
+    for warning in warnings {
+        let (color, label) = match warning.severity {
+            ConfigSeverity::Error => ("31", "error"),
+            ConfigSeverity::Warning => ("33", "warning"),
+            ConfigSeverity::Suggestion => ("36", "suggestion"),
+            ConfigSeverity::Info => ("34", "info"),
+        };
+        println!("\x1b[1;{}m{}\x1b[0m: {}", color, label, warning.message);
+    }
+    if !warnings.is_empty() {
+        println!();
+    }
+    warnings.iter().all(|warning| warning.severity != ConfigSeverity::Error)
+}
+
+/// Import a CSV export into the configured database and print the counts
+async fn import_history(config: &config::Config, path: &str) -> Result<()> {
+    let database =
+        database::Database::new(config.db_path.as_deref(), config.db_backup_count).await?;
+    let summary = database.import_csv(std::path::Path::new(path)).await;
+    database.close().await;
+    let summary = summary?;
+    println!(
+        "Imported {} heart rates, skipped {} malformed rows",
+        summary.imported, summary.skipped
+    );
+    Ok(())
+}
+
 /// Send initial log entries to GUI
 fn send_initial_logs(log_sender: &mpsc::Sender<LogEntry>) {
     let _ = log_sender.send(LogEntry {
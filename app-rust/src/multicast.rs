@@ -0,0 +1,58 @@
+// UDP multicast of heart rate readings for HeartIO
+use anyhow::{Context, Result};
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+
+/// Sends each BPM as a 4-byte little-endian integer to a multicast group, so LAN devices
+/// can follow the heart rate without knowing the HeartIO host's address
+pub struct MulticastSender {
+    socket: UdpSocket,
+    target: SocketAddrV4,
+}
+
+impl MulticastSender {
+    /// Create a sender for `group:port`, the group must be an IPv4 multicast address
+    pub fn new(group: &str, port: u16) -> Result<Self> {
+        let group: Ipv4Addr = group
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid multicast group {}", group))?;
+        if !group.is_multicast() {
+            anyhow::bail!("{} is not a multicast address (224.0.0.0/4)", group);
+        }
+
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .context("Failed to bind UDP socket for multicast")?;
+        socket
+            .join_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)
+            .with_context(|| format!("Failed to join multicast group {}", group))?;
+
+        Ok(Self {
+            socket,
+            target: SocketAddrV4::new(group, port),
+        })
+    }
+
+    /// Multicast group and port readings are sent to
+    pub fn target(&self) -> SocketAddrV4 {
+        self.target
+    }
+
+    /// Send one reading
+    pub fn send(&self, bpm: u32) -> Result<()> {
+        self.socket
+            .send_to(&bpm.to_le_bytes(), self.target)
+            .context("Failed to send multicast packet")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_unicast_and_invalid_groups() {
+        assert!(MulticastSender::new("192.168.1.20", 55000).is_err());
+        assert!(MulticastSender::new("not an address", 55000).is_err());
+    }
+}
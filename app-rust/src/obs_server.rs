@@ -0,0 +1,126 @@
+// Browser source overlay for OBS Studio
+use anyhow::{Context, Result};
+use axum::{
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::State,
+    http::header,
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+const OVERLAY_HTML: &str = include_str!("../assets/obs-overlay.html");
+const OVERLAY_CSS: &str = include_str!("../assets/style.css");
+const OVERLAY_JS: &str = include_str!("../assets/script.js");
+
+/// Readings queued per overlay, a browser falling further behind skips the oldest
+const CLIENT_BUFFER: usize = 16;
+
+/// Serves the overlay page and pushes each reading to it over a WebSocket
+pub struct ObsServer {
+    readings: broadcast::Sender<String>,
+    serve_task: JoinHandle<()>,
+}
+
+impl ObsServer {
+    /// Listen for browser sources on all IPv4 addresses
+    pub async fn start(port: u16) -> Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .await
+            .with_context(|| format!("Failed to bind OBS overlay port {}", port))?;
+        let (readings, _) = broadcast::channel(CLIENT_BUFFER);
+
+        let app = Router::new()
+            .route("/", get(|| async { Html(OVERLAY_HTML) }))
+            .route("/style.css", get(|| async { asset("text/css", OVERLAY_CSS) }))
+            .route("/script.js", get(|| async { asset("text/javascript", OVERLAY_JS) }))
+            .route("/ws", get(ws_handler))
+            .with_state(readings.clone());
+
+        let serve_task = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                tracing::error!("OBS overlay server error: {}", e);
+            }
+        });
+
+        Ok(Self {
+            readings,
+            serve_task,
+        })
+    }
+
+    /// Send a reading to every open overlay
+    pub fn send(&self, bpm: u32) {
+        // Only fails while no overlay is open
+        let _ = self.readings.send(json!({ "bpm": bpm }).to_string());
+    }
+}
+
+impl Drop for ObsServer {
+    fn drop(&mut self) {
+        self.serve_task.abort();
+    }
+}
+
+/// Embedded asset with its content type
+fn asset(content_type: &'static str, body: &'static str) -> Response {
+    ([(header::CONTENT_TYPE, content_type)], body).into_response()
+}
+
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(readings): State<broadcast::Sender<String>>,
+) -> Response {
+    ws.on_upgrade(move |socket| serve_overlay(socket, readings.subscribe()))
+}
+
+/// Forward readings to one overlay until it disconnects
+async fn serve_overlay(mut socket: WebSocket, mut readings: broadcast::Receiver<String>) {
+    loop {
+        let text = match readings.recv().await {
+            Ok(text) => text,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::debug!("OBS overlay fell behind, skipped {}", skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        if socket.send(Message::Text(text)).await.is_err() {
+            tracing::debug!("OBS overlay disconnected");
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    #[tokio::test]
+    async fn serves_embedded_overlay_page() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let _server = ObsServer::start(port).await.unwrap();
+
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        stream
+            .write_all(b"GET /script.js HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("text/javascript"));
+        assert!(response.contains("/ws"));
+    }
+}
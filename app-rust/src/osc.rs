@@ -1,28 +1,59 @@
 // OSC message handling for HeartIO
 use anyhow::{Context, Result};
 use rosc::{OscMessage, OscPacket, OscType};
-use std::net::UdpSocket;
+use std::net::{IpAddr, UdpSocket};
 use std::time::Duration;
-use tokio::time::timeout;
+use tokio::time::{sleep, timeout};
 
 const MESSAGE_MAX_LENGTH: usize = 144;
 const MESSAGE_PATH: &str = "/chatbox/input";
+const TYPING_PATH: &str = "/chatbox/typing";
+const AVATAR_PARAMETERS_PATH: &str = "/avatar/parameters";
+const TYPING_DELAY: Duration = Duration::from_millis(50);
 
 pub struct OscClient {
     socket: UdpSocket,
     host: String,
     port: u16,
+    typing_indicator: bool,
 }
 
 impl OscClient {
-    /// Create a new OSC client
-    pub fn new(host: String, port: u16) -> Result<Self> {
-        let socket = UdpSocket::bind("0.0.0.0:0")
-            .context("Failed to bind UDP socket for OSC client")?;
+    /// Create a new OSC client, sending from `bind_addr` if set so packets leave on its interface
+    pub fn new(
+        host: String,
+        port: u16,
+        typing_indicator: bool,
+        bind_addr: Option<&str>,
+    ) -> Result<Self> {
+        let local_ip: IpAddr = match bind_addr {
+            Some(addr) => addr
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid OSC bind address {:?}", addr))?,
+            None => IpAddr::from([0, 0, 0, 0]),
+        };
+        let socket = UdpSocket::bind((local_ip, 0))
+            .with_context(|| format!("Failed to bind UDP socket for OSC client to {}", local_ip))?;
         
-        tracing::info!("OSC client configured for {}:{}", host, port);
+        match bind_addr {
+            Some(_) => {
+                tracing::info!("OSC client configured for {}:{} from {}", host, port, local_ip)
+            }
+            None => tracing::info!("OSC client configured for {}:{}", host, port),
+        }
         
-        Ok(Self { socket, host, port })
+        Ok(Self {
+            socket,
+            host,
+            port,
+            typing_indicator,
+        })
+    }
+
+    /// Get the target address as host:port
+    pub fn target(&self) -> String {
+        format!("{}:{}", self.host, self.port)
     }
 
     /// Send OSC message with text
@@ -35,6 +66,12 @@ impl OscClient {
             );
         }
 
+        // Show the chatbox typing indicator briefly before the message
+        if self.typing_indicator {
+            self.send_typing(true).await?;
+            sleep(TYPING_DELAY).await;
+        }
+
         let msg = OscMessage {
             addr: MESSAGE_PATH.to_string(),
             args: vec![
@@ -43,7 +80,41 @@ impl OscClient {
                 OscType::Bool(false), // disable SFX
             ],
         };
+        self.send_packet(msg).await?;
+
+        if self.typing_indicator {
+            self.send_typing(false).await?;
+        }
 
+        tracing::info!("Sent OSC message: {}", text);
+        Ok(())
+    }
+
+    /// Send a value to an avatar parameter, `name` may also be a full OSC address
+    pub async fn send_parameter(&self, name: &str, value: OscType) -> Result<()> {
+        let addr = if name.starts_with('/') {
+            name.to_string()
+        } else {
+            format!("{}/{}", AVATAR_PARAMETERS_PATH, name)
+        };
+        self.send_packet(OscMessage {
+            addr,
+            args: vec![value],
+        })
+        .await
+    }
+
+    /// Toggle the chatbox typing indicator
+    async fn send_typing(&self, typing: bool) -> Result<()> {
+        self.send_packet(OscMessage {
+            addr: TYPING_PATH.to_string(),
+            args: vec![OscType::Bool(typing)],
+        })
+        .await
+    }
+
+    /// Encode and send a single OSC message
+    async fn send_packet(&self, msg: OscMessage) -> Result<()> {
         let packet = OscPacket::Message(msg);
         let encoded = rosc::encoder::encode(&packet)
             .context("Failed to encode OSC message")?;
@@ -61,7 +132,6 @@ impl OscClient {
         .context("Failed to spawn blocking task")?
         .context("Failed to send OSC message")?;
 
-        tracing::info!("Sent OSC message: {}", text);
         Ok(())
     }
 
@@ -0,0 +1,336 @@
+// Environment checks, run before the GUI opens and with `--selftest` instead of the GUI
+use anyhow::{Context, Result};
+use btleplug::api::Central;
+use std::sync::mpsc;
+use std::time::Duration;
+use tokio::time::timeout;
+
+use crate::bluetooth::{classify_scan_error, select_adapter, ScanError};
+use crate::config::{Config, ConfigSeverity, MonitorMode};
+use crate::database::Database;
+use crate::gui::{LogEntry, LogLevel};
+use crate::osc::OscClient;
+use crate::server::{self, APPLE_WATCH_PORT};
+use crate::xiaomi_band::XiaomiBandMonitor;
+
+/// Longest a single startup check may take, so a slow Bluetooth stack can't hold up the GUI
+const STARTUP_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Problems HeartIO can't run with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupFailure {
+    NoBluetoothAdapter,
+}
+
+impl std::fmt::Display for StartupFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoBluetoothAdapter => write!(
+                f,
+                "No Bluetooth adapter found, but every configured heart rate source needs one. \
+                 Enable Bluetooth or switch to Apple Watch mode in heartio.config.json"
+            ),
+        }
+    }
+}
+
+/// One line of the startup report
+pub struct StartupCheck {
+    pub name: String,
+    pub result: Result<String>,
+}
+
+/// Checks run before the GUI opens, printed to the console and repeated in the GUI log.
+/// Nothing is sent or scanned, the active probes are left to `--selftest`.
+pub struct StartupReport {
+    pub checks: Vec<StartupCheck>,
+}
+
+impl StartupReport {
+    /// Run all checks at once, each giving up after `STARTUP_CHECK_TIMEOUT`. Fails when a
+    /// problem HeartIO can't run with was found.
+    pub async fn run(config: &Config) -> Result<Self, StartupFailure> {
+        let osc_checks = config.osc_endpoints().into_iter().map(|(host, port)| async move {
+            let name = format!("OSC {}:{}", host, port);
+            (name, within_timeout(check_osc_socket(config, host, port)).await)
+        });
+        let (bluetooth, database_dir, osc) = tokio::join!(
+            timeout(STARTUP_CHECK_TIMEOUT, check_bluetooth(config)),
+            within_timeout(check_database_dir(config)),
+            futures::future::join_all(osc_checks),
+        );
+
+        // A slow adapter is only a warning, a missing one is fatal
+        let adapter_missing = matches!(bluetooth, Ok(Err(_)));
+        if let Some(failure) = fatal_failure(&config.sources(), adapter_missing) {
+            return Err(failure);
+        }
+        let bluetooth = bluetooth.unwrap_or_else(timed_out);
+        let xiaomi = xiaomi_band_mode(&config.sources(), &bluetooth);
+
+        let mut checks = vec![
+            StartupCheck::new("Bluetooth adapter", bluetooth),
+            StartupCheck::new("Config file", check_config_file(config)),
+            StartupCheck::new("Database directory", database_dir),
+        ];
+        checks.extend(osc.into_iter().map(|(name, result)| StartupCheck { name, result }));
+        checks.push(StartupCheck::new(
+            &format!("Apple Watch port {}", APPLE_WATCH_PORT),
+            check_apple_watch_port(config),
+        ));
+        checks.push(StartupCheck::new("Xiaomi Band mode", xiaomi));
+        Ok(Self { checks })
+    }
+
+    /// Print the report as a table
+    pub fn print(&self) {
+        let width = self.checks.iter().map(|check| check.name.len()).max().unwrap_or(0);
+        for check in &self.checks {
+            match &check.result {
+                Ok(detail) => {
+                    println!("  \x1b[1;32m✓\x1b[0m {:<width$}  {}", check.name, detail)
+                }
+                Err(e) => println!("  \x1b[1;31m✗\x1b[0m {:<width$}  {:#}", check.name, e),
+            }
+        }
+        println!();
+    }
+
+    /// Repeat the report in the GUI log, failed checks as warnings
+    pub fn send_logs(&self, log_sender: &mpsc::Sender<LogEntry>) {
+        for check in &self.checks {
+            let (level, message) = match &check.result {
+                Ok(detail) => (LogLevel::Info, format!("✓ {}: {}", check.name, detail)),
+                Err(e) => (LogLevel::Warn, format!("✗ {}: {:#}", check.name, e)),
+            };
+            let _ = log_sender.send(LogEntry {
+                timestamp: chrono::Local::now(),
+                level,
+                message,
+            });
+        }
+    }
+}
+
+impl StartupCheck {
+    fn new(name: &str, result: Result<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            result,
+        }
+    }
+}
+
+async fn within_timeout(
+    check: impl std::future::Future<Output = Result<String>>,
+) -> Result<String> {
+    timeout(STARTUP_CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or_else(timed_out)
+}
+
+fn timed_out(_: tokio::time::error::Elapsed) -> Result<String> {
+    anyhow::bail!("no answer within {}s", STARTUP_CHECK_TIMEOUT.as_secs())
+}
+
+/// HeartIO can't run when every source needs the Bluetooth adapter and there is none
+fn fatal_failure(sources: &[MonitorMode], adapter_missing: bool) -> Option<StartupFailure> {
+    let needs_adapter = sources
+        .iter()
+        .all(|source| matches!(source, MonitorMode::Bluetooth | MonitorMode::XiaomiBand));
+    (adapter_missing && needs_adapter).then_some(StartupFailure::NoBluetoothAdapter)
+}
+
+/// Run every check and print a pass/fail report, returns whether all of them passed
+pub async fn run(config: &Config) -> bool {
+    println!("HeartIO self-test");
+    println!();
+
+    let mut passed = report("Bluetooth adapter", check_bluetooth(config).await);
+    for (host, port) in config.osc_endpoints() {
+        let check = format!("OSC {}:{}", host, port);
+        passed &= report(&check, check_osc(config, host, port).await);
+    }
+    passed &= report("Database", check_database(config).await);
+    passed &= report(
+        &format!("Apple Watch port {}", APPLE_WATCH_PORT),
+        server::check_port(config.apple_watch_bind_address.as_deref(), APPLE_WATCH_PORT),
+    );
+    passed &= report("Xiaomi Band mode", check_xiaomi_band(config).await);
+
+    println!();
+    println!("{}", if passed { "All checks passed" } else { "Some checks failed" });
+    passed
+}
+
+/// Print one result line, returns whether the check passed
+fn report(check: &str, result: Result<String>) -> bool {
+    match result {
+        Ok(detail) => {
+            println!("  \x1b[1;32mPASS\x1b[0m {}: {}", check, detail);
+            true
+        }
+        Err(e) => {
+            println!("  \x1b[1;31mFAIL\x1b[0m {}: {:#}", check, e);
+            false
+        }
+    }
+}
+
+async fn check_bluetooth(config: &Config) -> Result<String> {
+    let adapter = select_adapter(config.ble_adapter.as_deref()).await?;
+    Ok(adapter
+        .adapter_info()
+        .await
+        .unwrap_or_else(|_| "unknown adapter".to_string()))
+}
+
+/// The config was already loaded by now, so only its warnings are left to report
+fn check_config_file(config: &Config) -> Result<String> {
+    let path = Config::config_path()?;
+    let warnings = config.validate();
+    let errors = warnings
+        .iter()
+        .filter(|warning| warning.severity == ConfigSeverity::Error)
+        .count();
+    anyhow::ensure!(errors == 0, "{} has {} errors", path.display(), errors);
+    Ok(format!("{}, {} warnings", path.display(), warnings.len()))
+}
+
+/// Write and delete a file where the database lives, without opening the database itself
+async fn check_database_dir(config: &Config) -> Result<String> {
+    if !config.persist {
+        return Ok("not used, PERSIST is off".to_string());
+    }
+    let path = Database::get_db_path(config.db_path.as_deref())?;
+    let dir = match path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        Some(dir) => dir.to_path_buf(),
+        None => std::path::PathBuf::from("."),
+    };
+    match tokio::fs::metadata(&dir).await {
+        Ok(metadata) => {
+            anyhow::ensure!(metadata.is_dir(), "{} is not a directory", dir.display());
+            let probe = dir.join(".heartio-write-check");
+            tokio::fs::write(&probe, b"")
+                .await
+                .with_context(|| format!("{} is not writable", dir.display()))?;
+            let _ = tokio::fs::remove_file(&probe).await;
+            Ok(format!("{} is writable", dir.display()))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Ok(format!("{} will be created", dir.display()))
+        }
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    }
+}
+
+fn check_apple_watch_port(config: &Config) -> Result<String> {
+    if !config.sources().contains(&MonitorMode::AppleWatch) {
+        return Ok("not used".to_string());
+    }
+    server::check_port(config.apple_watch_bind_address.as_deref(), APPLE_WATCH_PORT)
+}
+
+/// Whether the Xiaomi Band mode has an adapter to scan with, judged from the adapter check
+/// alone. Known Bluetooth problems get the same guidance the GUI shows for a failed scan.
+fn xiaomi_band_mode(sources: &[MonitorMode], adapter: &Result<String>) -> Result<String> {
+    if !sources.contains(&MonitorMode::XiaomiBand) {
+        return Ok("not used".to_string());
+    }
+    let e = match adapter {
+        Ok(adapter) => return Ok(format!("scans with {}", adapter)),
+        Err(e) => e,
+    };
+    let scan_error = e
+        .downcast_ref::<ScanError>()
+        .copied()
+        .or_else(|| e.downcast_ref::<btleplug::Error>().and_then(classify_scan_error));
+    match scan_error {
+        Some(scan_error) => anyhow::bail!("{:#}. {}", e, scan_error.guidance()),
+        None => anyhow::bail!("{:#}", e),
+    }
+}
+
+/// Start and stop a scan on the adapter the Xiaomi Band mode would use
+async fn check_xiaomi_band(config: &Config) -> Result<String> {
+    if !config.sources().contains(&MonitorMode::XiaomiBand) {
+        return Ok("not used".to_string());
+    }
+    let monitor = XiaomiBandMonitor::new(
+        config.bpm_range(),
+        config.ble_adapter.as_deref(),
+        config.xiaomi_hr_field(),
+    )
+    .await?;
+    anyhow::ensure!(
+        monitor.check_bluetooth_availability().await?,
+        "Bluetooth scanning is unavailable"
+    );
+    Ok("scanning works".to_string())
+}
+
+/// Bind the OSC socket and resolve the target, without sending anything
+async fn check_osc_socket(config: &Config, host: String, port: u16) -> Result<String> {
+    OscClient::new(host.clone(), port, false, config.osc_bind_addr.as_deref())?;
+    let resolved = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .with_context(|| format!("Failed to resolve {}", host))?
+        .next()
+        .with_context(|| format!("No address for {}", host))?;
+    Ok(format!("socket bound, sending to {}", resolved))
+}
+
+async fn check_osc(config: &Config, host: String, port: u16) -> Result<String> {
+    let client = OscClient::new(host, port, false, config.osc_bind_addr.as_deref())?;
+    client.test_connection().await?;
+    Ok("socket bound, test message sent".to_string())
+}
+
+async fn check_database(config: &Config) -> Result<String> {
+    let database = if config.persist {
+        Database::new(config.db_path.as_deref(), config.db_backup_count).await?
+    } else {
+        Database::in_memory().await?
+    };
+    let result = database.self_test().await;
+    database.close().await;
+    result?;
+    Ok(if config.persist {
+        "opened, written and read back".to_string()
+    } else {
+        "in memory (PERSIST is off), written and read back".to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_adapter_is_fatal_only_without_other_sources() {
+        use MonitorMode::*;
+        assert_eq!(
+            fatal_failure(&[Bluetooth], true),
+            Some(StartupFailure::NoBluetoothAdapter)
+        );
+        assert_eq!(
+            fatal_failure(&[XiaomiBand, Bluetooth], true),
+            Some(StartupFailure::NoBluetoothAdapter)
+        );
+        assert_eq!(fatal_failure(&[Bluetooth, AppleWatch], true), None);
+        assert_eq!(fatal_failure(&[Bluetooth], false), None);
+    }
+
+    #[test]
+    fn xiaomi_band_mode_follows_the_adapter_check() {
+        use MonitorMode::*;
+        let adapter: Result<String> = Ok("hci0".to_string());
+        assert_eq!(xiaomi_band_mode(&[Bluetooth], &adapter).unwrap(), "not used");
+        assert_eq!(xiaomi_band_mode(&[XiaomiBand], &adapter).unwrap(), "scans with hci0");
+
+        let denied: Result<String> = Err(btleplug::Error::PermissionDenied)
+            .context("Failed to get Bluetooth adapters");
+        let message = format!("{:#}", xiaomi_band_mode(&[XiaomiBand], &denied).unwrap_err());
+        assert!(message.ends_with(ScanError::PermissionDenied.guidance()), "{}", message);
+    }
+}
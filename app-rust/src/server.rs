@@ -1,22 +1,58 @@
 // HTTP server for Apple Watch heart rate data
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use axum::{
-    extract::Query,
-    http::StatusCode,
-    response::Json,
+    extract::{ConnectInfo, Query, Request},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Json, Response},
     routing::get,
     Router,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
-use tokio::sync::mpsc;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
 use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
 
+use crate::config::{BpmRange, MonitorMode};
+use crate::database::Database;
+use crate::gui::AppStats;
+use crate::heart_rate::HeartRateReading;
+use crate::source::{HeartRateSample, HeartRateSource};
+
+/// Port the Apple Watch server listens on
+pub const APPLE_WATCH_PORT: u16 = 2333;
+
+/// How far a reported measurement time may lie ahead of the local clock
+const MAX_FUTURE_SKEW: Duration = Duration::from_secs(60);
+/// How long open requests may take to finish on shutdown, within the monitor's source stop
+/// timeout
+const SERVER_DRAIN_TIMEOUT: Duration = Duration::from_secs(4);
+/// Rate limited clients are logged at most once per period
+const RATE_LIMIT_WARN_PERIOD: Duration = Duration::from_secs(60);
+/// Clients remembered by the rate limiter before idle ones are forgotten
+const RATE_LIMIT_MAX_CLIENTS: usize = 256;
+/// Readings returned by `/recent` without a `limit`
+const RECENT_DEFAULT_LIMIT: u32 = 60;
+/// Most readings `/recent` returns, larger limits are capped
+const RECENT_MAX_LIMIT: u32 = 1000;
+
 #[derive(Debug, Deserialize)]
 pub struct HeartRateQuery {
     pub bpm: Option<u32>,
+    /// Measurement time as RFC 3339 or Unix seconds
+    pub timestamp: Option<String>,
+    /// Name of the sending device or shortcut
+    pub source: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -25,51 +61,378 @@ pub struct ApiResponse {
     pub message: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RecentQuery {
+    pub limit: Option<u32>,
+}
+
+/// One stored reading returned by `/recent`
+#[derive(Debug, Serialize)]
+pub struct RecentReading {
+    pub id: i64,
+    pub bpm: i32,
+    pub timestamp: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatsResponse {
+    pub current_bpm: Option<u32>,
+    pub avg_bpm: Option<f64>,
+    pub stddev_bpm: Option<f64>,
+    pub min_bpm: Option<u32>,
+    pub max_bpm: Option<u32>,
+    pub total_readings: u32,
+    pub energy_kcal: Option<f64>,
+    pub energy_kcal_per_min: Option<f64>,
+    pub session_duration_secs: u64,
+    pub session_start: Option<String>,
+    pub last_update: Option<String>,
+    /// Requests rejected by the rate limit since the server started
+    pub rate_limited_requests: u32,
+}
+
+/// Counters of the Apple Watch server itself
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ServerStats {
+    pub rate_limited_count: u32,
+}
+
+/// Requests of one client in its current one-second window
+struct ClientWindow {
+    started: Instant,
+    requests: u32,
+    warned_at: Option<Instant>,
+}
+
+/// Whether a request may pass the rate limit
+#[derive(Debug, PartialEq, Eq)]
+enum Admission {
+    Allowed,
+    /// `warn` is set for the first rejection of the client per `RATE_LIMIT_WARN_PERIOD`
+    Limited { warn: bool },
+}
+
+/// Allows each client IP at most `max_per_second` requests per one-second window
+struct RateLimiter {
+    max_per_second: u32,
+    clients: Mutex<HashMap<IpAddr, ClientWindow>>,
+    stats: Mutex<ServerStats>,
+}
+
+impl RateLimiter {
+    /// 0 disables the limit
+    fn new(max_per_second: u32) -> Self {
+        Self {
+            max_per_second,
+            clients: Mutex::new(HashMap::new()),
+            stats: Mutex::new(ServerStats::default()),
+        }
+    }
+
+    fn admit(&self, ip: IpAddr, now: Instant) -> Admission {
+        if self.max_per_second == 0 {
+            return Admission::Allowed;
+        }
+        let mut clients = self.clients.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if clients.len() >= RATE_LIMIT_MAX_CLIENTS {
+            clients.retain(|_, window| now.duration_since(window.started) < RATE_LIMIT_WARN_PERIOD);
+        }
+
+        let window = clients.entry(ip).or_insert(ClientWindow {
+            started: now,
+            requests: 0,
+            warned_at: None,
+        });
+        if now.duration_since(window.started) >= Duration::from_secs(1) {
+            window.started = now;
+            window.requests = 0;
+        }
+        window.requests += 1;
+        if window.requests <= self.max_per_second {
+            return Admission::Allowed;
+        }
+
+        self.stats
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .rate_limited_count += 1;
+        let warn = window
+            .warned_at
+            .is_none_or(|warned_at| now.duration_since(warned_at) >= RATE_LIMIT_WARN_PERIOD);
+        if warn {
+            window.warned_at = Some(now);
+        }
+        Admission::Limited { warn }
+    }
+
+    fn stats(&self) -> ServerStats {
+        *self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Shared state for the Apple Watch server handlers
+#[derive(Clone)]
+struct ServerState {
+    heart_rate_sender: mpsc::UnboundedSender<HeartRateReading>,
+    bpm_range: BpmRange,
+    stats: Arc<RwLock<AppStats>>,
+    rate_limiter: Arc<RateLimiter>,
+    /// History served by `/recent`, `None` when the monitor runs without a database
+    database: Option<Database>,
+}
+
+type ListeningCallback = Box<dyn Fn(bool) + Send + Sync>;
+
 pub struct AppleWatchServer {
-    heart_rate_sender: mpsc::UnboundedSender<u32>,
+    state: ServerState,
+    listening_callback: Option<ListeningCallback>,
 }
 
 impl AppleWatchServer {
-    /// Create a new Apple Watch server
-    pub fn new(heart_rate_sender: mpsc::UnboundedSender<u32>) -> Self {
-        Self { heart_rate_sender }
+    /// Create a new Apple Watch server, accepting `max_rps` requests per second per client
+    pub fn new(
+        heart_rate_sender: mpsc::UnboundedSender<HeartRateReading>,
+        bpm_range: BpmRange,
+        stats: Arc<RwLock<AppStats>>,
+        max_rps: u32,
+        database: Option<Database>,
+    ) -> Self {
+        Self {
+            state: ServerState {
+                heart_rate_sender,
+                bpm_range,
+                stats,
+                rate_limiter: Arc::new(RateLimiter::new(max_rps)),
+                database,
+            },
+            listening_callback: None,
+        }
     }
 
-    /// Start the HTTP server
-    pub async fn start(&self, port: u16) -> Result<()> {
-        let addr = SocketAddr::from(([0, 0, 0, 0], port));
-        
+    /// Register a callback told when the server starts and stops accepting requests
+    pub fn on_listening<F>(&mut self, callback: F)
+    where
+        F: Fn(bool) + Send + Sync + 'static,
+    {
+        self.listening_callback = Some(Box::new(callback));
+    }
+
+    fn notify_listening(&self, listening: bool) {
+        if let Some(callback) = &self.listening_callback {
+            callback(listening);
+        }
+    }
+
+    /// Start the HTTP server, on all IPv4 and IPv6 addresses unless `bind_address` is set
+    ///
+    /// Once `shutdown_signal` changes or its sender is dropped, no new connections are
+    /// accepted and the server returns when the open ones are done.
+    pub async fn start(
+        &self,
+        bind_address: Option<&str>,
+        port: u16,
+        mut shutdown_signal: watch::Receiver<()>,
+    ) -> Result<()> {
         let app = Router::new()
             .route("/heart", get(heart_rate_handler))
             .route("/health", get(health_handler))
+            .route("/stats", get(stats_handler))
+            .route("/recent", get(recent_handler))
+            .layer(middleware::from_fn_with_state(self.state.clone(), rate_limit))
             .layer(
                 ServiceBuilder::new()
                     .layer(TraceLayer::new_for_http())
                     .layer(CorsLayer::permissive()),
             )
-            .with_state(self.heart_rate_sender.clone());
+            .with_state(self.state.clone());
+
+        let (listener, families) = bind_listener(bind_address, port)?;
+        tracing::info!(
+            "Apple Watch server listening on {} ({})",
+            listener.local_addr()?,
+            families
+        );
+
+        self.notify_listening(true);
+
+        let result = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(async move {
+                shutdown_signal.changed().await.ok();
+            })
+            .await
+            .context("Apple Watch server error");
+        self.notify_listening(false);
+        result
+    }
+}
+
+/// Heart rate source backed by the Apple Watch HTTP server
+pub struct AppleWatchSource {
+    heart_rate_receiver: mpsc::UnboundedReceiver<HeartRateReading>,
+    server: JoinHandle<Result<()>>,
+    shutdown_sender: watch::Sender<()>,
+}
+
+impl AppleWatchSource {
+    /// Start the server on the given address and port in the background
+    pub fn start(
+        bpm_range: BpmRange,
+        stats: Arc<RwLock<AppStats>>,
+        bind_address: Option<String>,
+        port: u16,
+        max_rps: u32,
+        database: Option<Database>,
+        on_listening: impl Fn(bool) + Send + Sync + 'static,
+    ) -> Self {
+        let (heart_rate_sender, heart_rate_receiver) = mpsc::unbounded_channel();
+        let (shutdown_sender, shutdown_signal) = watch::channel(());
+        let mut server =
+            AppleWatchServer::new(heart_rate_sender, bpm_range, stats, max_rps, database);
+        server.on_listening(on_listening);
+        Self {
+            heart_rate_receiver,
+            server: tokio::spawn(async move {
+                server.start(bind_address.as_deref(), port, shutdown_signal).await
+            }),
+            shutdown_sender,
+        }
+    }
+}
 
-        tracing::info!("Apple Watch server starting on {}", addr);
-        
-        let listener = tokio::net::TcpListener::bind(addr).await
-            .context("Failed to bind Apple Watch server")?;
-        
-        axum::serve(listener, app).await
-            .context("Apple Watch server error")?;
+#[async_trait]
+impl HeartRateSource for AppleWatchSource {
+    async fn next_sample(&mut self) -> Result<HeartRateSample> {
+        if let Some(reading) = self.heart_rate_receiver.recv().await {
+            return Ok(reading);
+        }
+
+        // The channel only closes once the server task has ended
+        match (&mut self.server).await {
+            Ok(Ok(())) => Err(anyhow::anyhow!("Apple Watch server stopped")),
+            Ok(Err(e)) => Err(e),
+            Err(e) => Err(anyhow::anyhow!("Apple Watch server task failed: {}", e)),
+        }
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        // Already awaited by `next_sample` if the server stopped by itself
+        if self.server.is_finished() {
+            return Ok(());
+        }
+
+        // Let requests in flight finish, so companion apps don't see a reset connection
+        let _ = self.shutdown_sender.send(());
+        match tokio::time::timeout(SERVER_DRAIN_TIMEOUT, &mut self.server).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(e)) => Err(anyhow::anyhow!("Apple Watch server task failed: {}", e)),
+            Err(_) => {
+                tracing::warn!(
+                    "Apple Watch server connections still open after {}s, closing them",
+                    SERVER_DRAIN_TIMEOUT.as_secs()
+                );
+                self.server.abort();
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Check that the server port can be bound, returning the address families it would serve
+pub fn check_port(bind_address: Option<&str>, port: u16) -> Result<String> {
+    let (listener, families) = bind_listener(bind_address, port)?;
+    Ok(format!("{} is free ({})", listener.local_addr()?, families))
+}
+
+/// Bind the server socket, returning it with a description of the address families it serves
+///
+/// Without a configured address a dual-stack socket is used, falling back to IPv4 when
+/// IPv6 is unavailable. An IPv6 wildcard (`::`) is dual-stack too, other addresses only
+/// serve their own family.
+fn bind_listener(bind_address: Option<&str>, port: u16) -> Result<(TcpListener, &'static str)> {
+    let ip = match bind_address {
+        Some(address) => address
+            .trim()
+            .parse::<IpAddr>()
+            .with_context(|| format!("Invalid APPLE_WATCH_BIND_ADDRESS '{}'", address))?,
+        None => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let addr = SocketAddr::new(ip, port);
 
-        Ok(())
+    let dual_stack = ip == IpAddr::V6(Ipv6Addr::UNSPECIFIED);
+    match bind_socket(addr, dual_stack) {
+        Ok(listener) if dual_stack => Ok((listener, "IPv4 and IPv6")),
+        Ok(listener) if ip.is_ipv4() => Ok((listener, "IPv4 only")),
+        Ok(listener) => Ok((listener, "IPv6 only")),
+        Err(e) if bind_address.is_none() => {
+            tracing::warn!("IPv6 unavailable ({}), Apple Watch server falls back to IPv4", e);
+            let listener = bind_socket(SocketAddr::from(([0, 0, 0, 0], port)), false)?;
+            Ok((listener, "IPv4 only"))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Create a listening TCP socket, accepting IPv4-mapped connections when `dual_stack` is set
+fn bind_socket(addr: SocketAddr, dual_stack: bool) -> Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))
+        .context("Failed to create Apple Watch server socket")?;
+    if addr.is_ipv6() {
+        socket
+            .set_only_v6(!dual_stack)
+            .context("Failed to configure IPv6 socket")?;
+    }
+    // Allows a quick restart while the old socket is in TIME_WAIT. On Windows it would let a
+    // second instance bind the port in use instead, and `check_port` report it as free.
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket
+        .bind(&addr.into())
+        .with_context(|| format!("Failed to bind Apple Watch server to {}", addr))?;
+    socket.listen(1024)?;
+    TcpListener::from_std(socket.into()).context("Failed to register Apple Watch server socket")
+}
+
+/// Reject clients sending more requests per second than allowed with 429 Too Many Requests
+async fn rate_limit(
+    axum::extract::State(state): axum::extract::State<ServerState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // Dual-stack sockets report IPv4 clients as mapped IPv6 addresses
+    let ip = addr.ip().to_canonical();
+    match state.rate_limiter.admit(ip, Instant::now()) {
+        Admission::Allowed => next.run(request).await,
+        Admission::Limited { warn } => {
+            if warn {
+                tracing::warn!(
+                    "Apple Watch server is rate limiting {}, more than {} requests per second",
+                    ip,
+                    state.rate_limiter.max_per_second
+                );
+            }
+            (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, "1")]).into_response()
+        }
     }
 }
 
 /// Handle heart rate data from Apple Watch
 async fn heart_rate_handler(
     Query(params): Query<HeartRateQuery>,
-    axum::extract::State(sender): axum::extract::State<mpsc::UnboundedSender<u32>>,
+    axum::extract::State(state): axum::extract::State<ServerState>,
 ) -> Result<Json<ApiResponse>, StatusCode> {
     let bpm = match params.bpm {
-        Some(bpm) if bpm > 0 && bpm < 300 => bpm,
-        Some(_) => {
-            tracing::warn!("Invalid BPM value received");
+        // Lost contact, passed on so the monitor can show its zero reading message
+        Some(0) => 0,
+        Some(bpm) if state.bpm_range.contains(bpm) => bpm,
+        Some(bpm) => {
+            tracing::debug!(
+                "Dropping out-of-range heart rate {} (allowed {}-{})",
+                bpm,
+                state.bpm_range.min,
+                state.bpm_range.max
+            );
             return Err(StatusCode::BAD_REQUEST);
         }
         None => {
@@ -78,20 +441,55 @@ async fn heart_rate_handler(
         }
     };
 
+    let measured_at = match params.timestamp.as_deref().map(parse_timestamp) {
+        Some(Some(time)) if time > Utc::now() + MAX_FUTURE_SKEW => {
+            tracing::warn!("Rejecting heart rate with timestamp in the future: {}", time);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Some(Some(time)) => Some(time),
+        Some(None) => {
+            tracing::warn!("Invalid timestamp parameter: {:?}", params.timestamp);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        None => None,
+    };
+    let device = params.source.filter(|source| !source.trim().is_empty());
+
+    match &device {
+        Some(device) => {
+            tracing::info!("Received heart rate from Apple Watch ({}): {}", device, bpm)
+        }
+        None => tracing::info!("Received heart rate from Apple Watch: {}", bpm),
+    }
+
     // Send heart rate data to main processor
-    if let Err(_) = sender.send(bpm) {
+    let reading = HeartRateReading {
+        measured_at,
+        device,
+        ..HeartRateReading::new(MonitorMode::AppleWatch, bpm)
+    };
+    if state.heart_rate_sender.send(reading).is_err() {
         tracing::error!("Failed to send heart rate data to processor");
         return Err(StatusCode::INTERNAL_SERVER_ERROR);
     }
 
-    tracing::info!("Received heart rate from Apple Watch: {}", bpm);
-
     Ok(Json(ApiResponse {
         status: "success".to_string(),
         message: format!("Heart rate {} BPM received", bpm),
     }))
 }
 
+/// Parse a measurement time given as RFC 3339 or Unix seconds
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<i64>() {
+        return DateTime::from_timestamp(seconds, 0);
+    }
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
 /// Health check endpoint
 async fn health_handler() -> Json<ApiResponse> {
     Json(ApiResponse {
@@ -99,3 +497,113 @@ async fn health_handler() -> Json<ApiResponse> {
         message: "Apple Watch server is running".to_string(),
     })
 }
+
+/// Current session statistics for dashboards and overlays
+async fn stats_handler(
+    axum::extract::State(state): axum::extract::State<ServerState>,
+) -> Result<Json<StatsResponse>, StatusCode> {
+    let stats = state
+        .stats
+        .read()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(StatsResponse {
+        current_bpm: stats.current_heart_rate,
+        avg_bpm: (stats.total_heart_rates > 0).then(|| stats.heart_rate_stats.mean()),
+        stddev_bpm: (stats.total_heart_rates > 0).then(|| stats.heart_rate_stats.stddev()),
+        min_bpm: stats.min_heart_rate,
+        max_bpm: stats.max_heart_rate,
+        total_readings: stats.total_heart_rates,
+        energy_kcal: stats.energy.has_data().then(|| stats.energy.total_kcal()),
+        energy_kcal_per_min: stats.energy.kcal_per_min(),
+        session_duration_secs: stats.session_start_time.map_or(0, |start| {
+            (chrono::Local::now() - start).to_std().unwrap_or_default().as_secs()
+        }),
+        session_start: stats.session_start_time.map(|start| start.to_rfc3339()),
+        last_update: stats.last_heart_rate_time.map(|time| time.to_rfc3339()),
+        rate_limited_requests: state.rate_limiter.stats().rate_limited_count,
+    }))
+}
+
+/// Latest stored readings, oldest first, for overlays drawing a short graph
+async fn recent_handler(
+    Query(params): Query<RecentQuery>,
+    axum::extract::State(state): axum::extract::State<ServerState>,
+) -> Result<Json<Vec<RecentReading>>, StatusCode> {
+    let database = state.database.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let limit = params.limit.unwrap_or(RECENT_DEFAULT_LIMIT).min(RECENT_MAX_LIMIT);
+
+    let records = database.get_recent_heart_rates(limit).await.map_err(|e| {
+        tracing::warn!("Failed to fetch recent heart rates: {:#}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(
+        records
+            .into_iter()
+            .map(|record| RecentReading {
+                id: record.id,
+                bpm: record.bpm,
+                timestamp: record.created_at.to_rfc3339(),
+            })
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn shutdown_closes_the_listener() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut source = AppleWatchSource::start(
+            BpmRange { min: 30, max: 250 },
+            Arc::new(RwLock::new(AppStats::default())),
+            Some("127.0.0.1".to_string()),
+            port,
+            10,
+            None,
+            |_| {},
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(tokio::net::TcpStream::connect(("127.0.0.1", port)).await.is_ok());
+
+        source.shutdown().await.unwrap();
+        assert!(source.server.is_finished());
+        assert!(tokio::net::TcpStream::connect(("127.0.0.1", port)).await.is_err());
+    }
+
+    #[test]
+    fn rate_limit_applies_per_client_and_window() {
+        let limiter = RateLimiter::new(2);
+        let client = IpAddr::from([192, 168, 1, 20]);
+        let other = IpAddr::from([192, 168, 1, 21]);
+        let now = Instant::now();
+
+        assert_eq!(limiter.admit(client, now), Admission::Allowed);
+        assert_eq!(limiter.admit(client, now), Admission::Allowed);
+        assert_eq!(limiter.admit(client, now), Admission::Limited { warn: true });
+        assert_eq!(limiter.admit(client, now), Admission::Limited { warn: false });
+        assert_eq!(limiter.admit(other, now), Admission::Allowed);
+
+        let next_window = now + Duration::from_secs(1);
+        assert_eq!(limiter.admit(client, next_window), Admission::Allowed);
+        assert_eq!(limiter.stats().rate_limited_count, 2);
+
+        let unlimited = RateLimiter::new(0);
+        assert!((0..100).all(|_| unlimited.admit(client, now) == Admission::Allowed));
+    }
+
+    #[tokio::test]
+    async fn port_check_fails_while_in_use() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(check_port(Some("127.0.0.1"), port).is_err());
+        drop(listener);
+        assert!(check_port(Some("127.0.0.1"), port).is_ok());
+    }
+}
@@ -0,0 +1,193 @@
+// Heart rate sharing between HeartIO instances over TCP
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+
+/// Readings queued per client, a client falling further behind skips the oldest
+const CLIENT_BUFFER: usize = 16;
+/// Wait before reconnecting to the remote instance after the connection drops
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// A reading as sent between instances, one JSON object per line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedReading {
+    pub bpm: u32,
+    pub measured_at: DateTime<Utc>,
+}
+
+/// Sends the local heart rate to every connected HeartIO client
+pub struct ShareServer {
+    readings: broadcast::Sender<String>,
+    accept_task: JoinHandle<()>,
+}
+
+impl ShareServer {
+    /// Listen for clients on all IPv4 addresses
+    pub async fn start(port: u16) -> Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .await
+            .with_context(|| format!("Failed to bind heart rate sharing port {}", port))?;
+        let (readings, _) = broadcast::channel(CLIENT_BUFFER);
+
+        let clients = readings.clone();
+        let accept_task = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, peer)) => {
+                        tracing::info!("Heart rate sharing client connected: {}", peer);
+                        tokio::spawn(serve_client(stream, peer, clients.subscribe()));
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to accept heart rate sharing client: {}", e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            readings,
+            accept_task,
+        })
+    }
+
+    /// Send a reading to all connected clients
+    pub fn send(&self, reading: &SharedReading) {
+        match serde_json::to_string(reading) {
+            // Only fails while no client is connected
+            Ok(line) => {
+                let _ = self.readings.send(line);
+            }
+            Err(e) => tracing::warn!("Failed to encode shared reading: {}", e),
+        }
+    }
+}
+
+impl Drop for ShareServer {
+    fn drop(&mut self) {
+        // Client tasks end once the last broadcast sender is gone
+        self.accept_task.abort();
+    }
+}
+
+/// Write readings to one client until it disconnects
+async fn serve_client(
+    mut stream: TcpStream,
+    peer: SocketAddr,
+    mut readings: broadcast::Receiver<String>,
+) {
+    loop {
+        let line = match readings.recv().await {
+            Ok(line) => line,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::debug!("Sharing client {} fell behind, skipped {}", peer, skipped);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if let Err(e) = stream.write_all(format!("{}\n", line).as_bytes()).await {
+            tracing::info!("Heart rate sharing client {} disconnected: {}", peer, e);
+            break;
+        }
+    }
+}
+
+/// Receives the heart rate of a remote HeartIO instance, reconnecting when the link drops
+pub struct ShareClient {
+    task: JoinHandle<()>,
+}
+
+impl ShareClient {
+    /// Connect to `host:port` in the background and forward its readings to `readings`
+    pub fn start(host: String, port: u16, readings: mpsc::UnboundedSender<SharedReading>) -> Self {
+        let task = tokio::spawn(async move {
+            while !readings.is_closed() {
+                if let Err(e) = receive_readings(&host, port, &readings).await {
+                    tracing::warn!(
+                        "Heart rate sharing with {}:{} interrupted, retrying in {}s: {:#}",
+                        host,
+                        port,
+                        RECONNECT_DELAY.as_secs(),
+                        e
+                    );
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+        Self { task }
+    }
+}
+
+impl Drop for ShareClient {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Forward readings from one connection, invalid lines are skipped
+async fn receive_readings(
+    host: &str,
+    port: u16,
+    readings: &mpsc::UnboundedSender<SharedReading>,
+) -> Result<()> {
+    let stream = TcpStream::connect((host, port))
+        .await
+        .context("Failed to connect")?;
+    tracing::info!("Receiving shared heart rate from {}:{}", host, port);
+
+    let mut lines = BufReader::new(stream).lines();
+    while let Some(line) = lines.next_line().await.context("Connection lost")? {
+        match serde_json::from_str::<SharedReading>(&line) {
+            Ok(reading) => {
+                if readings.send(reading).is_err() {
+                    return Ok(());
+                }
+            }
+            Err(e) => tracing::debug!("Ignoring invalid shared reading {:?}: {}", line, e),
+        }
+    }
+    anyhow::bail!("Remote HeartIO closed the connection")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn client_receives_server_readings() {
+        // Find a free port, the client needs to know it up front
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let server = ShareServer::start(port).await.unwrap();
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let _client = ShareClient::start("127.0.0.1".to_string(), port, sender);
+
+        let reading = SharedReading {
+            bpm: 82,
+            measured_at: Utc::now(),
+        };
+        // Keep sending until the client has connected and subscribed
+        let received = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                server.send(&reading);
+                tokio::select! {
+                    received = receiver.recv() => break received,
+                    _ = tokio::time::sleep(Duration::from_millis(50)) => {}
+                }
+            }
+        })
+        .await
+        .expect("no reading received");
+        assert_eq!(received, Some(reading));
+    }
+}
@@ -50,3 +50,37 @@ pub async fn wait_for_shutdown_signal() -> Result<()> {
     
     Ok(())
 }
+
+/// Wait for SIGTSTP (Ctrl+Z in a terminal), never resolves on platforms without it
+pub async fn wait_for_pause_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use signal::unix::{signal, SignalKind};
+
+        let mut sigtstp = signal(SignalKind::from_raw(libc::SIGTSTP))?;
+        sigtstp.recv().await;
+        tracing::info!("Received SIGTSTP");
+    }
+
+    #[cfg(not(unix))]
+    std::future::pending::<()>().await;
+
+    Ok(())
+}
+
+/// Wait for SIGCONT, never resolves on platforms without it
+pub async fn wait_for_resume_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use signal::unix::{signal, SignalKind};
+
+        let mut sigcont = signal(SignalKind::from_raw(libc::SIGCONT))?;
+        sigcont.recv().await;
+        tracing::info!("Received SIGCONT");
+    }
+
+    #[cfg(not(unix))]
+    std::future::pending::<()>().await;
+
+    Ok(())
+}
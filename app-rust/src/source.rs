@@ -0,0 +1,65 @@
+// Heart rate source abstraction for HeartIO
+use anyhow::Result;
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::time::{interval, Interval};
+
+use crate::config::MonitorMode;
+use crate::heart_rate::HeartRateReading;
+
+/// A single reading produced by a heart rate source
+pub type HeartRateSample = HeartRateReading;
+
+/// Anything that produces heart rate samples, e.g. a BLE strap or the Apple Watch server
+#[async_trait]
+pub trait HeartRateSource: Send {
+    /// Wait for the next sample, an error means the source has stopped
+    async fn next_sample(&mut self) -> Result<HeartRateSample>;
+
+    /// Release the device, socket or task behind the source
+    async fn shutdown(&mut self) -> Result<()>;
+
+    /// Reset the energy expended counter of the device, if it has one
+    async fn reset_energy_expended(&mut self) -> Result<()> {
+        anyhow::bail!("this source has no energy expended counter")
+    }
+}
+
+/// Synthetic heart rate drifting between 70 and 130 BPM, for trying OSC output without a device
+pub struct SimulatedSource {
+    ticker: Interval,
+    step: u32,
+}
+
+impl SimulatedSource {
+    /// Create a simulated source producing one sample per second
+    pub fn new() -> Self {
+        Self {
+            ticker: interval(Duration::from_secs(1)),
+            step: 0,
+        }
+    }
+}
+
+impl Default for SimulatedSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl HeartRateSource for SimulatedSource {
+    async fn next_sample(&mut self) -> Result<HeartRateSample> {
+        self.ticker.tick().await;
+        self.step = self.step.wrapping_add(1);
+
+        // One slow cycle every two minutes
+        let phase = self.step as f64 / 120.0 * std::f64::consts::TAU;
+        let bpm = (100.0 + 30.0 * phase.sin()).round() as u32;
+        Ok(HeartRateSample::new(MonitorMode::Simulated, bpm))
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,410 @@
+// Statistics helpers for HeartIO
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::config::UserSex;
+
+/// Running mean and variance using Welford's online algorithm
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WelfordStats {
+    count: u64,
+    mean: f64,
+    m2: f64,
+}
+
+impl WelfordStats {
+    /// Add a sample
+    pub fn update(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Number of samples seen
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Mean of all samples, 0 when empty
+    pub fn mean(&self) -> f64 {
+        self.mean
+    }
+
+    /// Sample variance, 0 with fewer than two samples
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            0.0
+        } else {
+            self.m2 / (self.count - 1) as f64
+        }
+    }
+
+    /// Sample standard deviation
+    pub fn stddev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+}
+
+/// Samples used for the instantaneous energy rate
+const ENERGY_RATE_WINDOW: Duration = Duration::from_secs(60);
+const KJ_PER_KCAL: f64 = 4.184;
+
+/// Session energy total from devices that report cumulative energy expended
+#[derive(Debug, Clone, Default)]
+pub struct EnergyAccumulator {
+    last_kj: Option<u16>,
+    total_kj: u64,
+    /// Running total at each sample, oldest first, for the kcal/min rate
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl EnergyAccumulator {
+    /// Add a cumulative energy expended value in kJ as reported by the device
+    pub fn update(&mut self, now: Instant, cumulative_kj: u16) {
+        let delta = match self.last_kj {
+            Some(last) if cumulative_kj >= last => cumulative_kj - last,
+            // The device reset its counter, everything since the reset is new
+            Some(_) => cumulative_kj,
+            // The first value includes energy from before this session
+            None => 0,
+        };
+        self.last_kj = Some(cumulative_kj);
+        self.total_kj += u64::from(delta);
+
+        self.samples.push_back((now, self.total_kj));
+        while self
+            .samples
+            .front()
+            .is_some_and(|(time, _)| now.duration_since(*time) > ENERGY_RATE_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Whether any energy value was received
+    pub fn has_data(&self) -> bool {
+        self.last_kj.is_some()
+    }
+
+    /// Energy expended this session in kcal
+    pub fn total_kcal(&self) -> f64 {
+        self.total_kj as f64 / KJ_PER_KCAL
+    }
+
+    /// Recent energy expenditure in kcal/min, `None` until two samples are available
+    pub fn kcal_per_min(&self) -> Option<f64> {
+        let (first_time, first_total) = self.samples.front()?;
+        let (last_time, last_total) = self.samples.back()?;
+        let minutes = last_time.duration_since(*first_time).as_secs_f64() / 60.0;
+        (minutes > 0.0).then(|| (last_total - first_total) as f64 / KJ_PER_KCAL / minutes)
+    }
+}
+
+/// Longest gap between readings counted towards the calorie estimate, longer gaps are dropouts
+const CALORIE_MAX_GAP: Duration = Duration::from_secs(15);
+
+/// Body data for estimating calories from heart rate with the Keytel et al. formulas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CalorieProfile {
+    pub weight_kg: f32,
+    pub age: u32,
+    pub sex: UserSex,
+}
+
+impl CalorieProfile {
+    /// Estimated energy expenditure at a heart rate in kcal/min
+    pub fn kcal_per_min(&self, bpm: u32) -> f32 {
+        let (hr, weight, age) = (bpm as f32, self.weight_kg, self.age as f32);
+        let kj_per_kcal = KJ_PER_KCAL as f32;
+        let male = (-55.0969 + 0.6309 * hr + 0.1988 * weight + 0.2017 * age) / kj_per_kcal;
+        let female = (-20.4022 + 0.4472 * hr - 0.1263 * weight + 0.074 * age) / kj_per_kcal;
+        let rate = match self.sex {
+            UserSex::Male => male,
+            UserSex::Female => female,
+            UserSex::Other => (male + female) / 2.0,
+        };
+        // The formulas go negative at very low heart rates
+        rate.max(0.0)
+    }
+
+    /// Calories burned at a heart rate since the previous reading
+    pub fn kcal_since(&self, bpm: u32, elapsed: Option<Duration>) -> f32 {
+        match elapsed {
+            Some(elapsed) if elapsed <= CALORIE_MAX_GAP => {
+                self.kcal_per_min(bpm) * elapsed.as_secs_f32() / 60.0
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// Window for the reading interval statistics
+const INTERVAL_WINDOW: Duration = Duration::from_secs(60);
+
+/// Rolling inter-arrival times of readings, irregular intervals point to a flaky sensor
+#[derive(Debug, Clone, Default)]
+pub struct IntervalStats {
+    arrivals: VecDeque<Instant>,
+}
+
+/// Interval statistics over the last minute
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntervalSummary {
+    pub min: Duration,
+    pub median: Duration,
+    pub max: Duration,
+    /// Readings estimated missing, from gaps that span several median intervals
+    pub dropped: u32,
+}
+
+impl IntervalStats {
+    /// Record the arrival of a reading
+    pub fn record(&mut self, now: Instant) {
+        self.arrivals.push_back(now);
+        while self
+            .arrivals
+            .front()
+            .is_some_and(|time| now.duration_since(*time) > INTERVAL_WINDOW)
+        {
+            self.arrivals.pop_front();
+        }
+    }
+
+    /// Summarize the window, `None` until two readings arrived
+    pub fn summary(&self) -> Option<IntervalSummary> {
+        let mut intervals: Vec<Duration> = self
+            .arrivals
+            .iter()
+            .zip(self.arrivals.iter().skip(1))
+            .map(|(previous, next)| next.duration_since(*previous))
+            .collect();
+        if intervals.is_empty() {
+            return None;
+        }
+        intervals.sort();
+
+        let median = intervals[intervals.len() / 2];
+        let dropped = if median.is_zero() {
+            0
+        } else {
+            intervals
+                .iter()
+                .map(|interval| {
+                    let missed = (interval.as_secs_f64() / median.as_secs_f64()).round() - 1.0;
+                    missed.max(0.0) as u32
+                })
+                .sum()
+        };
+
+        Some(IntervalSummary {
+            min: intervals[0],
+            median,
+            max: intervals[intervals.len() - 1],
+            dropped,
+        })
+    }
+}
+
+/// Readings the confidence score compares a new one against
+const CONFIDENCE_WINDOW: usize = 10;
+/// Relative distance from the recent median at which a reading scores no agreement
+const CONFIDENCE_MAX_DEVIATION: f64 = 0.25;
+
+/// Rates readings 0-100 from sensor contact, arrival regularity and agreement with recent ones
+#[derive(Debug, Clone, Default)]
+pub struct ConfidenceScorer {
+    recent_bpm: VecDeque<u32>,
+    arrivals: VecDeque<Instant>,
+}
+
+impl ConfidenceScorer {
+    /// Score a reading and remember it for the next ones
+    pub fn score(&mut self, bpm: u32, sensor_contact: Option<bool>, now: Instant) -> u8 {
+        // Most sources don't report contact, only a strap saying it has none counts against
+        let contact = if sensor_contact == Some(false) { 0.0 } else { 1.0 };
+        let regularity = self.regularity(now);
+        let agreement = self.agreement(bpm);
+
+        self.recent_bpm.push_back(bpm);
+        self.arrivals.push_back(now);
+        if self.recent_bpm.len() > CONFIDENCE_WINDOW {
+            self.recent_bpm.pop_front();
+            self.arrivals.pop_front();
+        }
+        (40.0 * contact + 30.0 * regularity + 30.0 * agreement).round() as u8
+    }
+
+    /// 1 for evenly spaced readings, falling with the coefficient of variation of the intervals
+    fn regularity(&self, now: Instant) -> f64 {
+        let mut intervals = WelfordStats::default();
+        let arrivals: Vec<Instant> = self.arrivals.iter().copied().chain([now]).collect();
+        for pair in arrivals.windows(2) {
+            intervals.update(pair[1].duration_since(pair[0]).as_secs_f64());
+        }
+        // Too few intervals to judge, or readings arriving all at once
+        if intervals.count() < 3 || intervals.mean() <= 0.0 {
+            return 1.0;
+        }
+        (1.0 - intervals.stddev() / intervals.mean()).clamp(0.0, 1.0)
+    }
+
+    /// 1 at the median of the recent readings, 0 at `CONFIDENCE_MAX_DEVIATION` away from it
+    fn agreement(&self, bpm: u32) -> f64 {
+        if self.recent_bpm.len() < 3 {
+            return 1.0;
+        }
+        let mut sorted: Vec<u32> = self.recent_bpm.iter().copied().collect();
+        sorted.sort_unstable();
+        let median = sorted[sorted.len() / 2] as f64;
+        let deviation = (bpm as f64 - median).abs() / median;
+        (1.0 - deviation / CONFIDENCE_MAX_DEVIATION).clamp(0.0, 1.0)
+    }
+}
+
+impl fmt::Display for IntervalSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.2}s / {:.2}s / {:.2}s, ~{} dropped",
+            self.min.as_secs_f64(),
+            self.median.as_secs_f64(),
+            self.max.as_secs_f64(),
+            self.dropped
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confidence_drops_for_outliers_and_lost_contact() {
+        let mut scorer = ConfidenceScorer::default();
+        let start = Instant::now();
+        for second in 0..6 {
+            let score = scorer.score(80, None, start + Duration::from_secs(second));
+            assert_eq!(score, 100);
+        }
+
+        let at = |second| start + Duration::from_secs(second);
+        let mut outlier = scorer.clone();
+        assert_eq!(outlier.score(100, None, at(6)), 70);
+        let mut no_contact = scorer.clone();
+        assert_eq!(no_contact.score(80, Some(false), at(6)), 60);
+        // A reading arriving after a long gap is less regular
+        assert!(scorer.score(80, Some(true), at(15)) < 100);
+    }
+
+    #[test]
+    fn welford_empty_single_and_reset() {
+        let mut stats = WelfordStats::default();
+        assert_eq!(stats.count(), 0);
+        assert_eq!(stats.mean(), 0.0);
+        assert_eq!(stats.variance(), 0.0);
+
+        stats.update(72.0);
+        assert_eq!(stats.count(), 1);
+        assert_eq!(stats.mean(), 72.0);
+        assert_eq!(stats.stddev(), 0.0);
+
+        stats = WelfordStats::default();
+        for value in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            stats.update(value);
+        }
+        assert_eq!(stats.count(), 8);
+        assert!((stats.mean() - 5.0).abs() < 1e-9);
+        assert!((stats.variance() - 32.0 / 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn energy_counts_from_the_first_value() {
+        let start = Instant::now();
+        let at = |second| start + Duration::from_secs(second);
+        let mut energy = EnergyAccumulator::default();
+        assert!(!energy.has_data());
+        assert_eq!(energy.total_kcal(), 0.0);
+        assert_eq!(energy.kcal_per_min(), None);
+
+        // The first value is the device's total from before the session
+        energy.update(at(0), 500);
+        assert!(energy.has_data());
+        assert_eq!(energy.total_kcal(), 0.0);
+        assert_eq!(energy.kcal_per_min(), None);
+
+        energy.update(at(60), 542);
+        assert!((energy.total_kcal() - 42.0 / KJ_PER_KCAL).abs() < 1e-9);
+        assert!((energy.kcal_per_min().unwrap() - 42.0 / KJ_PER_KCAL).abs() < 1e-9);
+
+        // A counter reset on the device counts everything since the reset, the rate only
+        // looks at the last minute
+        energy.update(at(90), 10);
+        assert!((energy.total_kcal() - 52.0 / KJ_PER_KCAL).abs() < 1e-9);
+        assert!((energy.kcal_per_min().unwrap() - 20.0 / KJ_PER_KCAL).abs() < 1e-9);
+
+        energy = EnergyAccumulator::default();
+        energy.update(at(120), 60);
+        assert_eq!(energy.total_kcal(), 0.0);
+    }
+
+    #[test]
+    fn interval_summary_and_dropped_readings() {
+        let start = Instant::now();
+        let at = |second| start + Duration::from_secs(second);
+        let mut intervals = IntervalStats::default();
+        assert_eq!(intervals.summary(), None);
+        intervals.record(at(0));
+        assert_eq!(intervals.summary(), None);
+
+        for second in [1, 2, 3, 6] {
+            intervals.record(at(second));
+        }
+        let summary = intervals.summary().unwrap();
+        assert_eq!(summary.min, Duration::from_secs(1));
+        assert_eq!(summary.median, Duration::from_secs(1));
+        assert_eq!(summary.max, Duration::from_secs(3));
+        // The 3 s gap stands for two missing readings
+        assert_eq!(summary.dropped, 2);
+
+        // Everything older than the window is forgotten
+        intervals.record(at(70));
+        assert_eq!(intervals.summary(), None);
+    }
+
+    #[test]
+    fn keytel_calorie_estimate() {
+        let male = CalorieProfile {
+            weight_kg: 70.0,
+            age: 30,
+            sex: UserSex::Male,
+        };
+        // (-55.0969 + 0.6309 * 150 + 0.1988 * 70 + 0.2017 * 30) / 4.184
+        assert!((male.kcal_per_min(150) - 14.22).abs() < 0.01);
+        let other = CalorieProfile {
+            sex: UserSex::Other,
+            ..male
+        };
+        let female = CalorieProfile {
+            sex: UserSex::Female,
+            ..male
+        };
+        let mean = (male.kcal_per_min(150) + female.kcal_per_min(150)) / 2.0;
+        assert!((other.kcal_per_min(150) - mean).abs() < 0.001);
+        assert_eq!(male.kcal_per_min(20), 0.0);
+    }
+
+    #[test]
+    fn long_gaps_burn_nothing() {
+        let profile = CalorieProfile {
+            weight_kg: 70.0,
+            age: 30,
+            sex: UserSex::Male,
+        };
+        let per_second = profile.kcal_per_min(150) / 60.0;
+        assert!((profile.kcal_since(150, Some(Duration::from_secs(1))) - per_second).abs() < 1e-4);
+        assert_eq!(profile.kcal_since(150, Some(Duration::from_secs(60))), 0.0);
+        assert_eq!(profile.kcal_since(150, None), 0.0);
+    }
+}
@@ -68,17 +68,131 @@ fn register_exit_handlers() {
     }
 }
 
+/// How sleep is being prevented on Linux, so it can be undone the same way
+#[cfg(target_os = "linux")]
+enum SleepInhibit {
+    /// logind inhibitor lock, held for as long as the file descriptor stays open
+    Logind(zbus::zvariant::OwnedFd),
+    /// `systemd-inhibit` holding the lock for as long as it runs
+    SystemdInhibit(Child),
+    /// X screensaver turned off with `xset s off`
+    Xset,
+}
+
+/// One way of preventing sleep, tried in order by `SleepInhibit::acquire`
+#[cfg(target_os = "linux")]
+type InhibitMethod = (&'static str, fn() -> Result<SleepInhibit>);
+
+#[cfg(target_os = "linux")]
+impl SleepInhibit {
+    /// Try each method in turn, from the most to the least reliable
+    fn acquire() -> Result<Self> {
+        let attempts: [InhibitMethod; 3] = [
+            ("D-Bus", Self::logind),
+            ("systemd-inhibit", Self::systemd_inhibit),
+            ("xset", Self::xset),
+        ];
+        for (method, attempt) in attempts {
+            match attempt() {
+                Ok(inhibit) => {
+                    tracing::debug!("Preventing system sleep via {}", method);
+                    return Ok(inhibit);
+                }
+                Err(e) => tracing::debug!("Sleep prevention via {} failed: {:#}", method, e),
+            }
+        }
+        anyhow::bail!("D-Bus, systemd-inhibit and xset are all unavailable")
+    }
+
+    /// Ask logind for an inhibitor lock over the system bus
+    fn logind() -> Result<Self> {
+        let connection = zbus::blocking::Connection::system()
+            .context("Failed to connect to the system bus")?;
+        let reply = connection
+            .call_method(
+                Some("org.freedesktop.login1"),
+                "/org/freedesktop/login1",
+                Some("org.freedesktop.login1.Manager"),
+                "Inhibit",
+                &("idle:sleep", "HeartIO", "Monitoring heart rate", "block"),
+            )
+            .context("logind refused the inhibitor lock")?;
+        let fd = reply
+            .body()
+            .deserialize()
+            .context("Invalid inhibitor lock reply")?;
+        Ok(Self::Logind(fd))
+    }
+
+    /// Hold the lock with `systemd-inhibit`, for systems where D-Bus is not reachable directly
+    fn systemd_inhibit() -> Result<Self> {
+        // `tail --pid` exits with HeartIO, so a crash can't leave the lock behind
+        let mut child = Command::new("systemd-inhibit")
+            .args(["--what=idle", "--who=HeartIO", "--why=Monitoring"])
+            .args(["tail", "--pid", &std::process::id().to_string(), "-f", "/dev/null"])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .context("Failed to start systemd-inhibit")?;
+
+        // It exits right away when the lock can't be taken
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        if let Some(status) = child.try_wait()? {
+            anyhow::bail!("systemd-inhibit exited with {}", status);
+        }
+        Ok(Self::SystemdInhibit(child))
+    }
+
+    /// Turn the X screensaver off, the last resort outside of systemd
+    fn xset() -> Result<Self> {
+        let status = Command::new("xset")
+            .args(["s", "off"])
+            .status()
+            .context("Failed to run xset")?;
+        anyhow::ensure!(status.success(), "xset exited with {}", status);
+        Ok(Self::Xset)
+    }
+
+    /// Undo the method that was used
+    fn release(self) {
+        match self {
+            // Closing the file descriptor releases the lock
+            Self::Logind(fd) => drop(fd),
+            Self::SystemdInhibit(mut child) => {
+                if let Err(e) = child.kill() {
+                    tracing::warn!("Failed to stop systemd-inhibit: {}", e);
+                }
+                let _ = child.wait();
+            }
+            Self::Xset => {
+                if let Err(e) = Command::new("xset").args(["s", "on"]).status() {
+                    tracing::warn!("Failed to turn the X screensaver back on: {}", e);
+                }
+            }
+        }
+        tracing::info!("System sleep prevention deactivated");
+    }
+}
+
 pub struct SystemUtils {
+    #[cfg(target_os = "macos")]
+    display_assertion: Option<iokit::DisplaySleepAssertion>,
     #[cfg(target_os = "macos")]
     caffeinate_process: Option<Child>,
+    #[cfg(target_os = "linux")]
+    sleep_inhibit: Option<SleepInhibit>,
 }
 
 impl SystemUtils {
     /// Create a new SystemUtils instance
     pub fn new() -> Self {
         Self {
+            #[cfg(target_os = "macos")]
+            display_assertion: None,
             #[cfg(target_os = "macos")]
             caffeinate_process: None,
+            #[cfg(target_os = "linux")]
+            sleep_inhibit: None,
         }
     }
 
@@ -86,7 +200,23 @@ impl SystemUtils {
     pub fn keep_system_awake(&mut self) -> Result<()> {
         #[cfg(target_os = "macos")]
         {
+            if self.display_assertion.is_some() {
+                tracing::debug!("Display sleep assertion already held");
+                return Ok(());
+            }
             if self.caffeinate_process.is_none() {
+                // Released by the system when HeartIO exits, no cleanup handlers needed
+                match iokit::DisplaySleepAssertion::create("HeartIO is monitoring heart rate") {
+                    Ok(assertion) => {
+                        tracing::info!("System sleep prevention activated (IOKit assertion)");
+                        self.display_assertion = Some(assertion);
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        tracing::warn!("IOKit sleep assertion failed, using caffeinate: {:#}", e)
+                    }
+                }
+
                 // Register exit handlers before starting caffeinate
                 register_exit_handlers();
                 
@@ -107,7 +237,16 @@ impl SystemUtils {
             }
         }
 
-        #[cfg(not(target_os = "macos"))]
+        #[cfg(target_os = "linux")]
+        {
+            if self.sleep_inhibit.is_none() {
+                self.sleep_inhibit = Some(SleepInhibit::acquire()?);
+            } else {
+                tracing::debug!("System sleep prevention already active");
+            }
+        }
+
+        #[cfg(not(any(target_os = "macos", target_os = "linux")))]
         {
             tracing::debug!("System sleep prevention not implemented for this platform");
         }
@@ -119,6 +258,11 @@ impl SystemUtils {
     pub fn allow_system_sleep(&mut self) -> Result<()> {
         #[cfg(target_os = "macos")]
         {
+            if self.display_assertion.take().is_some() {
+                tracing::info!("System sleep prevention deactivated (IOKit assertion released)");
+                return Ok(());
+            }
+
             let pid = CAFFEINATE_PID.load(Ordering::Relaxed);
             
             if let Some(mut child) = self.caffeinate_process.take() {
@@ -166,9 +310,11 @@ impl SystemUtils {
             }
         }
 
-        #[cfg(not(target_os = "macos"))]
+        #[cfg(target_os = "linux")]
         {
-            // No action needed on non-macOS platforms
+            if let Some(inhibit) = self.sleep_inhibit.take() {
+                inhibit.release();
+            }
         }
 
         Ok(())
@@ -179,6 +325,30 @@ impl SystemUtils {
         cleanup_caffeinate();
     }
 
+    /// Play the system alert sound without blocking
+    pub fn play_alert_sound() {
+        #[cfg(target_os = "windows")]
+        unsafe {
+            winapi::um::winuser::MessageBeep(winapi::um::winuser::MB_ICONEXCLAMATION);
+        }
+
+        #[cfg(target_os = "macos")]
+        std::thread::spawn(|| {
+            let sound = "/System/Library/Sounds/Sosumi.aiff";
+            if let Err(e) = Command::new("afplay").arg(sound).status() {
+                tracing::debug!("Failed to play alert sound: {}", e);
+            }
+        });
+
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            // Terminal bell, Linux has no common sound API
+            use std::io::Write;
+            print!("\x07");
+            let _ = std::io::stdout().flush();
+        }
+    }
+
     /// Get platform information
     pub fn get_platform_info() -> String {
         let os = std::env::consts::OS;
@@ -194,3 +364,131 @@ impl Drop for SystemUtils {
         }
     }
 }
+
+/// Native power assertions, so no external process has to be tracked and cleaned up
+#[cfg(target_os = "macos")]
+mod iokit {
+    use anyhow::Result;
+    use core_foundation::base::TCFType;
+    use core_foundation::string::{CFString, CFStringRef};
+
+    type IOPMAssertionID = u32;
+    type IOReturn = i32;
+
+    const IO_RETURN_SUCCESS: IOReturn = 0;
+    const IOPM_ASSERTION_LEVEL_ON: u32 = 255;
+    /// Value of `kIOPMAssertionTypeNoDisplaySleep`, keeps the display and the system awake
+    const NO_DISPLAY_SLEEP_ASSERTION: &str = "NoDisplaySleepAssertion";
+
+    #[link(name = "IOKit", kind = "framework")]
+    extern "C" {
+        fn IOPMAssertionCreateWithName(
+            assertion_type: CFStringRef,
+            level: u32,
+            name: CFStringRef,
+            id: *mut IOPMAssertionID,
+        ) -> IOReturn;
+        fn IOPMAssertionRelease(id: IOPMAssertionID) -> IOReturn;
+    }
+
+    /// Held `NoDisplaySleepAssertion`, released when dropped
+    pub struct DisplaySleepAssertion(IOPMAssertionID);
+
+    impl DisplaySleepAssertion {
+        /// Take the assertion, `reason` shows up in `pmset -g assertions`
+        pub fn create(reason: &str) -> Result<Self> {
+            let assertion_type = CFString::from_static_string(NO_DISPLAY_SLEEP_ASSERTION);
+            let name = CFString::new(reason);
+            let mut id: IOPMAssertionID = 0;
+            // SAFETY: both strings outlive the call and `id` is a valid out pointer
+            let result = unsafe {
+                IOPMAssertionCreateWithName(
+                    assertion_type.as_concrete_TypeRef(),
+                    IOPM_ASSERTION_LEVEL_ON,
+                    name.as_concrete_TypeRef(),
+                    &mut id,
+                )
+            };
+            anyhow::ensure!(
+                result == IO_RETURN_SUCCESS,
+                "IOPMAssertionCreateWithName returned {:#x}",
+                result
+            );
+            Ok(Self(id))
+        }
+    }
+
+    impl Drop for DisplaySleepAssertion {
+        fn drop(&mut self) {
+            // SAFETY: the id came from a successful IOPMAssertionCreateWithName
+            let result = unsafe { IOPMAssertionRelease(self.0) };
+            if result != IO_RETURN_SUCCESS {
+                tracing::warn!("IOPMAssertionRelease returned {:#x}", result);
+            }
+        }
+    }
+}
+
+/// COM setup for btleplug, whose WinRT calls fail with HRESULT errors on threads without COM
+#[cfg(target_os = "windows")]
+pub mod windows {
+    use ::windows::Win32::Foundation::{RPC_E_CHANGED_MODE, S_FALSE, S_OK};
+    use ::windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_MULTITHREADED};
+    use std::sync::{mpsc, Once};
+
+    /// Balances a successful `CoInitializeEx` with `CoUninitialize` when dropped
+    pub struct ComGuard;
+
+    impl Drop for ComGuard {
+        fn drop(&mut self) {
+            // SAFETY: only created by `enter_mta` and dropped on the COM thread that created it
+            unsafe { CoUninitialize() };
+        }
+    }
+
+    static COM_INIT: Once = Once::new();
+
+    /// Start the process-wide multithreaded apartment, at most once. A dedicated thread keeps
+    /// it alive, so the tokio workers btleplug runs on join it implicitly wherever a task moves.
+    pub fn init_com() {
+        COM_INIT.call_once(|| {
+            let (ready_sender, ready) = mpsc::channel();
+            let spawned = std::thread::Builder::new()
+                .name("heartio-com".to_string())
+                .spawn(move || {
+                    let guard = enter_mta();
+                    let _ = ready_sender.send(());
+                    // The apartment only exists while a thread is in it
+                    if guard.is_some() {
+                        loop {
+                            std::thread::park();
+                        }
+                    }
+                });
+            match spawned {
+                Ok(_) => {
+                    let _ = ready.recv();
+                }
+                Err(e) => tracing::warn!("Failed to start the COM thread: {}", e),
+            }
+        });
+    }
+
+    /// Join the multithreaded apartment on the calling thread
+    fn enter_mta() -> Option<ComGuard> {
+        // SAFETY: the reserved pointer must be None
+        let result = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) };
+        if result == S_OK {
+            Some(ComGuard)
+        } else if result == S_FALSE {
+            tracing::warn!("COM was already initialized on this thread");
+            Some(ComGuard)
+        } else if result == RPC_E_CHANGED_MODE {
+            tracing::warn!("COM was already initialized with another threading model");
+            None
+        } else {
+            tracing::warn!("Failed to initialize COM: HRESULT {:#010x}", result.0);
+            None
+        }
+    }
+}
@@ -0,0 +1,303 @@
+// System tray icon for HeartIO
+
+use crate::heart_rate::MonitorCommand;
+use tokio::sync::mpsc::UnboundedSender;
+
+pub use platform::Tray;
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+mod platform {
+    use crate::heart_rate::MonitorCommand;
+    use anyhow::Result;
+    use raw_window_handle::RawWindowHandle;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::mpsc::UnboundedSender;
+    use tray_icon::menu::{Menu, MenuEvent, MenuId, MenuItem, PredefinedMenuItem};
+    use tray_icon::{Icon, MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+
+    /// Side length of the generated tray icon in pixels
+    const ICON_SIZE: u32 = 32;
+
+    /// Action picked from the tray icon or its menu
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum TrayAction {
+        Show,
+        TogglePause,
+        Reset,
+        Quit,
+    }
+
+    /// Handle of the eframe window
+    #[derive(Clone, Copy)]
+    struct Window(RawWindowHandle);
+
+    // SAFETY: tray-icon calls its handlers on the thread running the event loop, which owns the
+    // window, the handle is never used anywhere else
+    unsafe impl Send for Window {}
+    unsafe impl Sync for Window {}
+
+    /// Carries out tray actions right in the tray-icon handlers. eframe doesn't run frames for a
+    /// hidden window, so nothing may wait for `update()` until the window is back on screen.
+    #[derive(Clone)]
+    struct Handler {
+        ctx: egui::Context,
+        window: Option<Window>,
+        commands: UnboundedSender<MonitorCommand>,
+        paused: Arc<AtomicBool>,
+        reset_requested: Arc<AtomicBool>,
+    }
+
+    impl Handler {
+        fn handle(&self, action: TrayAction) {
+            match action {
+                TrayAction::Show => self.show_window(),
+                TrayAction::TogglePause => {
+                    let paused = !self.paused.fetch_xor(true, Ordering::Relaxed);
+                    let _ = self.commands.send(MonitorCommand::SetPaused(paused));
+                }
+                TrayAction::Reset => {
+                    let _ = self.commands.send(MonitorCommand::ResetSession);
+                    self.reset_requested.store(true, Ordering::Relaxed);
+                }
+                TrayAction::Quit => {
+                    // The close request is handled by the next frame, which needs a visible window
+                    self.show_window();
+                    self.ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            }
+            self.ctx.request_repaint();
+        }
+
+        fn show_window(&self) {
+            if let Some(Window(handle)) = self.window {
+                show_native_window(handle);
+            }
+            // Keep egui's view of the window in sync, and the only way without a handle
+            let ctx = &self.ctx;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        }
+    }
+
+    /// Restore, show and focus the window through the OS
+    #[cfg(target_os = "windows")]
+    fn show_native_window(handle: RawWindowHandle) {
+        use windows::Win32::Foundation::HWND;
+        use windows::Win32::UI::WindowsAndMessaging::{
+            IsIconic, SetForegroundWindow, ShowWindow, SW_RESTORE, SW_SHOW,
+        };
+
+        let RawWindowHandle::Win32(handle) = handle else {
+            return;
+        };
+        let hwnd = HWND(handle.hwnd.get() as *mut std::ffi::c_void);
+        // SAFETY: the handle belongs to the eframe window, which lives as long as the event loop
+        unsafe {
+            let command = if IsIconic(hwnd).as_bool() {
+                SW_RESTORE
+            } else {
+                SW_SHOW
+            };
+            let _ = ShowWindow(hwnd, command);
+            let _ = SetForegroundWindow(hwnd);
+        }
+    }
+
+    /// Restore, show and focus the window through the OS
+    #[cfg(target_os = "macos")]
+    fn show_native_window(handle: RawWindowHandle) {
+        use objc2_app_kit::{NSApplication, NSView};
+        use objc2_foundation::MainThreadMarker;
+
+        let RawWindowHandle::AppKit(handle) = handle else {
+            return;
+        };
+        let Some(mtm) = MainThreadMarker::new() else {
+            return;
+        };
+        // SAFETY: the view belongs to the eframe window, which lives as long as the event loop
+        let view: &NSView = unsafe { handle.ns_view.cast().as_ref() };
+        if let Some(window) = view.window() {
+            if window.isMiniaturized() {
+                // SAFETY: called on the main thread with no sender, as AppKit expects
+                unsafe { window.deminiaturize(None) };
+            }
+            window.makeKeyAndOrderFront(None);
+        }
+        #[allow(deprecated)]
+        NSApplication::sharedApplication(mtm).activateIgnoringOtherApps(true);
+    }
+
+    /// Tray icon showing the current BPM with Show, Pause/Resume, Reset and Quit items
+    pub struct Tray {
+        icon: TrayIcon,
+        pause_item: MenuItem,
+        heart_rate: Option<Option<u32>>,
+        /// Pause state the menu item shows
+        menu_paused: bool,
+        paused: Arc<AtomicBool>,
+        reset_requested: Arc<AtomicBool>,
+    }
+
+    impl Tray {
+        /// Create the tray icon, must be called on the GUI thread once the event loop runs
+        pub fn new(
+            ctx: &egui::Context,
+            window: Option<RawWindowHandle>,
+            commands: UnboundedSender<MonitorCommand>,
+        ) -> Result<Self> {
+            let show_item = MenuItem::new("Show HeartIO", true, None);
+            let pause_item = MenuItem::new("Pause", true, None);
+            let reset_item = MenuItem::new("Reset Session", true, None);
+            let quit_item = MenuItem::new("Quit", true, None);
+            let menu = Menu::with_items(&[
+                &show_item,
+                &PredefinedMenuItem::separator(),
+                &pause_item,
+                &reset_item,
+                &PredefinedMenuItem::separator(),
+                &quit_item,
+            ])?;
+
+            let icon = TrayIconBuilder::new()
+                .with_menu(Box::new(menu))
+                .with_tooltip("HeartIO")
+                .with_icon(heart_icon()?)
+                .build()?;
+
+            let handler = Handler {
+                ctx: ctx.clone(),
+                window: window.map(Window),
+                commands,
+                paused: Arc::new(AtomicBool::new(false)),
+                reset_requested: Arc::new(AtomicBool::new(false)),
+            };
+            let items: [(MenuId, TrayAction); 4] = [
+                (show_item.id().clone(), TrayAction::Show),
+                (pause_item.id().clone(), TrayAction::TogglePause),
+                (reset_item.id().clone(), TrayAction::Reset),
+                (quit_item.id().clone(), TrayAction::Quit),
+            ];
+            let menu_handler = handler.clone();
+            MenuEvent::set_event_handler(Some(move |event: MenuEvent| {
+                if let Some((_, action)) = items.iter().find(|(id, _)| *id == event.id) {
+                    menu_handler.handle(*action);
+                }
+            }));
+            let icon_handler = handler.clone();
+            TrayIconEvent::set_event_handler(Some(move |event: TrayIconEvent| {
+                if let TrayIconEvent::Click {
+                    button: MouseButton::Left,
+                    button_state: MouseButtonState::Up,
+                    ..
+                } = event
+                {
+                    icon_handler.handle(TrayAction::Show);
+                }
+            }));
+
+            Ok(Self {
+                icon,
+                pause_item,
+                heart_rate: None,
+                menu_paused: false,
+                paused: handler.paused,
+                reset_requested: handler.reset_requested,
+            })
+        }
+
+        /// Show the current BPM in the tooltip
+        pub fn set_heart_rate(&mut self, heart_rate: Option<u32>) {
+            if self.heart_rate == Some(heart_rate) {
+                return;
+            }
+            self.heart_rate = Some(heart_rate);
+
+            let tooltip = match heart_rate {
+                Some(bpm) => format!("HeartIO - {} BPM", bpm),
+                None => "HeartIO - no heart rate".to_string(),
+            };
+            if let Err(e) = self.icon.set_tooltip(Some(tooltip)) {
+                tracing::debug!("Failed to update tray tooltip: {}", e);
+            }
+        }
+
+        /// Switch the menu between Pause and Resume
+        pub fn set_paused(&mut self, paused: bool) {
+            if self.menu_paused != paused {
+                self.menu_paused = paused;
+                self.paused.store(paused, Ordering::Relaxed);
+                self.pause_item
+                    .set_text(if paused { "Resume" } else { "Pause" });
+            }
+        }
+
+        /// Whether Reset Session was picked since the last call, the monitor is already reset
+        pub fn take_reset_request(&self) -> bool {
+            self.reset_requested.swap(false, Ordering::Relaxed)
+        }
+    }
+
+    /// Draw a crimson heart, HeartIO ships no image files
+    fn heart_icon() -> Result<Icon> {
+        let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+        for row in 0..ICON_SIZE {
+            for column in 0..ICON_SIZE {
+                // Inside of the heart curve (x² + y² - 1)³ - x²y³ = 0, scaled to fit the icon
+                let x = (column as f32 + 0.5) / ICON_SIZE as f32 * 2.6 - 1.3;
+                let y = 1.35 - (row as f32 + 0.5) / ICON_SIZE as f32 * 2.6;
+                let inside = (x * x + y * y - 1.0).powi(3) - x * x * y.powi(3) <= 0.0;
+                rgba.extend_from_slice(if inside {
+                    &[220, 20, 60, 255]
+                } else {
+                    &[0, 0, 0, 0]
+                });
+            }
+        }
+        Ok(Icon::from_rgba(rgba, ICON_SIZE, ICON_SIZE)?)
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    use crate::heart_rate::MonitorCommand;
+    use anyhow::Result;
+    use raw_window_handle::RawWindowHandle;
+    use tokio::sync::mpsc::UnboundedSender;
+
+    /// Tray icons need a GTK main loop on Linux, which eframe doesn't run
+    pub struct Tray;
+
+    impl Tray {
+        pub fn new(
+            _ctx: &egui::Context,
+            _window: Option<RawWindowHandle>,
+            _commands: UnboundedSender<MonitorCommand>,
+        ) -> Result<Self> {
+            anyhow::bail!("not supported on this platform")
+        }
+
+        pub fn set_heart_rate(&mut self, _heart_rate: Option<u32>) {}
+
+        pub fn set_paused(&mut self, _paused: bool) {}
+
+        pub fn take_reset_request(&self) -> bool {
+            false
+        }
+    }
+}
+
+/// Create the tray icon, logging why when it is unavailable
+pub fn create(
+    cc: &eframe::CreationContext,
+    commands: UnboundedSender<MonitorCommand>,
+) -> Option<Tray> {
+    use raw_window_handle::HasWindowHandle;
+
+    let window = cc.window_handle().ok().map(|handle| handle.as_raw());
+    Tray::new(&cc.egui_ctx, window, commands)
+        .inspect_err(|e| tracing::info!("Tray icon unavailable: {}", e))
+        .ok()
+}
@@ -0,0 +1,125 @@
+// Startup check for newer HeartIO releases
+use anyhow::{Context, Result};
+use semver::Version;
+use serde::Deserialize;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use crate::gui::{LogEntry, LogLevel};
+
+/// The whole check is given up after this long
+const UPDATE_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Release pages on GitHub, for the download link
+const RELEASES_PAGE_URL: &str = "https://github.com/xn-sakina/vrchat-heartio/releases";
+
+/// The part of the GitHub release response the check needs
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    html_url: Option<String>,
+}
+
+/// Look for a newer release in the background, only logging when there is one
+pub fn spawn_check(url: String, log_sender: mpsc::Sender<LogEntry>) {
+    tokio::spawn(async move {
+        let release = match fetch_latest_release(&url).await {
+            Ok(release) => release,
+            Err(e) => {
+                tracing::debug!("Update check failed: {:#}", e);
+                return;
+            }
+        };
+
+        let current = env!("CARGO_PKG_VERSION");
+        let Some(latest) = newer_version(&release.tag_name, current) else {
+            tracing::debug!("HeartIO {} is up to date (latest {})", current, release.tag_name);
+            return;
+        };
+        let changelog = release
+            .html_url
+            .unwrap_or_else(|| format!("{}/tag/{}", RELEASES_PAGE_URL, release.tag_name));
+        let _ = log_sender.send(LogEntry {
+            timestamp: chrono::Local::now(),
+            level: LogLevel::Warn,
+            message: format!(
+                "⬆ HeartIO {} is available, you are running {}. \
+                 Download: {}/latest, changelog: {}",
+                latest, current, RELEASES_PAGE_URL, changelog
+            ),
+        });
+    });
+}
+
+/// Version of the release tag if it is newer than `current`, tags may start with a `v`
+fn newer_version(tag: &str, current: &str) -> Option<Version> {
+    let latest = Version::parse(tag.trim_start_matches(|c: char| !c.is_ascii_digit())).ok()?;
+    let current = Version::parse(current).ok()?;
+    (latest > current).then_some(latest)
+}
+
+/// Fetch and parse the latest release
+async fn fetch_latest_release(url: &str) -> Result<Release> {
+    let client = reqwest::Client::builder()
+        .timeout(UPDATE_CHECK_TIMEOUT)
+        .user_agent(concat!("HeartIO/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .context("Failed to create the update check client")?;
+    client
+        .get(url)
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .send()
+        .await
+        .context("Failed to fetch the latest release")?
+        .error_for_status()
+        .context("Unexpected update check response")?
+        .json()
+        .await
+        .context("Failed to parse the release response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn compares_release_tags_with_semver() {
+        assert_eq!(newer_version("v0.7.0", "0.6.0"), Some(Version::new(0, 7, 0)));
+        assert_eq!(newer_version("0.6.1", "0.6.0"), Some(Version::new(0, 6, 1)));
+        assert_eq!(newer_version("v0.6.0", "0.6.0"), None);
+        assert_eq!(newer_version("v0.5.9", "0.6.0"), None);
+        assert_eq!(newer_version("v0.6.0-beta.1", "0.6.0"), None);
+        assert_eq!(newer_version("nightly", "0.6.0"), None);
+    }
+
+    #[tokio::test]
+    async fn fetches_release_over_http() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let read = stream.read(&mut request).unwrap();
+            let body = r#"{"tag_name":"v9.9.9","html_url":"https://example.com/v9.9.9"}"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+            String::from_utf8_lossy(&request[..read]).into_owned()
+        });
+
+        let release = fetch_latest_release(&format!("http://127.0.0.1:{}/releases/latest", port))
+            .await
+            .unwrap();
+        assert_eq!(release.tag_name, "v9.9.9");
+        assert_eq!(release.html_url.as_deref(), Some("https://example.com/v9.9.9"));
+
+        let request = server.join().unwrap().to_ascii_lowercase();
+        assert!(request.starts_with("get /releases/latest http/1.1\r\n"));
+        assert!(request.contains(&format!("host: 127.0.0.1:{}\r\n", port)));
+    }
+}
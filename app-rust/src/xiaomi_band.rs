@@ -1,48 +1,113 @@
 // Xiaomi Band heart rate monitoring via BLE advertisements
 use anyhow::{Context, Result};
-use btleplug::api::{Central, Manager as _, ScanFilter};
-use btleplug::platform::{Adapter, Manager};
-use futures::stream::StreamExt;
+use async_trait::async_trait;
+use btleplug::api::{Central, CentralEvent, ScanFilter};
+use btleplug::platform::Adapter;
+use futures::stream::{Stream, StreamExt};
 use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
-use tokio::sync::mpsc;
-use tokio::time::sleep;
+
+use crate::bluetooth::{classify_scan_error, ensure_bluetooth_authorized, select_adapter};
+use crate::config::{BpmRange, MonitorMode};
+use crate::source::{HeartRateSample, HeartRateSource};
+
+type EventStream = Pin<Box<dyn Stream<Item = CentralEvent> + Send>>;
+type ScanRestartCallback = Box<dyn Fn(Duration, u32) + Send + Sync>;
+
+/// How often stale `last_seen` entries are pruned
+const LAST_SEEN_CLEANUP_PERIOD: Duration = Duration::from_secs(60);
+/// Devices not seen for this long are dropped from `last_seen`
+const LAST_SEEN_MAX_AGE: Duration = Duration::from_secs(120);
+
+/// Position and encoding of the heart rate in the manufacturer data, firmware differs by region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartRateField {
+    pub offset: usize,
+    /// 1 or 2 bytes
+    pub width: usize,
+    pub big_endian: bool,
+}
+
+impl Default for HeartRateField {
+    /// A single byte at index 3, as sent by the Xiaomi Smart Band 7
+    fn default() -> Self {
+        Self {
+            offset: 3,
+            width: 1,
+            big_endian: false,
+        }
+    }
+}
+
+impl HeartRateField {
+    /// Read the heart rate, `None` if the data is too short
+    pub fn read(&self, data: &[u8]) -> Option<u32> {
+        let bytes = data.get(self.offset..self.offset + self.width)?;
+        Some(match (bytes, self.big_endian) {
+            ([value], _) => *value as u32,
+            ([first, second], false) => u16::from_le_bytes([*first, *second]) as u32,
+            ([first, second], true) => u16::from_be_bytes([*first, *second]) as u32,
+            _ => return None,
+        })
+    }
+}
 
 /// Xiaomi Band advertisement monitor for heart rate data
 pub struct XiaomiBandMonitor {
     adapter: Adapter,
     last_seen: HashMap<String, Instant>,
-    heart_rate_sender: mpsc::UnboundedSender<u32>,
-    running: bool,
+    last_cleanup: Instant,
+    /// Only wrapped to make the monitor Sync, always accessed through `get_mut`
+    events: Mutex<Option<EventStream>>,
     device_addr: Option<String>,
+    bpm_range: BpmRange,
+    heart_rate_field: HeartRateField,
+    /// Restart the scan after this long without any advertisement, `None` to wait forever
+    scan_watchdog: Option<Duration>,
+    scan_restarts: u32,
+    scan_restart_callback: Option<ScanRestartCallback>,
 }
 
 impl XiaomiBandMonitor {
     /// Create a new Xiaomi Band monitor
-    pub async fn new(heart_rate_sender: mpsc::UnboundedSender<u32>) -> Result<Self> {
-        let manager = Manager::new()
-            .await
-            .context("Failed to create Bluetooth manager")?;
+    pub async fn new(
+        bpm_range: BpmRange,
+        adapter_selector: Option<&str>,
+        heart_rate_field: HeartRateField,
+    ) -> Result<Self> {
+        ensure_bluetooth_authorized()?;
 
-        let adapters = manager
-            .adapters()
-            .await
-            .context("Failed to get Bluetooth adapters")?;
-
-        let adapter = adapters
-            .into_iter()
-            .next()
-            .context("No Bluetooth adapter found")?;
+        let adapter = select_adapter(adapter_selector).await?;
 
         Ok(Self {
             adapter,
             last_seen: HashMap::new(),
-            heart_rate_sender,
-            running: false,
+            last_cleanup: Instant::now(),
+            events: Mutex::new(None),
             device_addr: None,
+            bpm_range,
+            heart_rate_field,
+            scan_watchdog: None,
+            scan_restarts: 0,
+            scan_restart_callback: None,
         })
     }
 
+    /// Restart the scan when advertisements stop arriving for `timeout`, zero disables it
+    pub fn set_scan_watchdog(&mut self, timeout: Duration) {
+        self.scan_watchdog = (!timeout.is_zero()).then_some(timeout);
+    }
+
+    /// Register a callback that receives the silence and restart count on every watchdog restart
+    pub fn on_scan_restart<F>(&mut self, callback: F)
+    where
+        F: Fn(Duration, u32) + Send + Sync + 'static,
+    {
+        self.scan_restart_callback = Some(Box::new(callback));
+    }
+
     /// Check if Bluetooth is available
     pub async fn check_bluetooth_availability(&self) -> Result<bool> {
         match self.adapter.start_scan(ScanFilter::default()).await {
@@ -53,12 +118,15 @@ impl XiaomiBandMonitor {
             }
             Err(e) => {
                 tracing::error!("Bluetooth error: {}", e);
-                Ok(false)
+                match classify_scan_error(&e) {
+                    Some(scan_error) => Err(scan_error.into()),
+                    None => Ok(false),
+                }
             }
         }
     }
 
-    /// Start monitoring for Xiaomi Band advertisements
+    /// Start scanning for Xiaomi Band advertisements
     pub async fn start_monitoring(&mut self) -> Result<()> {
         if !self.check_bluetooth_availability().await? {
             return Err(anyhow::anyhow!(
@@ -67,7 +135,6 @@ impl XiaomiBandMonitor {
         }
 
         tracing::info!("Starting Xiaomi Band advertisement monitoring...");
-        self.running = true;
 
         // Start scanning for BLE advertisements
         self.adapter
@@ -77,104 +144,153 @@ impl XiaomiBandMonitor {
 
         tracing::info!("Scanner started. Waiting for Xiaomi band advertisements...");
 
-        // Get the event stream
-        let mut events = self.adapter.events().await?;
-
-        // Process advertisements
-        while self.running {
-            tokio::select! {
-                event = events.next() => {
-                    if let Some(event) = event {
-                        if self.device_addr.is_some() {
-                            let addr = self.device_addr.as_ref().unwrap();
-                            if let btleplug::api::CentralEvent::ManufacturerDataAdvertisement { id, manufacturer_data } = &event {
-                                if id.to_string() == *addr {
-                                    // send bpm
-                                    self.send_bpm(&manufacturer_data, id.to_string().as_str()).await;
-                                }
-                            }
-                        } else {
-                            if let btleplug::api::CentralEvent::DeviceUpdated(id) = event {
-                                if let Ok(peripheral) = self.adapter.peripheral(&id).await {
-                                    self.handle_advertisement(&peripheral).await;
-                                }
-                            }
-                        }
+        let events = self.adapter.events().await?;
+        self.events = Mutex::new(Some(events));
+        Ok(())
+    }
 
-                    }
-                }
-                _ = sleep(Duration::from_millis(100)) => {
-                    // Continue processing
-                }
+    /// Wait for the next advertisement event, restarting the scan whenever the watchdog fires
+    async fn next_event_watched(&mut self) -> Result<CentralEvent> {
+        let Some(watchdog) = self.scan_watchdog else {
+            return self.next_event().await;
+        };
+        loop {
+            // Some platforms silently stop delivering advertisements after a while
+            match tokio::time::timeout(watchdog, self.next_event()).await {
+                Ok(event) => return event,
+                Err(_) => self.restart_scan(watchdog).await?,
             }
         }
+    }
 
-        // Stop scanning
-        let _ = self.adapter.stop_scan().await;
-        tracing::info!("Xiaomi Band monitoring stopped");
+    /// Stop and start the scan, with a fresh event stream
+    async fn restart_scan(&mut self, silence: Duration) -> Result<()> {
+        self.scan_restarts += 1;
+        tracing::warn!(
+            "No Bluetooth advertisements for {}s, restarting the Xiaomi Band scan (restart {})",
+            silence.as_secs(),
+            self.scan_restarts
+        );
+        if let Some(callback) = &self.scan_restart_callback {
+            callback(silence, self.scan_restarts);
+        }
+        if let Err(e) = self.adapter.stop_scan().await {
+            tracing::debug!("Error stopping the stalled scan: {}", e);
+        }
+        self.adapter
+            .start_scan(ScanFilter::default())
+            .await
+            .context("Failed to restart BLE scan")?;
+        let events = self.adapter.events().await?;
+        self.events = Mutex::new(Some(events));
         Ok(())
     }
 
-    /// Handle a BLE advertisement
-    async fn handle_advertisement(&mut self, peripheral: &impl btleplug::api::Peripheral) {
+    /// Wait for the next advertisement event
+    async fn next_event(&mut self) -> Result<CentralEvent> {
+        let events = self
+            .events
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_mut()
+            .context("Xiaomi Band monitoring not started")?;
+        events
+            .next()
+            .await
+            .context("Bluetooth advertisement stream ended")
+    }
+
+    /// Handle a BLE advertisement, returning its heart rate if it came from a Xiaomi Band
+    async fn handle_advertisement(
+        &mut self,
+        peripheral: &impl btleplug::api::Peripheral,
+    ) -> Option<u32> {
         let now = Instant::now();
         let addr = peripheral.address().to_string();
 
         // Rate limiting - only process each device once per second
         if let Some(last_time) = self.last_seen.get(&addr) {
             if now.duration_since(*last_time) < Duration::from_secs(1) {
-                return;
+                return None;
             }
         }
         self.last_seen.insert(addr.clone(), now);
 
         // Get device properties
-        if let Ok(properties) = peripheral.properties().await {
-            if let Some(properties) = properties {
-                let name = properties.local_name.unwrap_or_default();
-
-                // Check if this is a Xiaomi Smart Band
-                if name.contains("Xiaomi Smart Band") {
-                    // Get manufacturer data
-                    let manufacturer_data = properties.manufacturer_data;
-                    // send heart rate data if available
-                    self.send_bpm(&manufacturer_data, addr.as_str()).await;
-                }
-            }
+        let properties = peripheral.properties().await.ok()??;
+        let name = properties.local_name.unwrap_or_default();
+
+        // Check if this is a Xiaomi Smart Band
+        if !name.contains("Xiaomi Smart Band") {
+            return None;
         }
+        self.extract_bpm(&properties.manufacturer_data, &addr)
     }
 
-    pub async fn send_bpm(&mut self, manufacturer_data: &HashMap<u16, Vec<u8>>, addr: &str) {
-        for (_, value) in manufacturer_data.iter() {
-            if value.len() >= 4 {
-                let heart_rate = value[3] as u32;
-                if heart_rate > 0 && heart_rate < 300 {
-                    // save device address if not already set
-                    if self.device_addr.is_none() {
-                        self.device_addr = Some(addr.to_string());
-                        tracing::info!("Detected Xiaomi Band at address: {}", addr);
-                    }
+    /// Forget devices not seen recently, so busy places don't grow `last_seen` without bound
+    fn clean_stale_entries(&mut self) {
+        let now = Instant::now();
+        if now.duration_since(self.last_cleanup) < LAST_SEEN_CLEANUP_PERIOD {
+            return;
+        }
+        self.last_cleanup = now;
 
-                    tracing::info!("[{}] Received heart rate: {} bpm", addr, heart_rate);
+        let before = self.last_seen.len();
+        self.last_seen.retain(|_, seen| now.duration_since(*seen) < LAST_SEEN_MAX_AGE);
+        tracing::debug!(
+            "Pruned {} stale devices from the advertisement cache, {} remaining",
+            before - self.last_seen.len(),
+            self.last_seen.len()
+        );
+    }
 
-                    // Send heart rate to the channel
-                    if let Err(e) = self.heart_rate_sender.send(heart_rate) {
-                        tracing::error!("Failed to send heart rate: {}", e);
-                    }
-                }
-            } else {
-                tracing::debug!("[{}] Manufacturer data too short: {:?}", addr, value);
-            }
-        }
+    /// Get the first valid heart rate from the manufacturer data of an advertisement
+    fn extract_bpm(
+        &mut self,
+        manufacturer_data: &HashMap<u16, Vec<u8>>,
+        addr: &str,
+    ) -> Option<u32> {
         if manufacturer_data.is_empty() {
             tracing::debug!("[{}] No manufacturer data in advertisement", addr);
         }
+        for (company_id, value) in manufacturer_data {
+            // Raw bytes help to find the heart rate field of other band models
+            tracing::debug!("[{}] Manufacturer data {:#06x}: {:02x?}", addr, company_id, value);
+            let Some(heart_rate) = self.heart_rate_field.read(value) else {
+                tracing::debug!("[{}] Manufacturer data too short for the heart rate", addr);
+                continue;
+            };
+            if heart_rate == 0 {
+                // No heart rate in this advertisement
+                continue;
+            }
+            if !self.bpm_range.contains(heart_rate) {
+                tracing::debug!(
+                    "[{}] Dropping out-of-range heart rate {} (allowed {}-{})",
+                    addr,
+                    heart_rate,
+                    self.bpm_range.min,
+                    self.bpm_range.max
+                );
+                continue;
+            }
+
+            // save device address if not already set
+            if self.device_addr.is_none() {
+                self.device_addr = Some(addr.to_string());
+                tracing::info!("Detected Xiaomi Band at address: {}", addr);
+            }
+
+            tracing::info!("[{}] Received heart rate: {} bpm", addr, heart_rate);
+            return Some(heart_rate);
+        }
+        None
     }
 
     /// Stop monitoring
     pub async fn stop(&mut self) -> Result<()> {
         tracing::info!("Stopping Xiaomi Band monitor...");
-        self.running = false;
+        self.events = Mutex::new(None);
 
         // Stop scanning
         if let Err(e) = self.adapter.stop_scan().await {
@@ -185,3 +301,61 @@ impl XiaomiBandMonitor {
         Ok(())
     }
 }
+
+#[async_trait]
+impl HeartRateSource for XiaomiBandMonitor {
+    async fn next_sample(&mut self) -> Result<HeartRateSample> {
+        loop {
+            self.clean_stale_entries();
+            let heart_rate = match self.next_event_watched().await? {
+                // Once the band is known, only its manufacturer data is of interest
+                CentralEvent::ManufacturerDataAdvertisement {
+                    id,
+                    manufacturer_data,
+                } if self.device_addr.as_deref() == Some(id.to_string().as_str()) => {
+                    self.extract_bpm(&manufacturer_data, &id.to_string())
+                }
+                CentralEvent::DeviceUpdated(id) if self.device_addr.is_none() => {
+                    match self.adapter.peripheral(&id).await {
+                        Ok(peripheral) => self.handle_advertisement(&peripheral).await,
+                        Err(_) => None,
+                    }
+                }
+                _ => None,
+            };
+            if let Some(bpm) = heart_rate {
+                return Ok(HeartRateSample::new(MonitorMode::XiaomiBand, bpm));
+            }
+        }
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        self.stop().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_configured_heart_rate_field() {
+        let data = [0x01, 0x02, 0x03, 0x48, 0x00];
+        assert_eq!(HeartRateField::default().read(&data), Some(0x48));
+
+        let little_endian = HeartRateField {
+            offset: 3,
+            width: 2,
+            big_endian: false,
+        };
+        assert_eq!(little_endian.read(&data), Some(0x48));
+        let big_endian = HeartRateField {
+            big_endian: true,
+            ..little_endian
+        };
+        assert_eq!(big_endian.read(&data), Some(0x4800));
+
+        assert_eq!(HeartRateField::default().read(&data[..3]), None);
+        assert_eq!(big_endian.read(&data[..4]), None);
+    }
+}
@@ -0,0 +1,225 @@
+// Heart rate zones for HeartIO
+use std::time::{Duration, Instant};
+
+/// Max heart rate used when neither USER_MAX_HR nor USER_AGE is configured
+pub const DEFAULT_MAX_HR: u32 = 190;
+
+/// Share of max heart rate where FatBurn, Cardio, Peak and Max start
+const ZONE_PERCENTAGES: [f32; 4] = [0.6, 0.7, 0.8, 0.9];
+
+/// Training intensity zone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartRateZone {
+    Rest,
+    FatBurn,
+    Cardio,
+    Peak,
+    Max,
+}
+
+impl HeartRateZone {
+    const ALL: [HeartRateZone; 5] = [
+        HeartRateZone::Rest,
+        HeartRateZone::FatBurn,
+        HeartRateZone::Cardio,
+        HeartRateZone::Peak,
+        HeartRateZone::Max,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HeartRateZone::Rest => "Rest",
+            HeartRateZone::FatBurn => "Fat Burn",
+            HeartRateZone::Cardio => "Cardio",
+            HeartRateZone::Peak => "Peak",
+            HeartRateZone::Max => "Max",
+        }
+    }
+
+    /// Zone number starting at 0 for Rest
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// BPM boundaries between heart rate zones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZoneConfig {
+    /// Lowest BPM of FatBurn, Cardio, Peak and Max
+    pub thresholds: [u32; 4],
+}
+
+impl ZoneConfig {
+    /// Zones as fixed percentages of max heart rate
+    pub fn from_max_hr(max_hr: u32) -> Self {
+        Self {
+            thresholds: ZONE_PERCENTAGES.map(|percentage| (max_hr as f32 * percentage).round() as u32),
+        }
+    }
+
+    /// Zones as percentages of heart rate reserve (Karvonen), personalized by resting heart rate
+    pub fn from_karvonen(resting_hr: u32, max_hr: u32) -> Self {
+        let reserve = max_hr.saturating_sub(resting_hr) as f32;
+        Self {
+            thresholds: ZONE_PERCENTAGES
+                .map(|percentage| (resting_hr as f32 + percentage * reserve).round() as u32),
+        }
+    }
+
+    /// Get the zone for a heart rate
+    pub fn zone(&self, bpm: u32) -> HeartRateZone {
+        let index = self
+            .thresholds
+            .iter()
+            .take_while(|threshold| bpm >= **threshold)
+            .count();
+        HeartRateZone::ALL[index]
+    }
+}
+
+/// Reports zone crossings once the new zone has held for the debounce period,
+/// so a heart rate hovering on a boundary doesn't flip back and forth
+#[derive(Debug, Clone)]
+pub struct ZoneTracker {
+    current: Option<HeartRateZone>,
+    candidate: Option<(HeartRateZone, Instant)>,
+    debounce: Duration,
+}
+
+impl ZoneTracker {
+    pub fn new(debounce: Duration) -> Self {
+        Self {
+            current: None,
+            candidate: None,
+            debounce,
+        }
+    }
+
+    pub fn set_debounce(&mut self, debounce: Duration) {
+        self.debounce = debounce;
+    }
+
+    /// Feed the zone of a reading, returns `(from, to)` once a crossing is confirmed.
+    /// The first reading only sets the starting zone.
+    pub fn update(
+        &mut self,
+        zone: HeartRateZone,
+        now: Instant,
+    ) -> Option<(HeartRateZone, HeartRateZone)> {
+        let Some(current) = self.current else {
+            self.current = Some(zone);
+            return None;
+        };
+        if zone == current {
+            self.candidate = None;
+            return None;
+        }
+
+        let since = match self.candidate {
+            Some((candidate, since)) if candidate == zone => since,
+            _ => {
+                self.candidate = Some((zone, now));
+                now
+            }
+        };
+        if now.duration_since(since) < self.debounce {
+            return None;
+        }
+        self.current = Some(zone);
+        self.candidate = None;
+        Some((current, zone))
+    }
+}
+
+/// BPM below the alarm threshold a reading must fall to re-arm the alarm
+const ALARM_CLEAR_MARGIN: u32 = 5;
+
+/// Raises the max heart rate alarm once per crossing instead of on every reading above it
+#[derive(Debug, Clone, Default)]
+pub struct AlarmTracker {
+    active: bool,
+}
+
+impl AlarmTracker {
+    /// Feed a reading, returns `Some(true)` when the alarm goes off and `Some(false)` once
+    /// the heart rate is back below the threshold by a safe margin
+    pub fn update(&mut self, bpm: u32, threshold: u32) -> Option<bool> {
+        if !self.active && bpm > threshold {
+            self.active = true;
+            Some(true)
+        } else if self.active && bpm + ALARM_CLEAR_MARGIN <= threshold {
+            self.active = false;
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEBOUNCE: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn first_reading_sets_the_zone_silently() {
+        let mut tracker = ZoneTracker::new(DEBOUNCE);
+        assert_eq!(tracker.update(HeartRateZone::Cardio, Instant::now()), None);
+    }
+
+    #[test]
+    fn crossing_fires_after_the_debounce() {
+        let start = Instant::now();
+        let mut tracker = ZoneTracker::new(DEBOUNCE);
+        tracker.update(HeartRateZone::FatBurn, start);
+        assert_eq!(tracker.update(HeartRateZone::Cardio, start + Duration::from_secs(1)), None);
+        assert_eq!(
+            tracker.update(HeartRateZone::Cardio, start + Duration::from_secs(6)),
+            Some((HeartRateZone::FatBurn, HeartRateZone::Cardio))
+        );
+        assert_eq!(tracker.update(HeartRateZone::Cardio, start + Duration::from_secs(7)), None);
+    }
+
+    #[test]
+    fn hovering_on_a_boundary_is_ignored() {
+        let start = Instant::now();
+        let mut tracker = ZoneTracker::new(DEBOUNCE);
+        tracker.update(HeartRateZone::FatBurn, start);
+        for secs in 1..20 {
+            let zone = if secs % 2 == 0 { HeartRateZone::FatBurn } else { HeartRateZone::Cardio };
+            assert_eq!(tracker.update(zone, start + Duration::from_secs(secs)), None);
+        }
+    }
+
+    #[test]
+    fn zero_debounce_fires_immediately() {
+        let start = Instant::now();
+        let mut tracker = ZoneTracker::new(Duration::ZERO);
+        tracker.update(HeartRateZone::Rest, start);
+        assert_eq!(
+            tracker.update(HeartRateZone::Peak, start),
+            Some((HeartRateZone::Rest, HeartRateZone::Peak))
+        );
+    }
+
+    #[test]
+    fn alarm_fires_once_per_crossing() {
+        let mut alarm = AlarmTracker::default();
+        assert_eq!(alarm.update(170, 180), None);
+        assert_eq!(alarm.update(181, 180), Some(true));
+        assert_eq!(alarm.update(190, 180), None);
+        // Hovering just under the threshold doesn't re-arm it
+        assert_eq!(alarm.update(178, 180), None);
+        assert_eq!(alarm.update(182, 180), None);
+        assert_eq!(alarm.update(175, 180), Some(false));
+        assert_eq!(alarm.update(181, 180), Some(true));
+    }
+
+    #[test]
+    fn karvonen_zones_use_heart_rate_reserve() {
+        // Reserve of 130 BPM on top of a resting heart rate of 50
+        assert_eq!(ZoneConfig::from_karvonen(50, 180).thresholds, [128, 141, 154, 167]);
+        assert_eq!(ZoneConfig::from_karvonen(0, 180), ZoneConfig::from_max_hr(180));
+    }
+}